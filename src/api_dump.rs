@@ -0,0 +1,800 @@
+//! Index over the Roblox API dump, answering class, member and enum questions without Studio.
+//!
+//! The full `API-Dump.json` is read from `api_dump.path`, or downloaded from Roblox's setup CDN
+//! and cached. Without either, the reflection database bundled with `rbx_reflection_database`
+//! is used, which knows classes, properties and enums but not methods, events or security.
+
+use crate::config::ApiDumpConfig;
+use crate::http::HttpClient;
+use rbx_reflection::{ClassTag, DataType, PropertyTag, ReflectionDatabase, Scriptability};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+const SETUP_CDN: &str = "https://setup.rbxcdn.com";
+const CACHE_FILE_NAME: &str = "API-Dump.json";
+/// A downloaded dump older than this is replaced when the CDN is reachable.
+const MAX_CACHE_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Superclass of `Instance` and other root classes in the dump.
+const ROOT_SUPERCLASS: &str = "<<<ROOT>>>";
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiDump {
+    #[serde(default)]
+    pub classes: Vec<ApiClass>,
+    #[serde(default)]
+    pub enums: Vec<ApiEnum>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiClass {
+    pub name: String,
+    pub superclass: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_category: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Value>,
+    #[serde(default)]
+    pub members: Vec<ApiMember>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "MemberType")]
+pub enum ApiMember {
+    Property(ApiProperty),
+    Function(ApiFunction),
+    Event(ApiEvent),
+    Callback(ApiFunction),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiProperty {
+    pub name: String,
+    pub value_type: ApiType,
+    #[serde(default)]
+    pub security: Security,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_safety: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiFunction {
+    pub name: String,
+    #[serde(default)]
+    pub parameters: Vec<ApiParameter>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub return_type: Value,
+    #[serde(default)]
+    pub security: Security,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_safety: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiEvent {
+    pub name: String,
+    #[serde(default)]
+    pub parameters: Vec<ApiParameter>,
+    #[serde(default)]
+    pub security: Security,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_safety: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiParameter {
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub ty: ApiType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiType {
+    /// `Primitive`, `DataType`, `Enum`, `Class` or `Group`
+    pub category: String,
+    pub name: String,
+}
+
+/// Security of a member: one level for functions and events, separate read and write levels
+/// for properties.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Security {
+    Level(String),
+    #[serde(rename_all = "PascalCase")]
+    ReadWrite {
+        read: String,
+        write: String,
+    },
+}
+
+impl Default for Security {
+    fn default() -> Self {
+        Security::Level("None".to_string())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiEnum {
+    pub name: String,
+    #[serde(default)]
+    pub items: Vec<ApiEnumItem>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiEnumItem {
+    pub name: String,
+    pub value: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Value>,
+}
+
+impl ApiMember {
+    pub fn name(&self) -> &str {
+        match self {
+            ApiMember::Property(property) => &property.name,
+            ApiMember::Function(function) | ApiMember::Callback(function) => &function.name,
+            ApiMember::Event(event) => &event.name,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiMember::Property(_) => "Property",
+            ApiMember::Function(_) => "Function",
+            ApiMember::Event(_) => "Event",
+            ApiMember::Callback(_) => "Callback",
+        }
+    }
+
+    pub fn tags(&self) -> &[Value] {
+        match self {
+            ApiMember::Property(property) => &property.tags,
+            ApiMember::Function(function) | ApiMember::Callback(function) => &function.tags,
+            ApiMember::Event(event) => &event.tags,
+        }
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        has_tag(self.tags(), "Deprecated")
+    }
+}
+
+/// Whether `tags` contains the plain tag `name`. Structured tags such as
+/// `{"PreferredDescriptorName": ...}` are ignored.
+pub fn has_tag(tags: &[Value], name: &str) -> bool {
+    tags.iter().any(|tag| tag.as_str() == Some(name))
+}
+
+/// The replacement a deprecated member or class points to, if the dump names one.
+pub fn preferred_name(tags: &[Value]) -> Option<&str> {
+    tags.iter()
+        .find_map(|tag| tag.get("PreferredDescriptorName")?.as_str())
+}
+
+/// A class and one of its members, which may be inherited from a superclass.
+pub struct MemberRef<'a> {
+    pub class: &'a ApiClass,
+    pub member: &'a ApiMember,
+}
+
+pub struct ApiIndex {
+    /// Where the data came from, for tool output
+    pub source: String,
+    /// `false` for the bundled database, which has no methods, events or security levels
+    complete: bool,
+    classes: HashMap<String, ApiClass>,
+    enums: HashMap<String, ApiEnum>,
+    subclasses: HashMap<String, Vec<String>>,
+}
+
+impl ApiIndex {
+    pub fn new(dump: ApiDump, source: String, complete: bool) -> Self {
+        let mut subclasses: HashMap<String, Vec<String>> = HashMap::new();
+        for class in &dump.classes {
+            subclasses
+                .entry(class.superclass.clone())
+                .or_default()
+                .push(class.name.clone());
+        }
+        for names in subclasses.values_mut() {
+            names.sort();
+        }
+        Self {
+            source,
+            complete,
+            classes: dump
+                .classes
+                .into_iter()
+                .map(|class| (class.name.clone(), class))
+                .collect(),
+            enums: dump
+                .enums
+                .into_iter()
+                .map(|item| (item.name.clone(), item))
+                .collect(),
+            subclasses,
+        }
+    }
+
+    /// Loads the index from the configured dump, a cached download younger than a week, a
+    /// fresh download, an older cached download, or the bundled database, in that order.
+    pub async fn load(config: &ApiDumpConfig, cache_dir: Option<&Path>, http: &HttpClient) -> Self {
+        Self::load_from_cdn(config, cache_dir, http, SETUP_CDN).await
+    }
+
+    async fn load_from_cdn(
+        config: &ApiDumpConfig,
+        cache_dir: Option<&Path>,
+        http: &HttpClient,
+        cdn: &str,
+    ) -> Self {
+        if let Some(path) = &config.path {
+            match read_dump(path).await {
+                Ok(dump) => return Self::new(dump, path.display().to_string(), true),
+                Err(e) => tracing::warn!("Could not load API dump {}: {e}", path.display()),
+            }
+        }
+
+        let cached = cache_dir.map(|dir| dir.join(CACHE_FILE_NAME));
+        let cached_age = match &cached {
+            Some(path) => file_age(path).await,
+            None => None,
+        };
+        let fresh = cached_age.is_some_and(|age| age < MAX_CACHE_AGE);
+        if config.download && !fresh && !http.is_offline() {
+            match download_dump(http, cdn).await {
+                Ok((version, text)) => {
+                    if let Some(path) = &cached {
+                        if let Err(e) = write_cache(path, &text).await {
+                            tracing::warn!("Could not cache API dump at {}: {e}", path.display());
+                        }
+                    }
+                    match serde_json::from_str(&text) {
+                        Ok(dump) => return Self::new(dump, format!("{cdn} ({version})"), true),
+                        Err(e) => tracing::warn!("Downloaded API dump is invalid: {e}"),
+                    }
+                }
+                Err(e) => tracing::warn!("Could not download the API dump: {e}"),
+            }
+        }
+        if let (Some(path), Some(_)) = (&cached, cached_age) {
+            match read_dump(path).await {
+                Ok(dump) => return Self::new(dump, path.display().to_string(), true),
+                Err(e) => tracing::warn!("Could not load cached API dump {}: {e}", path.display()),
+            }
+        }
+
+        Self::bundled()
+    }
+
+    /// The index of the reflection database bundled with rbx-dom.
+    pub fn bundled() -> Self {
+        let database = rbx_reflection_database::get_bundled();
+        let version = database
+            .version
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        Self::new(
+            from_reflection(database),
+            format!("bundled reflection database {version} (properties and enums only)"),
+            false,
+        )
+    }
+
+    /// Note for tool output when the index is missing part of the API.
+    pub fn limitations(&self) -> Option<&'static str> {
+        (!self.complete).then_some(
+            "Only properties and enums are known: methods, events, callbacks and security levels need a full API dump (set api_dump.path, or allow the download)",
+        )
+    }
+
+    pub fn class(&self, name: &str) -> Option<&ApiClass> {
+        self.classes.get(name)
+    }
+
+    pub fn enum_(&self, name: &str) -> Option<&ApiEnum> {
+        self.enums.get(name)
+    }
+
+    /// "Unknown class" message, with the closest class name when there is one.
+    pub fn unknown_class(&self, name: &str) -> String {
+        let suggestion = closest(name, self.classes.keys().map(String::as_str));
+        did_you_mean(format!("Unknown class `{name}`"), suggestion)
+    }
+
+    /// "Unknown enum" message, with the closest enum name when there is one.
+    pub fn unknown_enum(&self, name: &str) -> String {
+        let suggestion = closest(name, self.enums.keys().map(String::as_str));
+        did_you_mean(format!("Unknown enum `{name}`"), suggestion)
+    }
+
+    /// "Unknown member" message, with the closest member of `class` when there is one.
+    pub fn unknown_member(&self, class: &str, name: &str) -> String {
+        let members = self.members(class);
+        let suggestion = closest(name, members.iter().map(|member| member.member.name()));
+        did_you_mean(format!("`{class}` has no member `{name}`"), suggestion)
+    }
+
+    /// `name` followed by its superclasses up to `Instance`.
+    pub fn inheritance(&self, name: &str) -> Vec<&ApiClass> {
+        let mut chain = Vec::new();
+        let mut current = self.classes.get(name);
+        while let Some(class) = current {
+            chain.push(class);
+            current = self.classes.get(&class.superclass);
+        }
+        chain
+    }
+
+    pub fn subclasses(&self, name: &str) -> &[String] {
+        self.subclasses.get(name).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The member `name` of `class` or the nearest superclass that declares it.
+    pub fn member(&self, class: &str, name: &str) -> Option<MemberRef<'_>> {
+        self.inheritance(class).into_iter().find_map(|class| {
+            class
+                .members
+                .iter()
+                .find(|member| member.name() == name)
+                .map(|member| MemberRef { class, member })
+        })
+    }
+
+    /// All members of `class`, its own first, then those of each superclass.
+    pub fn members(&self, class: &str) -> Vec<MemberRef<'_>> {
+        self.inheritance(class)
+            .into_iter()
+            .flat_map(|class| {
+                class
+                    .members
+                    .iter()
+                    .map(move |member| MemberRef { class, member })
+            })
+            .collect()
+    }
+
+    /// Class, enum and member names containing `query`, case-insensitively.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Value> {
+        let query = query.to_lowercase();
+        let mut classes: Vec<&ApiClass> = self
+            .classes
+            .values()
+            .filter(|class| class.name.to_lowercase().contains(&query))
+            .collect();
+        classes.sort_by_key(|class| (class.name.len(), class.name.as_str()));
+        let mut enums: Vec<&ApiEnum> = self
+            .enums
+            .values()
+            .filter(|item| item.name.to_lowercase().contains(&query))
+            .collect();
+        enums.sort_by_key(|item| (item.name.len(), item.name.as_str()));
+        let mut members: Vec<(&ApiClass, &ApiMember)> = self
+            .classes
+            .values()
+            .flat_map(|class| class.members.iter().map(move |member| (class, member)))
+            .filter(|(_, member)| member.name().to_lowercase().contains(&query))
+            .collect();
+        members.sort_by_key(|(class, member)| {
+            (member.name().len(), member.name(), class.name.as_str())
+        });
+
+        let classes = classes
+            .into_iter()
+            .map(|class| serde_json::json!({ "Kind": "Class", "Name": class.name }));
+        let enums = enums
+            .into_iter()
+            .map(|item| serde_json::json!({ "Kind": "Enum", "Name": item.name }));
+        let members = members.into_iter().map(|(class, member)| {
+            serde_json::json!({ "Kind": member.kind(), "Class": class.name, "Name": member.name() })
+        });
+        classes.chain(enums).chain(members).take(limit).collect()
+    }
+}
+
+fn did_you_mean(message: String, suggestion: Option<&str>) -> String {
+    match suggestion {
+        Some(suggestion) => format!("{message}. Did you mean `{suggestion}`?"),
+        None => message,
+    }
+}
+
+async fn read_dump(path: &Path) -> color_eyre::Result<ApiDump> {
+    let text = tokio::fs::read_to_string(path).await?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+async fn file_age(path: &Path) -> Option<Duration> {
+    let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+    Some(modified.elapsed().unwrap_or_default())
+}
+
+async fn write_cache(path: &Path, text: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, text).await
+}
+
+/// Fetches the API dump of the current Studio release from `cdn`. Returns the version and the dump.
+async fn download_dump(http: &HttpClient, cdn: &str) -> color_eyre::Result<(String, String)> {
+    let version_url = format!("{cdn}/versionQTStudio");
+    let version = http.send(http.get(&version_url)).await?.text().await?;
+    let version = version.trim().to_string();
+    let dump_url = format!("{cdn}/{version}-API-Dump.json");
+    let text = http.send(http.get(&dump_url)).await?.text().await?;
+    tracing::info!("Downloaded API dump for {version}");
+    Ok((version, text))
+}
+
+/// Converts the bundled reflection database to the API dump format. Properties get their
+/// scriptability as `ReadOnly`/`NotScriptable` tags; security levels are unknown.
+fn from_reflection(database: &ReflectionDatabase) -> ApiDump {
+    let mut classes: Vec<ApiClass> = database
+        .classes
+        .values()
+        .map(|class| {
+            let mut members: Vec<ApiMember> = class
+                .properties
+                .values()
+                .map(|property| {
+                    let mut tags: Vec<Value> = property
+                        .tags
+                        .iter()
+                        .map(|tag| Value::String(property_tag_name(tag).to_string()))
+                        .collect();
+                    match property.scriptability {
+                        Scriptability::Read => tags.push("ReadOnly".into()),
+                        Scriptability::None => tags.push("NotScriptable".into()),
+                        _ => {}
+                    }
+                    tags.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+                    tags.dedup();
+                    ApiMember::Property(ApiProperty {
+                        name: property.name.to_string(),
+                        value_type: value_type(&property.data_type),
+                        security: Security::default(),
+                        category: None,
+                        thread_safety: None,
+                        tags,
+                    })
+                })
+                .collect();
+            members.sort_by(|a, b| a.name().cmp(b.name()));
+            let mut tags: Vec<Value> = class
+                .tags
+                .iter()
+                .map(|tag| Value::String(class_tag_name(tag).to_string()))
+                .collect();
+            tags.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+            ApiClass {
+                name: class.name.to_string(),
+                superclass: class.superclass.unwrap_or(ROOT_SUPERCLASS).to_string(),
+                memory_category: None,
+                tags,
+                members,
+            }
+        })
+        .collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut enums: Vec<ApiEnum> = database
+        .enums
+        .values()
+        .map(|descriptor| {
+            let mut items: Vec<ApiEnumItem> = descriptor
+                .items
+                .iter()
+                .map(|(name, value)| ApiEnumItem {
+                    name: name.to_string(),
+                    value: i64::from(*value),
+                    tags: Vec::new(),
+                })
+                .collect();
+            items.sort_by_key(|item| item.value);
+            ApiEnum {
+                name: descriptor.name.to_string(),
+                items,
+                tags: Vec::new(),
+            }
+        })
+        .collect();
+    enums.sort_by(|a, b| a.name.cmp(&b.name));
+    ApiDump { classes, enums }
+}
+
+fn value_type(data_type: &DataType) -> ApiType {
+    use rbx_reflection::DataType::{Enum, Value as Type};
+    use rbx_types::VariantType;
+
+    let (category, name) = match data_type {
+        Enum(name) => ("Enum", name.to_string()),
+        Type(VariantType::Bool) => ("Primitive", "bool".to_string()),
+        Type(VariantType::Int32) => ("Primitive", "int".to_string()),
+        Type(VariantType::Int64) => ("Primitive", "int64".to_string()),
+        Type(VariantType::Float32) => ("Primitive", "float".to_string()),
+        Type(VariantType::Float64) => ("Primitive", "double".to_string()),
+        Type(VariantType::String) => ("Primitive", "string".to_string()),
+        Type(VariantType::Ref) => ("Class", "Instance".to_string()),
+        Type(other) => ("DataType", format!("{other:?}")),
+        _ => ("DataType", "Unknown".to_string()),
+    };
+    ApiType {
+        category: category.to_string(),
+        name,
+    }
+}
+
+fn class_tag_name(tag: &ClassTag) -> &'static str {
+    match tag {
+        ClassTag::Deprecated => "Deprecated",
+        ClassTag::NotBrowsable => "NotBrowsable",
+        ClassTag::NotCreatable => "NotCreatable",
+        ClassTag::NotReplicated => "NotReplicated",
+        ClassTag::PlayerReplicated => "PlayerReplicated",
+        ClassTag::Service => "Service",
+        ClassTag::Settings => "Settings",
+        ClassTag::UserSettings => "UserSettings",
+        _ => "Unknown",
+    }
+}
+
+fn property_tag_name(tag: &PropertyTag) -> &'static str {
+    match tag {
+        PropertyTag::Deprecated => "Deprecated",
+        PropertyTag::Hidden => "Hidden",
+        PropertyTag::NotBrowsable => "NotBrowsable",
+        PropertyTag::NotReplicated => "NotReplicated",
+        PropertyTag::NotScriptable => "NotScriptable",
+        PropertyTag::ReadOnly => "ReadOnly",
+        PropertyTag::WriteOnly => "WriteOnly",
+        _ => "Unknown",
+    }
+}
+
+/// The candidate closest to `target` by edit distance, ignoring case, if it is close enough to
+/// be a plausible typo.
+pub fn closest<'a>(target: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let target = target.to_lowercase();
+    let max_distance = (target.chars().count() / 3).max(2);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&target, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, candidate)| (*distance, candidate.len(), *candidate))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance counting a swap of neighbouring letters as one edit, the most common typo.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j - 1] + cost)
+                .min(rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use axum::routing::get;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A dump with a single class, so each test can tell where the index came from.
+    fn dump(class: &str) -> String {
+        serde_json::json!({
+            "Classes": [{ "Name": class, "Superclass": ROOT_SUPERCLASS, "Members": [] }],
+            "Enums": [],
+        })
+        .to_string()
+    }
+
+    /// Stands in for the setup CDN. Returns its URL and how many requests it has answered.
+    async fn cdn(working: bool) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().fallback(get(move |uri: axum::http::Uri| async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            match (working, uri.path()) {
+                (false, _) => Err(axum::http::StatusCode::SERVICE_UNAVAILABLE),
+                (true, "/versionQTStudio") => Ok("version-abc\n".to_string()),
+                (true, "/version-abc-API-Dump.json") => Ok(dump("Downloaded")),
+                (true, _) => Err(axum::http::StatusCode::NOT_FOUND),
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, hits)
+    }
+
+    fn http(offline: bool) -> HttpClient {
+        let mut config = Config {
+            offline,
+            ..Default::default()
+        };
+        config.http.max_retries = 0;
+        HttpClient::new(&config).unwrap()
+    }
+
+    struct Setup {
+        dir: PathBuf,
+        config: ApiDumpConfig,
+    }
+
+    impl Setup {
+        /// A cache directory holding a cached dump of `age`, if any.
+        fn new(cached_age: Option<Duration>) -> Self {
+            let dir = std::env::temp_dir().join(format!("api-dump-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            if let Some(age) = cached_age {
+                let path = dir.join(CACHE_FILE_NAME);
+                std::fs::write(&path, dump("Cached")).unwrap();
+                let file = std::fs::File::options().write(true).open(&path).unwrap();
+                file.set_modified(std::time::SystemTime::now() - age)
+                    .unwrap();
+            }
+            Self {
+                dir,
+                config: ApiDumpConfig::default(),
+            }
+        }
+
+        async fn load(&self, cdn: &str, http: &HttpClient) -> ApiIndex {
+            ApiIndex::load_from_cdn(&self.config, Some(&self.dir), http, cdn).await
+        }
+    }
+
+    impl Drop for Setup {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[tokio::test]
+    async fn configured_path_comes_first() {
+        let (url, hits) = cdn(true).await;
+        let mut setup = Setup::new(Some(DAY));
+        let path = setup.dir.join("custom.json");
+        std::fs::write(&path, dump("FromPath")).unwrap();
+        setup.config.path = Some(path.clone());
+
+        let index = setup.load(&url, &http(false)).await;
+        assert!(index.class("FromPath").is_some());
+        assert_eq!(index.source, path.display().to_string());
+        assert!(index.limitations().is_none());
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        // A path that can't be read falls through to the cache
+        setup.config.path = Some(setup.dir.join("missing.json"));
+        assert!(setup
+            .load(&url, &http(false))
+            .await
+            .class("Cached")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn fresh_cache_comes_before_the_download() {
+        let (url, hits) = cdn(true).await;
+        let setup = Setup::new(Some(DAY));
+        let index = setup.load(&url, &http(false)).await;
+        assert!(index.class("Cached").is_some());
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn downloads_replace_an_old_cache() {
+        let (url, _) = cdn(true).await;
+        let setup = Setup::new(Some(8 * DAY));
+        let index = setup.load(&url, &http(false)).await;
+        assert!(index.class("Downloaded").is_some());
+        assert_eq!(index.source, format!("{url} (version-abc)"));
+        let cached = std::fs::read_to_string(setup.dir.join(CACHE_FILE_NAME)).unwrap();
+        assert_eq!(cached, dump("Downloaded"));
+    }
+
+    #[tokio::test]
+    async fn old_cache_is_used_when_the_download_fails() {
+        let (url, hits) = cdn(false).await;
+        let setup = Setup::new(Some(8 * DAY));
+        assert!(setup
+            .load(&url, &http(false))
+            .await
+            .class("Cached")
+            .is_some());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Offline and with downloads turned off nothing is requested at all
+        let (url, hits) = cdn(true).await;
+        assert!(setup
+            .load(&url, &http(true))
+            .await
+            .class("Cached")
+            .is_some());
+        let mut no_download = Setup::new(Some(8 * DAY));
+        no_download.config.download = false;
+        assert!(no_download
+            .load(&url, &http(false))
+            .await
+            .class("Cached")
+            .is_some());
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn bundled_database_comes_last() {
+        let (url, _) = cdn(false).await;
+        let setup = Setup::new(None);
+        let index = setup.load(&url, &http(false)).await;
+        assert!(index.class("Part").is_some());
+        assert!(index.source.starts_with("bundled reflection database"));
+        assert!(index.limitations().is_some());
+    }
+
+    #[test]
+    fn closest_finds_plausible_typos() {
+        let classes = ["Part", "Model", "MeshPart", "Script", "LocalScript"];
+        assert_eq!(closest("part", classes), Some("Part"));
+        assert_eq!(closest("Prat", classes), Some("Part"));
+        assert_eq!(closest("LocalScritp", classes), Some("LocalScript"));
+        assert_eq!(closest("Humanoid", classes), None);
+        assert_eq!(closest("Part", []), None);
+    }
+
+    #[test]
+    fn closest_breaks_ties_the_same_way_every_time() {
+        // All one edit away from "Cart"
+        let candidates = ["Dart", "Carts", "Bart", "Part"];
+        assert_eq!(closest("Cart", candidates), Some("Bart"));
+        let mut reversed = candidates;
+        reversed.reverse();
+        assert_eq!(closest("Cart", reversed), Some("Bart"));
+    }
+}
@@ -0,0 +1,174 @@
+//! What `get_asset_details` reports about an asset, assembled from the economy API's product
+//! details, the catalog's favorite count and the develop API's archive flag.
+
+use serde::{Deserialize, Serialize};
+
+/// Name and description the economy API gives assets removed by moderation
+const CONTENT_DELETED: &str = "[ Content Deleted ]";
+
+/// An `economy.roblox.com/v2/assets/{id}/details` response, the fields used here.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EconomyDetails {
+    pub asset_id: u64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub asset_type_id: Option<u32>,
+    pub creator: Option<EconomyCreator>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub price_in_robux: Option<u64>,
+    #[serde(default)]
+    pub is_for_sale: bool,
+    #[serde(default)]
+    pub is_public_domain: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EconomyCreator {
+    pub name: String,
+    pub creator_type: Option<String>,
+    pub creator_target_id: Option<u64>,
+    #[serde(default)]
+    pub has_verified_badge: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssetDetails {
+    pub asset_id: u64,
+    pub name: String,
+    pub description: String,
+    /// Roblox asset type, e.g. `Model` or `Audio`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_type_id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<Creator>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    /// Robux; `None` when it isn't sold for Robux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<u64>,
+    pub for_sale: bool,
+    /// Free to take, as Creator Store models and audio are
+    pub free: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favorites: Option<u64>,
+    /// Removed by moderation; the asset can't be inserted
+    pub moderated: bool,
+    /// Archived by its creator; `None` when the develop API wouldn't say
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Creator {
+    pub name: String,
+    /// `User` or `Group`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub verified: bool,
+}
+
+impl AssetDetails {
+    pub fn new(details: EconomyDetails, favorites: Option<u64>, archived: Option<bool>) -> Self {
+        let description = details.description.unwrap_or_default();
+        Self {
+            asset_id: details.asset_id,
+            moderated: details.name == CONTENT_DELETED || description == CONTENT_DELETED,
+            name: details.name,
+            description,
+            asset_type: details
+                .asset_type_id
+                .and_then(asset_type_name)
+                .map(str::to_string),
+            asset_type_id: details.asset_type_id,
+            creator: details.creator.map(|creator| Creator {
+                name: creator.name,
+                kind: creator.creator_type,
+                id: creator.creator_target_id,
+                verified: creator.has_verified_badge,
+            }),
+            created: details.created,
+            updated: details.updated,
+            price: details.price_in_robux,
+            for_sale: details.is_for_sale,
+            free: details.is_public_domain || details.price_in_robux == Some(0),
+            favorites,
+            archived,
+        }
+    }
+}
+
+/// Name of a Roblox `AssetType` enum value.
+pub fn asset_type_name(id: u32) -> Option<&'static str> {
+    Some(match id {
+        1 => "Image",
+        2 => "TShirt",
+        3 => "Audio",
+        4 => "Mesh",
+        5 => "Lua",
+        8 => "Hat",
+        9 => "Place",
+        10 => "Model",
+        11 => "Shirt",
+        12 => "Pants",
+        13 => "Decal",
+        17 => "Head",
+        18 => "Face",
+        19 => "Gear",
+        21 => "Badge",
+        24 => "Animation",
+        27 => "Torso",
+        28 => "RightArm",
+        29 => "LeftArm",
+        30 => "LeftLeg",
+        31 => "RightLeg",
+        32 => "Package",
+        34 => "GamePass",
+        38 => "Plugin",
+        40 => "MeshPart",
+        41 => "HairAccessory",
+        42 => "FaceAccessory",
+        43 => "NeckAccessory",
+        44 => "ShoulderAccessory",
+        45 => "FrontAccessory",
+        46 => "BackAccessory",
+        47 => "WaistAccessory",
+        48 => "ClimbAnimation",
+        49 => "DeathAnimation",
+        50 => "FallAnimation",
+        51 => "IdleAnimation",
+        52 => "JumpAnimation",
+        53 => "RunAnimation",
+        54 => "SwimAnimation",
+        55 => "WalkAnimation",
+        56 => "PoseAnimation",
+        59 => "LocalizationTableManifest",
+        61 => "EmoteAnimation",
+        62 => "Video",
+        64 => "TShirtAccessory",
+        65 => "ShirtAccessory",
+        66 => "PantsAccessory",
+        67 => "JacketAccessory",
+        68 => "SweaterAccessory",
+        69 => "ShortsAccessory",
+        70 => "LeftShoeAccessory",
+        71 => "RightShoeAccessory",
+        72 => "DressSkirtAccessory",
+        73 => "FontFamily",
+        76 => "EyebrowAccessory",
+        77 => "EyelashAccessory",
+        78 => "MoodAnimation",
+        79 => "DynamicHead",
+        _ => return None,
+    })
+}
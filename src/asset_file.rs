@@ -0,0 +1,600 @@
+//! Reads Roblox model and place files (`.rbxm`, `.rbxmx`, `.rbxl`, `.rbxlx`) so an asset can
+//! be judged before it is inserted into the place: what it contains, which scripts it runs and
+//! how heavy it is to render.
+
+use crate::api_dump::ApiIndex;
+use crate::instance_path;
+use crate::scanner::ScanReport;
+use rbx_dom_weak::types::{Ref, Variant};
+use rbx_dom_weak::{Instance, WeakDom};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Deepest level of the instance tree an inspection lists, so a hostile file can't exhaust
+/// the stack
+const MAX_TREE_DEPTH: usize = 100;
+
+/// What a downloaded asset file holds, going by its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    BinaryModel,
+    XmlModel,
+    Ogg,
+    Png,
+    Mesh,
+    Unknown,
+}
+
+impl ContentKind {
+    pub fn sniff(bytes: &[u8]) -> Self {
+        let text = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        if bytes.starts_with(b"<roblox!") {
+            Self::BinaryModel
+        } else if text.trim_ascii_start().starts_with(b"<roblox") {
+            Self::XmlModel
+        } else if bytes.starts_with(b"OggS") {
+            Self::Ogg
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Self::Png
+        } else if bytes.starts_with(b"version ") {
+            Self::Mesh
+        } else {
+            Self::Unknown
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::BinaryModel => "rbxm",
+            Self::XmlModel => "rbxmx",
+            Self::Ogg => "ogg",
+            Self::Png => "png",
+            Self::Mesh => "mesh",
+            Self::Unknown => "bin",
+        }
+    }
+
+    /// The Roblox asset type files of this kind belong to.
+    pub fn asset_type(self) -> Option<&'static str> {
+        match self {
+            Self::BinaryModel | Self::XmlModel => Some("Model"),
+            Self::Ogg => Some("Audio"),
+            Self::Png => Some("Image"),
+            Self::Mesh => Some("Mesh"),
+            Self::Unknown => None,
+        }
+    }
+}
+
+/// A decoded model or place file.
+pub struct AssetFile {
+    format: &'static str,
+    dom: WeakDom,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InspectOptions {
+    /// Include each script's source, not just its size
+    pub include_sources: bool,
+    /// Levels of the instance tree to list, up to [`MAX_TREE_DEPTH`]; deeper instances are
+    /// only counted
+    pub tree_depth: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssetReport {
+    /// `binary` or `xml`
+    pub format: &'static str,
+    pub instance_count: usize,
+    pub part_count: usize,
+    pub triangles: TriangleCount,
+    /// Number of instances of each class, by `ClassName`
+    pub class_counts: BTreeMap<String, usize>,
+    /// Backdoor scan of the scripts, filled in by the caller
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan: Option<ScanReport>,
+    pub scripts: Vec<ScriptInfo>,
+    pub tree: Vec<TreeNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TriangleCount {
+    /// Triangles of the parts whose geometry is known, at full detail
+    pub total: u64,
+    /// Parts left out of `total`: balls, cylinders, unions, trusses and meshes that could not
+    /// be read
+    pub uncounted_parts: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScriptInfo {
+    /// Path from the top of the file, e.g. `Sword.Handle.Damage`
+    pub path: String,
+    pub class: String,
+    pub lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// A script and where it sits, as the scanner sees it.
+#[derive(Debug)]
+pub struct ScriptSource<'a> {
+    /// Path from the top of the file, e.g. `Sword.Handle.Damage`
+    pub path: String,
+    pub name: &'a str,
+    pub class: &'a str,
+    /// `None` for scripts at the top of the file
+    pub parent_class: Option<&'a str>,
+    pub source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub class: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+    /// Descendants left out below `tree_depth` or [`MAX_TREE_DEPTH`]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub hidden_descendants: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// What a part renders as.
+enum Geometry {
+    Triangles(u64),
+    /// A mesh asset, whose triangles are in the mesh file
+    Mesh(u64),
+    Unknown,
+}
+
+impl AssetFile {
+    /// Decodes `bytes`, telling the binary and XML formats apart by their header.
+    pub fn read(bytes: &[u8]) -> Result<Self, String> {
+        match ContentKind::sniff(bytes) {
+            ContentKind::BinaryModel => {
+                let dom = rbx_binary::from_reader(bytes)
+                    .map_err(|e| format!("Could not decode the binary file: {e}"))?;
+                return Ok(Self {
+                    format: "binary",
+                    dom,
+                });
+            }
+            ContentKind::XmlModel => {
+                let text = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+                let dom = rbx_xml::from_reader_default(text)
+                    .map_err(|e| format!("Could not decode the XML file: {e}"))?;
+                return Ok(Self { format: "xml", dom });
+            }
+            _ => {}
+        }
+        let kind = match ContentKind::sniff(bytes).asset_type() {
+            Some(asset_type) => format!("a file of type {asset_type}"),
+            None => "of an unknown type".to_string(),
+        };
+        Err(format!(
+            "Not a Roblox model or place file: it is {kind}, {} bytes long",
+            bytes.len()
+        ))
+    }
+
+    /// The file's top-level instances in the binary format, which
+    /// `SerializationService:DeserializeInstancesAsync` reads in Studio.
+    pub fn to_binary(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        rbx_binary::to_writer(&mut bytes, &self.dom, self.dom.root().children())
+            .map_err(|e| format!("Could not encode the model: {e}"))?;
+        Ok(bytes)
+    }
+
+    /// Asset ids of the meshes parts render, for counting their triangles.
+    pub fn mesh_ids(&self, api: &ApiIndex) -> BTreeSet<u64> {
+        self.instances()
+            .filter_map(|instance| match self.geometry(api, instance) {
+                Geometry::Mesh(id) => Some(id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Summarizes the file. `mesh_triangles` holds the triangle counts of the meshes from
+    /// [`Self::mesh_ids`] that could be read.
+    pub fn inspect(
+        &self,
+        api: &ApiIndex,
+        mesh_triangles: &HashMap<u64, u64>,
+        options: &InspectOptions,
+    ) -> AssetReport {
+        let mut class_counts = BTreeMap::new();
+        let mut part_count = 0;
+        let mut triangles = TriangleCount {
+            total: 0,
+            uncounted_parts: 0,
+        };
+        for instance in self.instances() {
+            *class_counts.entry(instance.class.to_string()).or_insert(0) += 1;
+            if is_a(api, &instance.class, "BasePart") {
+                part_count += 1;
+                let count = match self.geometry(api, instance) {
+                    Geometry::Triangles(count) => Some(count),
+                    Geometry::Mesh(id) => mesh_triangles.get(&id).copied(),
+                    Geometry::Unknown => None,
+                };
+                match count {
+                    Some(count) => triangles.total += count,
+                    None => triangles.uncounted_parts += 1,
+                }
+            }
+        }
+        let scripts = self
+            .scripts(api)
+            .into_iter()
+            .map(|script| ScriptInfo {
+                path: script.path,
+                class: script.class.to_string(),
+                lines: script.source.lines().count(),
+                source: options.include_sources.then_some(script.source),
+            })
+            .collect();
+        let root = self.dom.root();
+        let max_depth = options
+            .tree_depth
+            .map_or(MAX_TREE_DEPTH, |depth| depth.min(MAX_TREE_DEPTH));
+        AssetReport {
+            format: self.format,
+            instance_count: class_counts.values().sum(),
+            part_count,
+            triangles,
+            class_counts,
+            scan: None,
+            scripts,
+            tree: root
+                .children()
+                .iter()
+                .filter_map(|child| self.tree_node(*child, 1, max_depth))
+                .collect(),
+        }
+    }
+
+    /// Every script in the file, in tree order.
+    pub fn scripts(&self, api: &ApiIndex) -> Vec<ScriptSource<'_>> {
+        self.instances()
+            .filter(|instance| is_a(api, &instance.class, "LuaSourceContainer"))
+            .map(|instance| ScriptSource {
+                path: self.path_of(instance),
+                name: &instance.name,
+                class: &instance.class,
+                parent_class: self
+                    .dom
+                    .get_by_ref(instance.parent())
+                    .filter(|parent| parent.referent() != self.dom.root_ref())
+                    .map(|parent| parent.class.as_str()),
+                source: string_property(instance, "Source").unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Every instance in the file, leaving out the root that holds them.
+    fn instances(&self) -> impl Iterator<Item = &Instance> {
+        self.dom.descendants().skip(1)
+    }
+
+    fn tree_node(&self, referent: Ref, depth: usize, max_depth: usize) -> Option<TreeNode> {
+        let instance = self.dom.get_by_ref(referent)?;
+        let (children, hidden_descendants) = if depth < max_depth {
+            let children = instance
+                .children()
+                .iter()
+                .filter_map(|child| self.tree_node(*child, depth + 1, max_depth))
+                .collect();
+            (children, 0)
+        } else {
+            (Vec::new(), self.dom.descendants_of(referent).count() - 1)
+        };
+        Some(TreeNode {
+            name: instance.name.clone(),
+            class: instance.class.to_string(),
+            children,
+            hidden_descendants,
+        })
+    }
+
+    /// Dot path from the top of the file, quoting names as instance paths do.
+    fn path_of(&self, instance: &Instance) -> String {
+        let mut names = vec![instance_path::format_name(&instance.name)];
+        let mut parent = instance.parent();
+        while let Some(ancestor) = self.dom.get_by_ref(parent) {
+            if ancestor.referent() == self.dom.root_ref() {
+                break;
+            }
+            names.push(instance_path::format_name(&ancestor.name));
+            parent = ancestor.parent();
+        }
+        names.reverse();
+        names.join(".")
+    }
+
+    fn geometry(&self, api: &ApiIndex, part: &Instance) -> Geometry {
+        if !is_a(api, &part.class, "BasePart") {
+            return Geometry::Unknown;
+        }
+        // A SpecialMesh with a mesh file replaces the part's own shape
+        let special_mesh = part
+            .children()
+            .iter()
+            .filter_map(|child| self.dom.get_by_ref(*child))
+            .find(|child| child.class == "SpecialMesh");
+        if let Some(mesh) = special_mesh {
+            if enum_property(mesh, "MeshType") == Some(FILE_MESH) {
+                return mesh_geometry(mesh, &["MeshId"]);
+            }
+        }
+        match part.class.as_str() {
+            "MeshPart" => mesh_geometry(part, &["MeshContent", "MeshId"]),
+            "WedgePart" => Geometry::Triangles(WEDGE_TRIANGLES),
+            "CornerWedgePart" => Geometry::Triangles(CORNER_WEDGE_TRIANGLES),
+            class if is_a(api, class, "Part") => {
+                let shape = enum_property(part, "Shape").or_else(|| enum_property(part, "shape"));
+                match shape.unwrap_or(BLOCK) {
+                    BLOCK => Geometry::Triangles(BLOCK_TRIANGLES),
+                    WEDGE => Geometry::Triangles(WEDGE_TRIANGLES),
+                    CORNER_WEDGE => Geometry::Triangles(CORNER_WEDGE_TRIANGLES),
+                    _ => Geometry::Unknown,
+                }
+            }
+            _ => Geometry::Unknown,
+        }
+    }
+}
+
+/// `Enum.PartType` values
+const BLOCK: u32 = 1;
+const WEDGE: u32 = 3;
+const CORNER_WEDGE: u32 = 4;
+/// `Enum.MeshType.FileMesh`
+const FILE_MESH: u32 = 5;
+
+const BLOCK_TRIANGLES: u64 = 12;
+const WEDGE_TRIANGLES: u64 = 8;
+const CORNER_WEDGE_TRIANGLES: u64 = 6;
+
+/// Whether `class` is `base` or inherits from it. Classes missing from the index only match
+/// themselves.
+pub fn is_a(api: &ApiIndex, class: &str, base: &str) -> bool {
+    class == base
+        || api
+            .inheritance(class)
+            .iter()
+            .any(|class| class.name == base)
+}
+
+fn mesh_geometry(instance: &Instance, properties: &[&str]) -> Geometry {
+    properties
+        .iter()
+        .filter_map(|property| string_property(instance, property))
+        .find_map(|uri| content_asset_id(&uri))
+        .map_or(Geometry::Unknown, Geometry::Mesh)
+}
+
+fn string_property(instance: &Instance, name: &str) -> Option<String> {
+    match instance.properties.get(&rbx_dom_weak::ustr(name))? {
+        Variant::String(text) => Some(text.clone()),
+        Variant::BinaryString(bytes) => Some(String::from_utf8_lossy(bytes.as_ref()).into_owned()),
+        Variant::ContentId(content) => Some(content.as_str().to_string()),
+        Variant::Content(content) => content.as_uri().map(str::to_string),
+        _ => None,
+    }
+}
+
+fn enum_property(instance: &Instance, name: &str) -> Option<u32> {
+    match instance.properties.get(&rbx_dom_weak::ustr(name))? {
+        Variant::Enum(value) => Some(value.to_u32()),
+        _ => None,
+    }
+}
+
+/// The asset id in a content URI such as `rbxassetid://123` or
+/// `http://www.roblox.com/asset/?id=123`.
+pub fn content_asset_id(uri: &str) -> Option<u64> {
+    let uri = uri.trim();
+    let digits = match uri.strip_prefix("rbxassetid://") {
+        Some(rest) => rest,
+        None => &uri[uri.to_ascii_lowercase().find("id=")? + 3..],
+    };
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    digits[..end].parse().ok()
+}
+
+/// Triangles in the most detailed level of a Roblox mesh file, read from its header. `None`
+/// for formats whose count isn't stored plainly, such as Draco-compressed meshes.
+pub fn mesh_triangles(bytes: &[u8]) -> Option<u64> {
+    let newline = bytes.iter().position(|&b| b == b'\n')?;
+    let version = std::str::from_utf8(&bytes[..newline]).ok()?.trim();
+    let major = version.strip_prefix("version ")?.split('.').next()?;
+    let body = &bytes[newline + 1..];
+    match major {
+        "1" => {
+            let text = String::from_utf8_lossy(body);
+            text.lines().next()?.trim().parse().ok()
+        }
+        "2" => u32_at(body, 8).map(u64::from),
+        "3" => {
+            let header = usize::from(u16_at(body, 0)?);
+            let vertex_size = usize::from(*body.get(2)?);
+            let face_size = usize::from(*body.get(3)?);
+            let lods = usize::from(u16_at(body, 6)?);
+            let vertices = u32_at(body, 8)? as usize;
+            let faces = u32_at(body, 12)?;
+            let lod_offsets = header + vertices * vertex_size + faces as usize * face_size;
+            Some(first_lod(body, lod_offsets, lods, faces))
+        }
+        "4" | "5" => {
+            let header = usize::from(u16_at(body, 0)?);
+            let vertices = u32_at(body, 4)? as usize;
+            let faces = u32_at(body, 8)?;
+            let lods = usize::from(u16_at(body, 12)?);
+            let bones = u16_at(body, 14)?;
+            let envelopes = if bones > 0 { vertices * 8 } else { 0 };
+            let lod_offsets = header + vertices * 40 + envelopes + faces as usize * 12;
+            Some(first_lod(body, lod_offsets, lods, faces))
+        }
+        _ => chunked_mesh_triangles(body),
+    }
+}
+
+/// Meshes from version 6 on are a list of chunks: an 8-byte name, a version and a size.
+fn chunked_mesh_triangles(mut body: &[u8]) -> Option<u64> {
+    let mut faces = None;
+    let mut lod_faces = None;
+    while body.len() >= 16 {
+        let name = &body[..8];
+        let version = u32_at(body, 8)?;
+        let size = u32_at(body, 12)? as usize;
+        let data = body.get(16..16 + size)?;
+        match (name, version) {
+            (b"COREMESH", 1) => {
+                let vertices = u32_at(data, 0)? as usize;
+                faces = Some(u32_at(data, 4 + vertices * 40)?);
+            }
+            (b"LODS\0\0\0\0", 1) => {
+                let count = u32_at(data, 3)?;
+                if count >= 2 {
+                    lod_faces = u32_at(data, 11);
+                }
+            }
+            _ => {}
+        }
+        body = &body[16 + size..];
+    }
+    let faces = faces?;
+    Some(u64::from(
+        lod_faces.filter(|lod| *lod <= faces).unwrap_or(faces),
+    ))
+}
+
+/// Faces of the first level of detail: up to the second LOD offset when there is one.
+fn first_lod(body: &[u8], lod_offsets: usize, lods: usize, faces: u32) -> u64 {
+    let lod_faces = if lods >= 2 {
+        u32_at(body, lod_offsets + 4)
+    } else {
+        None
+    };
+    u64::from(lod_faces.filter(|lod| *lod <= faces).unwrap_or(faces))
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    #[test]
+    fn deep_tree_is_cut_off() {
+        let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+        let mut parent = dom.root_ref();
+        for _ in 0..10_000 {
+            parent = dom.insert(parent, InstanceBuilder::new("Folder"));
+        }
+        let file = AssetFile {
+            format: "binary",
+            dom,
+        };
+        let report = file.inspect(
+            &ApiIndex::bundled(),
+            &HashMap::new(),
+            &InspectOptions::default(),
+        );
+        assert_eq!(report.instance_count, 10_000);
+        let mut node = &report.tree[0];
+        let mut depth = 1;
+        while let Some(child) = node.children.first() {
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_TREE_DEPTH);
+        assert_eq!(node.hidden_descendants, 10_000 - MAX_TREE_DEPTH);
+    }
+
+    #[test]
+    fn sniffs_file_kinds() {
+        let cases: [(&[u8], ContentKind); 7] = [
+            (b"<roblox!\x89\xff\r\n", ContentKind::BinaryModel),
+            (
+                b"\xEF\xBB\xBF  <roblox version=\"4\">",
+                ContentKind::XmlModel,
+            ),
+            (b"OggS\0\x02", ContentKind::Ogg),
+            (b"\x89PNG\r\n\x1a\n\0", ContentKind::Png),
+            (b"version 4.00\n", ContentKind::Mesh),
+            (b"{\"errors\": []}", ContentKind::Unknown),
+            (b"", ContentKind::Unknown),
+        ];
+        for (bytes, kind) in cases {
+            assert_eq!(ContentKind::sniff(bytes), kind, "{bytes:?}");
+        }
+        assert_eq!(ContentKind::XmlModel.extension(), "rbxmx");
+        assert_eq!(ContentKind::Unknown.asset_type(), None);
+    }
+
+    #[test]
+    fn unknown_files_are_not_echoed() {
+        let error = AssetFile::read(b"secret=hunter2").err().unwrap();
+        assert_eq!(
+            error,
+            "Not a Roblox model or place file: it is of an unknown type, 14 bytes long"
+        );
+        let error = AssetFile::read(b"OggS secret").err().unwrap();
+        assert!(error.contains("a file of type Audio"), "{error}");
+    }
+
+    fn mesh(version: &str, body: &[u8]) -> Vec<u8> {
+        let mut bytes = format!("version {version}\n").into_bytes();
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn mesh_triangle_counts() {
+        assert_eq!(mesh_triangles(&mesh("1.00", b"3\n[0,0,0]")), Some(3));
+
+        let mut v2 = vec![12, 0, 40, 12];
+        v2.extend(0u32.to_le_bytes());
+        v2.extend(9u32.to_le_bytes());
+        assert_eq!(mesh_triangles(&mesh("2.00", &v2)), Some(9));
+
+        // Two levels of detail: the first covers 4 of the 10 faces
+        let mut v3 = vec![16, 0, 40, 12, 4, 0, 2, 0];
+        v3.extend(0u32.to_le_bytes());
+        v3.extend(10u32.to_le_bytes());
+        v3.extend([0; 120]);
+        v3.extend(0u32.to_le_bytes());
+        v3.extend(4u32.to_le_bytes());
+        assert_eq!(mesh_triangles(&mesh("3.00", &v3)), Some(4));
+
+        let mut v6 = b"COREMESH".to_vec();
+        v6.extend(1u32.to_le_bytes());
+        v6.extend(8u32.to_le_bytes());
+        v6.extend(0u32.to_le_bytes());
+        v6.extend(7u32.to_le_bytes());
+        assert_eq!(mesh_triangles(&mesh("6.00", &v6)), Some(7));
+
+        assert_eq!(mesh_triangles(&mesh("2.00", &[12, 0])), None);
+        assert_eq!(mesh_triangles(b"not a mesh"), None);
+    }
+}
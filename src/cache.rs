@@ -0,0 +1,520 @@
+use crate::config::Config;
+use crate::http::HttpError;
+use crate::util::{self, now_secs};
+use rmcp::model::Content;
+use rmcp::{schemars, ErrorData};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Groups of cached responses, each stored in its own directory so they can be cleared
+/// separately.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheNamespace {
+    /// `search_creator_store` results
+    CreatorStore,
+    /// `search_marketplace` results
+    Marketplace,
+    /// `get_asset_details` results
+    AssetDetails,
+}
+
+impl CacheNamespace {
+    pub const ALL: [CacheNamespace; 3] = [
+        CacheNamespace::CreatorStore,
+        CacheNamespace::Marketplace,
+        CacheNamespace::AssetDetails,
+    ];
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            CacheNamespace::CreatorStore => "creator_store",
+            CacheNamespace::Marketplace => "marketplace",
+            CacheNamespace::AssetDetails => "asset_details",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheMode {
+    /// Serve a fresh cached response if there is one, otherwise fetch and store
+    #[default]
+    Default,
+    /// Always fetch and overwrite the cached response
+    Refresh,
+    /// Fetch without reading or writing the cache
+    Bypass,
+    /// Never touch the network; serve whatever is cached, however old
+    OnlyIfCached,
+}
+
+/// Per-call cache options shared by the cached web tools. These are handled server-side and
+/// are not part of the cache key.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
+pub struct CacheControl {
+    #[schemars(
+        description = "Cache behaviour: \"default\", \"refresh\" (fetch and update), \"bypass\" (fetch, don't store) or \"only_if_cached\" (never go online)"
+    )]
+    #[serde(default, skip_serializing)]
+    cache: Option<CacheMode>,
+    #[schemars(
+        description = "Oldest cached response to accept, in seconds. Defaults to the configured TTL"
+    )]
+    #[serde(default, skip_serializing)]
+    max_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    /// Fetched from the API
+    Miss,
+    /// Served from a cached response within its TTL
+    Hit,
+    /// Served from an expired cached response because the API request failed
+    Stale,
+}
+
+/// A response body together with where it came from.
+pub struct Cached {
+    pub body: String,
+    status: CacheStatus,
+    age_secs: u64,
+    error: Option<String>,
+}
+
+impl Cached {
+    fn fetched(body: String) -> Self {
+        Self {
+            body,
+            status: CacheStatus::Miss,
+            age_secs: 0,
+            error: None,
+        }
+    }
+
+    /// `[cache] {...}` note for the tool result, for responses that did not come straight from
+    /// the API.
+    pub fn notice(&self) -> Option<Content> {
+        if self.status == CacheStatus::Miss {
+            return None;
+        }
+        let mut info = serde_json::json!({ "status": self.status, "age_secs": self.age_secs });
+        if let Some(error) = &self.error {
+            info["error"] = Value::String(error.clone());
+        }
+        Some(Content::text(format!("[cache] {info}")))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    stored_at: u64,
+    request: Value,
+    body: String,
+}
+
+/// TTL cache of web API responses on disk, keyed on a hash of the normalized request.
+#[derive(Clone)]
+pub struct ResponseCache {
+    /// `None` when caching is disabled
+    dir: Option<PathBuf>,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            dir: config.cache.enabled.then(|| config.cache_dir()).flatten(),
+            ttl: Duration::from_secs(config.cache.ttl_secs),
+        }
+    }
+
+    /// Serves `request` from the cache according to `control`, calling `fetch` when there is no
+    /// usable entry. If `fetch` fails, an expired entry is served instead of the error.
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        namespace: CacheNamespace,
+        request: Value,
+        control: &CacheControl,
+        fetch: F,
+    ) -> Result<Cached, ErrorData>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<String, HttpError>>,
+    {
+        let mode = control.cache.unwrap_or_default();
+        let Some(path) = self.entry_path(namespace, &request) else {
+            if mode == CacheMode::OnlyIfCached {
+                return Err(ErrorData::invalid_request(
+                    "The response cache is disabled",
+                    None,
+                ));
+            }
+            return Ok(Cached::fetched(fetch().await?));
+        };
+        let max_age = control
+            .max_age_secs
+            .map(Duration::from_secs)
+            .unwrap_or(self.ttl);
+
+        let cached = match mode {
+            CacheMode::Default | CacheMode::OnlyIfCached => read_entry(&path).await,
+            CacheMode::Refresh | CacheMode::Bypass => None,
+        };
+        if let Some(entry) = &cached {
+            let age_secs = age_secs(entry.stored_at);
+            if mode == CacheMode::OnlyIfCached || age_secs <= max_age.as_secs() {
+                return Ok(Cached {
+                    body: entry.body.clone(),
+                    status: CacheStatus::Hit,
+                    age_secs,
+                    error: None,
+                });
+            }
+        } else if mode == CacheMode::OnlyIfCached {
+            return Err(ErrorData::resource_not_found(
+                "No cached response for this request",
+                Some(request),
+            ));
+        }
+
+        match fetch().await {
+            Ok(body) => {
+                if mode != CacheMode::Bypass {
+                    let entry = Entry {
+                        stored_at: now_secs(),
+                        request,
+                        body,
+                    };
+                    if let Err(e) = write_entry(&path, &entry).await {
+                        tracing::warn!("Could not write cache entry {}: {e}", path.display());
+                    }
+                    return Ok(Cached::fetched(entry.body));
+                }
+                Ok(Cached::fetched(body))
+            }
+            Err(e) => {
+                // Refresh skipped the read above, so look again before giving up
+                let stale = match (cached, mode) {
+                    (Some(entry), _) => Some(entry),
+                    (None, CacheMode::Refresh) => read_entry(&path).await,
+                    (None, _) => None,
+                };
+                match stale {
+                    Some(entry) => {
+                        tracing::warn!("Serving stale cached response: {e}");
+                        Ok(Cached {
+                            age_secs: age_secs(entry.stored_at),
+                            body: entry.body,
+                            status: CacheStatus::Stale,
+                            error: Some(e.to_string()),
+                        })
+                    }
+                    None => Err(e.into()),
+                }
+            }
+        }
+    }
+
+    /// Removes the cached responses of `namespace`, or all of them. Returns how many were
+    /// removed.
+    pub async fn clear(&self, namespace: Option<CacheNamespace>) -> std::io::Result<usize> {
+        let Some(dir) = &self.dir else {
+            return Ok(0);
+        };
+        let namespaces = match namespace {
+            Some(namespace) => vec![namespace],
+            None => CacheNamespace::ALL.to_vec(),
+        };
+        let mut removed = 0;
+        for namespace in namespaces {
+            let mut entries = match tokio::fs::read_dir(dir.join(namespace.dir_name())).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.path().extension().is_some_and(|ext| ext == "json") {
+                    tokio::fs::remove_file(entry.path()).await?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+
+    fn entry_path(&self, namespace: CacheNamespace, request: &Value) -> Option<PathBuf> {
+        // serde_json maps are sorted, so equal requests always hash the same
+        let name = util::sha256_hex(request.to_string().as_bytes());
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(namespace.dir_name()).join(format!("{name}.json")))
+    }
+}
+
+/// Lowercases `query` and collapses its whitespace, so trivially different searches share a
+/// cache entry.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+async fn read_entry(path: &Path) -> Option<Entry> {
+    let text = tokio::fs::read_to_string(path).await.ok()?;
+    match serde_json::from_str(&text) {
+        Ok(entry) => Some(entry),
+        Err(e) => {
+            tracing::debug!("Ignoring corrupt cache entry {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+async fn write_entry(path: &Path, entry: &Entry) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // Write then rename so a concurrent reader never sees a partial entry
+    let tmp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+    tokio::fs::write(&tmp, serde_json::to_vec(entry)?).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+fn age_secs(stored_at: u64) -> u64 {
+    now_secs().saturating_sub(stored_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const NS: CacheNamespace = CacheNamespace::Marketplace;
+
+    fn cache() -> ResponseCache {
+        ResponseCache {
+            dir: Some(std::env::temp_dir().join(format!("cache-test-{}", uuid::Uuid::new_v4()))),
+            ttl: Duration::from_secs(60),
+        }
+    }
+
+    fn control(mode: CacheMode) -> CacheControl {
+        CacheControl {
+            cache: Some(mode),
+            max_age_secs: None,
+        }
+    }
+
+    fn request() -> Value {
+        serde_json::json!({ "keyword": "tree" })
+    }
+
+    /// Fetches `body`, counting each call.
+    async fn fetch_ok(
+        cache: &ResponseCache,
+        control: &CacheControl,
+        calls: &AtomicUsize,
+        body: &str,
+    ) -> Result<Cached, ErrorData> {
+        cache
+            .get_or_fetch(NS, request(), control, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(body.to_string())
+            })
+            .await
+    }
+
+    /// Fails with a 503, counting each call.
+    async fn fetch_err(
+        cache: &ResponseCache,
+        control: &CacheControl,
+        calls: &AtomicUsize,
+    ) -> Result<Cached, ErrorData> {
+        cache
+            .get_or_fetch(NS, request(), control, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(HttpError::Status {
+                    url: "https://example.com".into(),
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    body: String::new(),
+                })
+            })
+            .await
+    }
+
+    /// Moves the stored entry `secs` into the past.
+    async fn backdate(cache: &ResponseCache, secs: u64) {
+        let path = cache.entry_path(NS, &request()).unwrap();
+        let mut entry = read_entry(&path).await.unwrap();
+        entry.stored_at -= secs;
+        write_entry(&path, &entry).await.unwrap();
+    }
+
+    fn cleanup(cache: &ResponseCache) {
+        std::fs::remove_dir_all(cache.dir.as_ref().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fresh_entries_are_served_until_the_ttl() {
+        let cache = cache();
+        let calls = AtomicUsize::new(0);
+        let default = CacheControl::default();
+
+        let first = fetch_ok(&cache, &default, &calls, "one").await.unwrap();
+        assert_eq!(
+            (first.body.as_str(), first.status),
+            ("one", CacheStatus::Miss)
+        );
+        assert!(first.notice().is_none());
+
+        let second = fetch_ok(&cache, &default, &calls, "two").await.unwrap();
+        assert_eq!(
+            (second.body.as_str(), second.status),
+            ("one", CacheStatus::Hit)
+        );
+        assert!(second.notice().is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        backdate(&cache, 61).await;
+        let third = fetch_ok(&cache, &default, &calls, "three").await.unwrap();
+        assert_eq!(
+            (third.body.as_str(), third.status),
+            ("three", CacheStatus::Miss)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // A tighter max_age turns a fresh entry into an expired one
+        backdate(&cache, 10).await;
+        let tight = CacheControl {
+            cache: None,
+            max_age_secs: Some(5),
+        };
+        let fourth = fetch_ok(&cache, &tight, &calls, "four").await.unwrap();
+        assert_eq!(fourth.status, CacheStatus::Miss);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        cleanup(&cache);
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_served_when_fetching_fails() {
+        let cache = cache();
+        let calls = AtomicUsize::new(0);
+        let default = CacheControl::default();
+
+        assert!(fetch_err(&cache, &default, &calls).await.is_err());
+
+        fetch_ok(&cache, &default, &calls, "one").await.unwrap();
+        backdate(&cache, 120).await;
+        let stale = fetch_err(&cache, &default, &calls).await.unwrap();
+        assert_eq!(
+            (stale.body.as_str(), stale.status),
+            ("one", CacheStatus::Stale)
+        );
+        assert!(stale.age_secs >= 120);
+        assert!(stale.error.unwrap().contains("503"));
+
+        // Refresh skips the cache, but still falls back to it
+        let refreshed = fetch_err(&cache, &control(CacheMode::Refresh), &calls)
+            .await
+            .unwrap();
+        assert_eq!(refreshed.status, CacheStatus::Stale);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        cleanup(&cache);
+    }
+
+    #[tokio::test]
+    async fn cache_modes() {
+        let cache = cache();
+        let calls = AtomicUsize::new(0);
+        let only_cached = control(CacheMode::OnlyIfCached);
+
+        assert!(fetch_ok(&cache, &only_cached, &calls, "never")
+            .await
+            .is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        // Bypass fetches without storing
+        let bypassed = fetch_ok(&cache, &control(CacheMode::Bypass), &calls, "one")
+            .await
+            .unwrap();
+        assert_eq!(bypassed.status, CacheStatus::Miss);
+        assert!(fetch_ok(&cache, &only_cached, &calls, "never")
+            .await
+            .is_err());
+
+        fetch_ok(&cache, &CacheControl::default(), &calls, "two")
+            .await
+            .unwrap();
+        // Refresh fetches even though the entry is fresh, and stores the new body
+        let refreshed = fetch_ok(&cache, &control(CacheMode::Refresh), &calls, "three")
+            .await
+            .unwrap();
+        assert_eq!(refreshed.status, CacheStatus::Miss);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // OnlyIfCached serves any entry, however old
+        backdate(&cache, 10_000).await;
+        let old = fetch_ok(&cache, &only_cached, &calls, "never")
+            .await
+            .unwrap();
+        assert_eq!((old.body.as_str(), old.status), ("three", CacheStatus::Hit));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        cleanup(&cache);
+    }
+
+    #[tokio::test]
+    async fn disabled_cache_always_fetches() {
+        let cache = ResponseCache {
+            dir: None,
+            ttl: Duration::from_secs(60),
+        };
+        let calls = AtomicUsize::new(0);
+        for _ in 0..2 {
+            let fetched = fetch_ok(&cache, &CacheControl::default(), &calls, "one")
+                .await
+                .unwrap();
+            assert_eq!(fetched.status, CacheStatus::Miss);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(
+            fetch_ok(&cache, &control(CacheMode::OnlyIfCached), &calls, "one")
+                .await
+                .is_err()
+        );
+        assert_eq!(cache.clear(None).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn clear_removes_one_namespace_or_all() {
+        let cache = cache();
+        for namespace in CacheNamespace::ALL {
+            for keyword in ["a", "b"] {
+                let request = serde_json::json!({ "keyword": keyword });
+                cache
+                    .get_or_fetch(namespace, request, &CacheControl::default(), || async {
+                        Ok("body".to_string())
+                    })
+                    .await
+                    .unwrap();
+            }
+        }
+        assert_eq!(cache.clear(Some(NS)).await.unwrap(), 2);
+        assert_eq!(cache.clear(Some(NS)).await.unwrap(), 0);
+        assert_eq!(cache.clear(None).await.unwrap(), 4);
+        cleanup(&cache);
+    }
+
+    #[test]
+    fn queries_are_normalized() {
+        assert_eq!(normalize_query("  Red   Car\tModel "), "red car model");
+    }
+}
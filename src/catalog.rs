@@ -0,0 +1,287 @@
+//! The catalog search API behind `search_marketplace`: asset type categories, the request URL
+//! and a compact form of the results.
+
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+const SEARCH_URL: &str = "https://catalog.roblox.com/v1/search/items/details";
+/// Page sizes the catalog API accepts; other values are rejected with a 400
+const PAGE_SIZES: [u32; 3] = [10, 28, 30];
+/// Longest description kept in a compact result, in characters
+const DESCRIPTION_LIMIT: usize = 200;
+
+/// Catalog search categories, numbered as the API's `Category` parameter.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, Default, PartialEq)]
+pub enum Category {
+    All,
+    #[serde(alias = "Collectibles")]
+    Collectible,
+    Clothing,
+    #[serde(alias = "BodyParts")]
+    BodyPart,
+    Gear,
+    #[default]
+    #[serde(alias = "Models")]
+    Model,
+    #[serde(alias = "Plugins")]
+    Plugin,
+    #[serde(alias = "Decals")]
+    Decal,
+    Audio,
+    #[serde(alias = "Meshes")]
+    Mesh,
+    #[serde(alias = "Accessories")]
+    Accessory,
+    #[serde(alias = "Animations", alias = "AvatarAnimation")]
+    Animation,
+    #[serde(alias = "CommunityCreations")]
+    CommunityCreation,
+    #[serde(alias = "Videos")]
+    Video,
+}
+
+impl Category {
+    pub fn id(self) -> u32 {
+        match self {
+            Category::All => 1,
+            Category::Collectible => 2,
+            Category::Clothing => 3,
+            Category::BodyPart => 4,
+            Category::Gear => 5,
+            Category::Model => 6,
+            Category::Plugin => 7,
+            Category::Decal => 8,
+            Category::Audio => 9,
+            Category::Mesh => 10,
+            Category::Accessory => 11,
+            Category::Animation => 12,
+            Category::CommunityCreation => 13,
+            Category::Video => 14,
+        }
+    }
+}
+
+/// The smallest page size the API accepts that holds `limit` results.
+pub fn page_size(limit: u32) -> u32 {
+    PAGE_SIZES
+        .into_iter()
+        .find(|&size| size >= limit)
+        .unwrap_or(PAGE_SIZES[PAGE_SIZES.len() - 1])
+}
+
+pub fn search_url(query: &str, category: Category, page_size: u32, cursor: Option<&str>) -> String {
+    let mut url = format!(
+        "{SEARCH_URL}?Keyword={}&Category={}&Limit={page_size}",
+        urlencoding::encode(query),
+        category.id()
+    );
+    if let Some(cursor) = cursor {
+        url.push_str(&format!("&Cursor={}", urlencoding::encode(cursor)));
+    }
+    url
+}
+
+/// A `search/items/details` response. Fields the API may leave out default to empty.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResponse {
+    #[serde(default)]
+    pub data: Vec<Item>,
+    pub next_page_cursor: Option<String>,
+    /// Set instead of `data` when the API refuses the search
+    #[serde(default)]
+    pub errors: Vec<ApiError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiError {
+    #[serde(default)]
+    pub code: i64,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    pub id: u64,
+    #[serde(default)]
+    pub item_type: String,
+    pub asset_type: Option<u32>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub creator_name: Option<String>,
+    pub creator_type: Option<String>,
+    pub creator_target_id: Option<u64>,
+    #[serde(default)]
+    pub creator_has_verified_badge: bool,
+    pub price: Option<u64>,
+    pub lowest_price: Option<u64>,
+    pub price_status: Option<String>,
+    pub favorite_count: Option<u64>,
+}
+
+/// What `search_marketplace` reports for each item.
+#[derive(Debug, Serialize)]
+pub struct ItemSummary {
+    pub id: u64,
+    /// `Asset` or `Bundle`
+    pub item_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_type_id: Option<u32>,
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<Creator>,
+    /// Robux, or `None` when it can't be bought (see `price_status`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favorites: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Creator {
+    pub name: String,
+    /// `User` or `Group`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub verified: bool,
+}
+
+impl From<Item> for ItemSummary {
+    fn from(item: Item) -> Self {
+        let mut description = item.description.trim().to_string();
+        if let Some((cut, _)) = description.char_indices().nth(DESCRIPTION_LIMIT) {
+            description.truncate(cut);
+            description.push('…');
+        }
+        Self {
+            id: item.id,
+            item_type: item.item_type,
+            asset_type_id: item.asset_type,
+            name: item.name,
+            description,
+            creator: item.creator_name.map(|name| Creator {
+                name,
+                kind: item.creator_type,
+                id: item.creator_target_id,
+                verified: item.creator_has_verified_badge,
+            }),
+            price: item.price.or(item.lowest_price),
+            price_status: item.price_status,
+            favorites: item.favorite_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn page_size_rounds_up_to_an_accepted_size() {
+        assert_eq!(page_size(0), 10);
+        assert_eq!(page_size(10), 10);
+        assert_eq!(page_size(11), 28);
+        assert_eq!(page_size(29), 30);
+        assert_eq!(page_size(100), 30);
+    }
+
+    #[test]
+    fn search_urls() {
+        assert_eq!(
+            search_url("red car", Category::Model, 10, None),
+            format!("{SEARCH_URL}?Keyword=red%20car&Category=6&Limit=10")
+        );
+        assert_eq!(
+            search_url("sword&shield=1", Category::Gear, 30, Some("2_1_a/b+c=")),
+            format!("{SEARCH_URL}?Keyword=sword%26shield%3D1&Category=5&Limit=30&Cursor=2_1_a%2Fb%2Bc%3D")
+        );
+    }
+
+    #[test]
+    fn category_aliases() {
+        let category = |name: &str| serde_json::from_value::<Category>(json!(name)).unwrap();
+        assert_eq!(category("Models"), Category::Model);
+        assert_eq!(category("Meshes"), Category::Mesh);
+        assert_eq!(category("AvatarAnimation"), Category::Animation);
+        assert_eq!(category("Animations"), Category::Animation);
+        assert_eq!(category("Accessory"), Category::Accessory);
+        assert!(serde_json::from_value::<Category>(json!("models")).is_err());
+        assert_eq!(Category::default().id(), 6);
+        assert_eq!(Category::Video.id(), 14);
+    }
+
+    #[test]
+    fn responses_become_summaries() {
+        let text = std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/catalog_search.json"
+        ))
+        .unwrap();
+        let response: SearchResponse = serde_json::from_str(&text).unwrap();
+        assert_eq!(
+            response.next_page_cursor.as_deref(),
+            Some("2_1_a1b2c3d4e5f6")
+        );
+        assert!(response.errors.is_empty());
+
+        let summaries: Vec<ItemSummary> = response.data.into_iter().map(Into::into).collect();
+        let json = serde_json::to_value(&summaries).unwrap();
+        assert_eq!(
+            json[0],
+            json!({
+                "id": 125378389,
+                "item_type": "Asset",
+                "asset_type_id": 19,
+                "name": "Classic Sword",
+                "description": "The original sword.",
+                "creator": { "name": "Roblox", "kind": "User", "id": 1, "verified": true },
+                "price": 250,
+                "favorites": 48213,
+            })
+        );
+
+        // Bundles only have a lowest price, and long descriptions are cut between characters
+        let bundle = &summaries[1];
+        assert_eq!(bundle.price, Some(95));
+        assert_eq!(bundle.asset_type_id, None);
+        assert_eq!(bundle.description.chars().count(), DESCRIPTION_LIMIT + 1);
+        assert!(bundle.description.ends_with("armure…"));
+        assert_eq!(
+            bundle.creator.as_ref().unwrap().kind.as_deref(),
+            Some("Group")
+        );
+
+        assert_eq!(
+            json[2],
+            json!({
+                "id": 7,
+                "item_type": "Asset",
+                "asset_type_id": 19,
+                "name": "Off Sale Blade",
+                "price_status": "Off Sale",
+            })
+        );
+    }
+
+    #[test]
+    fn refused_searches_carry_errors() {
+        let response: SearchResponse = serde_json::from_value(json!({
+            "errors": [{ "code": 0, "message": "Invalid limit" }]
+        }))
+        .unwrap();
+        assert!(response.data.is_empty());
+        assert_eq!(response.next_page_cursor, None);
+        assert_eq!(response.errors[0].message, "Invalid limit");
+    }
+}
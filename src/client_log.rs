@@ -0,0 +1,220 @@
+//! Forwards this crate's tracing events to the MCP client as `notifications/message`, at the
+//! level the client picks with `logging/setLevel`.
+
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use rmcp::service::Peer;
+use rmcp::RoleServer;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// Only events from this crate are forwarded, so rmcp's own tracing of the notifications we
+/// send can't feed back into the layer.
+const FORWARDED_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// Minimum level forwarded to the client, as chosen through `logging/setLevel`.
+#[derive(Clone)]
+pub struct ClientLogLevel(Arc<AtomicU8>);
+
+impl ClientLogLevel {
+    pub fn set(&self, level: LoggingLevel) {
+        self.0.store(level_rank(level), Ordering::Relaxed);
+    }
+
+    fn allows(&self, level: LoggingLevel) -> bool {
+        level_rank(level) >= self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracing layer that queues this crate's events as MCP `notifications/message`.
+pub struct ClientLogLayer {
+    level: ClientLogLevel,
+    tx: mpsc::UnboundedSender<LoggingMessageNotificationParam>,
+}
+
+/// Receiving half of [`ClientLogLayer`], drained into the connected client by [`forward`](Self::forward).
+pub struct ClientLogForwarder {
+    level: ClientLogLevel,
+    rx: mpsc::UnboundedReceiver<LoggingMessageNotificationParam>,
+}
+
+pub fn layer() -> (ClientLogLayer, ClientLogForwarder) {
+    let level = ClientLogLevel(Arc::new(AtomicU8::new(level_rank(LoggingLevel::Info))));
+    let (tx, rx) = mpsc::unbounded_channel();
+    (
+        ClientLogLayer {
+            level: level.clone(),
+            tx,
+        },
+        ClientLogForwarder { level, rx },
+    )
+}
+
+impl ClientLogForwarder {
+    pub fn level(&self) -> ClientLogLevel {
+        self.level.clone()
+    }
+
+    pub async fn forward(mut self, peer: Peer<RoleServer>) {
+        while let Some(message) = self.rx.recv().await {
+            if peer.notify_logging_message(message).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for ClientLogLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if !metadata.target().starts_with(FORWARDED_TARGET) {
+            return;
+        }
+        let level = match *metadata.level() {
+            tracing::Level::ERROR => LoggingLevel::Error,
+            tracing::Level::WARN => LoggingLevel::Warning,
+            tracing::Level::INFO => LoggingLevel::Info,
+            tracing::Level::DEBUG | tracing::Level::TRACE => LoggingLevel::Debug,
+        };
+        if !self.level.allows(level) {
+            return;
+        }
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        _ = self.tx.send(LoggingMessageNotificationParam {
+            level,
+            logger: Some(metadata.target().to_string()),
+            data: serde_json::Value::Object(fields.0),
+        });
+    }
+}
+
+#[derive(Default)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            serde_json::Value::String(format!("{value:?}")),
+        );
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(
+            field.name().to_string(),
+            serde_json::Value::String(value.to_string()),
+        );
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+fn level_rank(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Runs `log` with only the client log layer installed and returns what reached the channel.
+    fn forwarded(
+        level: Option<LoggingLevel>,
+        log: impl FnOnce(),
+    ) -> Vec<LoggingMessageNotificationParam> {
+        let (layer, mut forwarder) = layer();
+        if let Some(level) = level {
+            forwarder.level().set(level);
+        }
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), log);
+        let mut messages = Vec::new();
+        while let Ok(message) = forwarder.rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[test]
+    fn only_this_crate_is_forwarded() {
+        let messages = forwarded(None, || {
+            tracing::info!(target: "rmcp::service", "sent notification");
+            tracing::info!(tool = "run_command", elapsed_ms = 12, "Tool finished");
+        });
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].level, LoggingLevel::Info);
+        assert!(messages[0]
+            .logger
+            .as_deref()
+            .unwrap()
+            .starts_with(FORWARDED_TARGET));
+        assert_eq!(
+            messages[0].data,
+            serde_json::json!({ "message": "Tool finished", "tool": "run_command", "elapsed_ms": 12 })
+        );
+    }
+
+    #[test]
+    fn events_below_the_level_are_dropped() {
+        let log = || {
+            tracing::trace!("trace");
+            tracing::debug!("debug");
+            tracing::info!("info");
+            tracing::warn!("warn");
+            tracing::error!("error");
+        };
+        let levels = |level| {
+            forwarded(level, log)
+                .into_iter()
+                .map(|message| message.level)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            levels(None),
+            [
+                LoggingLevel::Info,
+                LoggingLevel::Warning,
+                LoggingLevel::Error
+            ]
+        );
+        assert_eq!(
+            levels(Some(LoggingLevel::Debug)),
+            [
+                LoggingLevel::Debug,
+                LoggingLevel::Debug,
+                LoggingLevel::Info,
+                LoggingLevel::Warning,
+                LoggingLevel::Error
+            ]
+        );
+        // Notice sits between info and warning
+        assert_eq!(
+            levels(Some(LoggingLevel::Notice)),
+            [LoggingLevel::Warning, LoggingLevel::Error]
+        );
+        assert!(levels(Some(LoggingLevel::Critical)).is_empty());
+    }
+}
@@ -0,0 +1,534 @@
+use crate::rbx_studio_server::STUDIO_PLUGIN_PORT;
+use color_eyre::eyre::{Result, WrapErr};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CONFIG_DIR_NAME: &str = "rbx-studio-mcp";
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// Web tools without an offline fallback. `search_creator_store`, `search_marketplace`,
+/// `get_asset_details` and `download_asset` stay available offline and serve cached responses
+/// and downloaded files.
+const ONLINE_ONLY_TOOLS: [&str; 4] = [
+    "search_web_scripts",
+    "tavily_extract",
+    "fetch_url_content",
+    "install_system",
+];
+
+/// Server configuration, read from `config.toml` and then overridden by env vars and CLI flags.
+///
+/// ```toml
+/// port = 44755
+/// offline = false
+/// asset_dir = "~/RobloxAssets"
+///
+/// [api_keys]
+/// tavily = "tvly-..."
+///
+/// [tools]
+/// disabled = ["run_command"]
+///
+/// [timeouts]
+/// http_secs = 30
+/// tool_secs = 120
+///
+/// [http]
+/// proxy = "http://proxy.internal:3128"
+/// max_retries = 3
+///
+/// [cache]
+/// ttl_secs = 3600
+///
+/// [api_dump]
+/// path = "~/roblox/API-Dump.json"
+/// validate_writes = true
+///
+/// [scanner]
+/// block = "malicious"
+///
+/// [web_search]
+/// provider = "searxng"
+/// url = "https://searx.internal"
+///
+/// [fetch]
+/// denied_domains = ["example.com"]
+/// max_bytes = 2000000
+///
+/// [policies.get_project_structure]
+/// max_tokens = 4000
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Port the Studio plugin connects to
+    pub port: u16,
+    /// Never go online. Web tools are hidden, searches are answered from the response cache.
+    pub offline: bool,
+    /// Asset library directory: downloaded assets and their index, `library.json`
+    pub asset_dir: PathBuf,
+    pub api_keys: ApiKeys,
+    pub tools: ToolsConfig,
+    pub timeouts: Timeouts,
+    pub http: HttpConfig,
+    pub cache: CacheConfig,
+    pub fetch: FetchConfig,
+    pub web_search: WebSearchConfig,
+    pub api_dump: ApiDumpConfig,
+    pub scanner: ScannerConfig,
+    /// Per-tool overrides, keyed by tool name
+    pub policies: HashMap<String, ToolPolicy>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiKeys {
+    pub tavily: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsConfig {
+    /// When set, only these tools are exposed
+    pub enabled: Option<Vec<String>>,
+    /// Tools that are never exposed, applied after `enabled`
+    pub disabled: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    /// Timeout of a single outgoing web request
+    pub http_secs: u64,
+    /// How long a tool call may run before it is abandoned. Unlimited when unset.
+    pub tool_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Proxy URL used for all outgoing web requests
+    pub proxy: Option<String>,
+    /// Defaults to `rbx-studio-mcp/<version>`
+    pub user_agent: Option<String>,
+    /// Retries of timed out, rate limited or 5xx requests
+    pub max_retries: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Cache catalog and Creator Store responses on disk
+    pub enabled: bool,
+    /// Defaults to the platform cache directory
+    pub dir: Option<PathBuf>,
+    /// How long a cached response is served before it is fetched again
+    pub ttl_secs: u64,
+}
+
+/// Where the Roblox API dump used for class and member lookups comes from.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiDumpConfig {
+    /// An `API-Dump.json` on disk, used instead of downloading one
+    pub path: Option<PathBuf>,
+    /// Download the dump of the current Studio release and keep it in the cache directory
+    pub download: bool,
+    /// Check property names and values of `set_property`, `mass_set_property` and
+    /// `create_object` against the dump before they reach Studio
+    pub validate_writes: bool,
+}
+
+/// Backdoor scan of models before `install_system`, `insert_model` and
+/// `search_creator_store` insert them.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScannerConfig {
+    /// Scan verdict from which inserts are refused; lesser findings come back as warnings
+    pub block: BlockLevel,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockLevel {
+    /// Refuse anything the scanner flags
+    Suspicious,
+    /// Refuse assets with code that is hardly ever found outside backdoors
+    #[default]
+    Malicious,
+    /// Only warn
+    Never,
+}
+
+/// Backend of `search_web_scripts`.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(tag = "provider", rename_all = "lowercase", deny_unknown_fields)]
+pub enum WebSearchConfig {
+    /// api.tavily.com, using the `tavily` API key
+    #[default]
+    Tavily,
+    /// A SearXNG instance with the JSON format enabled
+    Searxng { url: String },
+    /// Canned results from a JSON file, for tests and demos
+    Fixture { path: PathBuf },
+}
+
+/// Limits of `fetch_url_content`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchConfig {
+    /// When non-empty, only these domains and their subdomains can be fetched
+    pub allowed_domains: Vec<String>,
+    /// Domains (and their subdomains) that are never fetched, applied before `allowed_domains`
+    pub denied_domains: Vec<String>,
+    /// Allow localhost, LAN and other private addresses
+    pub allow_private_networks: bool,
+    /// Response bodies are cut off after this many bytes
+    pub max_bytes: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolPolicy {
+    /// Overrides `timeouts.tool_secs` for this tool
+    pub timeout_secs: Option<u64>,
+    /// Default `max_tokens` for tools that support output shaping
+    pub max_tokens: Option<u32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: STUDIO_PLUGIN_PORT,
+            offline: false,
+            asset_dir: PathBuf::from("assets"),
+            api_keys: ApiKeys::default(),
+            tools: ToolsConfig::default(),
+            timeouts: Timeouts::default(),
+            http: HttpConfig::default(),
+            cache: CacheConfig::default(),
+            fetch: FetchConfig::default(),
+            web_search: WebSearchConfig::default(),
+            api_dump: ApiDumpConfig::default(),
+            scanner: ScannerConfig::default(),
+            policies: HashMap::new(),
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            user_agent: None,
+            max_retries: 3,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: None,
+            ttl_secs: 3600,
+        }
+    }
+}
+
+impl Default for ApiDumpConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            download: true,
+            validate_writes: true,
+        }
+    }
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            denied_domains: Vec::new(),
+            allow_private_networks: false,
+            max_bytes: 2_000_000,
+        }
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            http_secs: 30,
+            tool_secs: None,
+        }
+    }
+}
+
+/// Settings that can be given on the command line or through the environment. These take
+/// precedence over the config file.
+#[derive(Debug, Default)]
+pub struct Overrides {
+    pub port: Option<u16>,
+    pub asset_dir: Option<PathBuf>,
+    pub proxy: Option<String>,
+    pub offline: bool,
+}
+
+impl Config {
+    /// Loads `path`, or the platform config file if no path is given and one exists.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => default_path().filter(|path| path.exists()),
+        };
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .wrap_err_with(|| format!("Could not read config file {}", path.display()))?;
+                let config: Config = toml::from_str(&text)
+                    .wrap_err_with(|| format!("Invalid config file {}", path.display()))?;
+                tracing::debug!("Loaded config from {}", path.display());
+                config
+            }
+            None => Config::default(),
+        };
+        config.asset_dir = expand_home(&config.asset_dir);
+        config.cache.dir = config.cache.dir.as_deref().map(expand_home);
+        config.api_dump.path = config.api_dump.path.as_deref().map(expand_home);
+        if let WebSearchConfig::Fixture { path } = &mut config.web_search {
+            *path = expand_home(path);
+        }
+        Ok(config)
+    }
+
+    pub fn apply(mut self, overrides: Overrides) -> Self {
+        if let Some(port) = overrides.port {
+            self.port = port;
+        }
+        if let Some(asset_dir) = overrides.asset_dir {
+            self.asset_dir = expand_home(&asset_dir);
+        }
+        if let Some(proxy) = overrides.proxy {
+            self.http.proxy = Some(proxy);
+        }
+        if overrides.offline {
+            self.offline = true;
+        }
+        self
+    }
+
+    pub fn is_tool_enabled(&self, name: &str) -> bool {
+        let enabled = match &self.tools.enabled {
+            Some(enabled) => enabled.iter().any(|tool| tool == name),
+            None => true,
+        };
+        enabled && !self.tools.disabled.iter().any(|tool| tool == name)
+    }
+
+    /// Whether `name` is a tool that cannot do anything without network access and is
+    /// unavailable in offline mode.
+    pub fn is_blocked_offline(&self, name: &str) -> bool {
+        let local_search = name == "search_web_scripts"
+            && matches!(self.web_search, WebSearchConfig::Fixture { .. });
+        self.offline && ONLINE_ONLY_TOOLS.contains(&name) && !local_search
+    }
+
+    /// Directory for cached responses and downloads: `cache.dir`, or the platform cache directory.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache.dir.clone().or_else(default_cache_dir)
+    }
+
+    pub fn policy(&self, name: &str) -> Option<&ToolPolicy> {
+        self.policies.get(name)
+    }
+
+    pub fn tool_timeout(&self, tool: &str) -> Option<Duration> {
+        self.policy(tool)
+            .and_then(|policy| policy.timeout_secs)
+            .or(self.timeouts.tool_secs)
+            .map(Duration::from_secs)
+    }
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+pub fn default_cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join(CONFIG_DIR_NAME))
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `text` the way `Config::load` reads a config file.
+    fn load(text: &str) -> Result<Config> {
+        let path = std::env::temp_dir().join(format!("config-test-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, text).unwrap();
+        let config = Config::load(Some(&path));
+        std::fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn file_values_replace_defaults() {
+        let config = load(
+            r#"
+            port = 1234
+            offline = true
+            asset_dir = "~/RobloxAssets"
+
+            [http]
+            proxy = "http://proxy.internal:3128"
+
+            [cache]
+            ttl_secs = 60
+
+            [web_search]
+            provider = "searxng"
+            url = "https://searx.internal"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.port, 1234);
+        assert!(config.offline);
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(config.asset_dir, home.join("RobloxAssets"));
+        }
+        assert_eq!(
+            config.http.proxy.as_deref(),
+            Some("http://proxy.internal:3128")
+        );
+        // Unset keys of a table keep their defaults
+        assert_eq!(config.http.max_retries, 3);
+        assert_eq!(config.cache.ttl_secs, 60);
+        assert!(config.cache.enabled);
+        assert!(matches!(
+            config.web_search,
+            WebSearchConfig::Searxng { url } if url == "https://searx.internal"
+        ));
+        assert_eq!(config.timeouts.http_secs, 30);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(load("prot = 1234").is_err());
+        assert!(load("[http]\nretries = 3").is_err());
+        assert!(load("[web_search]\nprovider = \"bing\"").is_err());
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let file = r#"
+            port = 1234
+            asset_dir = "/srv/assets"
+            [http]
+            proxy = "http://file.proxy:3128"
+        "#;
+        let config = load(file).unwrap().apply(Overrides {
+            port: Some(5678),
+            asset_dir: Some(PathBuf::from("/tmp/assets")),
+            proxy: Some("http://flag.proxy:3128".to_string()),
+            offline: true,
+        });
+        assert_eq!(config.port, 5678);
+        assert_eq!(config.asset_dir, PathBuf::from("/tmp/assets"));
+        assert_eq!(config.http.proxy.as_deref(), Some("http://flag.proxy:3128"));
+        assert!(config.offline);
+
+        // Overrides that are not given leave the file alone, and cannot turn offline mode off
+        let config = load(&format!("offline = true\n{file}"))
+            .unwrap()
+            .apply(Overrides::default());
+        assert_eq!(config.port, 1234);
+        assert_eq!(config.asset_dir, PathBuf::from("/srv/assets"));
+        assert_eq!(config.http.proxy.as_deref(), Some("http://file.proxy:3128"));
+        assert!(config.offline);
+    }
+
+    #[test]
+    fn enabled_and_disabled_tools() {
+        let config = Config::default();
+        assert!(config.is_tool_enabled("run_command"));
+
+        let config = load("[tools]\ndisabled = [\"run_command\"]").unwrap();
+        assert!(!config.is_tool_enabled("run_command"));
+        assert!(config.is_tool_enabled("insert_model"));
+
+        let config = load(
+            "[tools]\nenabled = [\"run_command\", \"insert_model\"]\ndisabled = [\"run_command\"]",
+        )
+        .unwrap();
+        assert!(!config.is_tool_enabled("run_command"));
+        assert!(config.is_tool_enabled("insert_model"));
+        assert!(!config.is_tool_enabled("get_file_tree"));
+    }
+
+    #[test]
+    fn online_only_tools_are_blocked_offline() {
+        let online = Config::default();
+        let offline = online.clone().apply(Overrides {
+            offline: true,
+            ..Default::default()
+        });
+        for tool in ONLINE_ONLY_TOOLS {
+            assert!(!online.is_blocked_offline(tool), "{tool}");
+            assert!(offline.is_blocked_offline(tool), "{tool}");
+        }
+        for tool in ["search_creator_store", "get_asset_details", "run_command"] {
+            assert!(!offline.is_blocked_offline(tool), "{tool}");
+        }
+
+        // Canned search results need no network
+        let fixture =
+            load("offline = true\n[web_search]\nprovider = \"fixture\"\npath = \"results.json\"")
+                .unwrap();
+        assert!(!fixture.is_blocked_offline("search_web_scripts"));
+        assert!(fixture.is_blocked_offline("fetch_url_content"));
+    }
+
+    #[test]
+    fn tool_timeouts() {
+        assert_eq!(Config::default().tool_timeout("run_command"), None);
+
+        let config = load(
+            r#"
+            [timeouts]
+            tool_secs = 120
+            [policies.run_command]
+            timeout_secs = 5
+            [policies.get_file_tree]
+            max_tokens = 4000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.tool_timeout("run_command"),
+            Some(Duration::from_secs(5))
+        );
+        // A policy without a timeout falls back to the global one
+        assert_eq!(
+            config.tool_timeout("get_file_tree"),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            config.tool_timeout("insert_model"),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            config.policy("get_file_tree").unwrap().max_tokens,
+            Some(4000)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Filters of the toolbox-service `assets:search` endpoint behind `search_creator_store`.
+
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Best match for the query first
+    #[default]
+    Relevance,
+    /// Most popular first
+    Top,
+    /// Newest first
+    Recent,
+}
+
+impl SortOrder {
+    fn sort_category(self) -> &'static str {
+        match self {
+            SortOrder::Relevance => "Relevance",
+            SortOrder::Top => "Top",
+            SortOrder::Recent => "CreateTime",
+        }
+    }
+}
+
+/// Optional narrowing of a Creator Store search. Part of the cache key, so differently
+/// filtered searches are cached apart.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
+pub struct SearchFilters {
+    #[schemars(description = "Only assets published by this user")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    creator_user_id: Option<u64>,
+    #[schemars(description = "Only assets published by this group")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    creator_group_id: Option<u64>,
+    #[schemars(description = "Only assets from verified creators. Default: false")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    verified_only: Option<bool>,
+    #[schemars(description = "Only free assets. Default: false")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    free_only: Option<bool>,
+    #[schemars(description = "\"relevance\", \"top\" or \"recent\". Default: relevance")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sort: Option<SortOrder>,
+    #[schemars(description = "Audio only: shortest duration in seconds")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min_duration_secs: Option<u32>,
+    #[schemars(description = "Audio only: longest duration in seconds")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_duration_secs: Option<u32>,
+}
+
+impl SearchFilters {
+    /// Rejects combinations the API can't serve for `asset_type`.
+    pub fn validate(&self, asset_type: &str) -> Result<(), String> {
+        if self.creator_user_id.is_some() && self.creator_group_id.is_some() {
+            return Err("Pass creator_user_id or creator_group_id, not both".to_string());
+        }
+        let has_duration = self.min_duration_secs.is_some() || self.max_duration_secs.is_some();
+        if has_duration && !asset_type.eq_ignore_ascii_case("Audio") {
+            return Err(format!(
+                "min_duration_secs and max_duration_secs only apply to Audio, not {asset_type}"
+            ));
+        }
+        if let (Some(min), Some(max)) = (self.min_duration_secs, self.max_duration_secs) {
+            if min > max {
+                return Err(format!(
+                    "min_duration_secs ({min}) is more than max_duration_secs ({max})"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The filters as URL query parameters, each starting with `&`.
+    pub fn query_string(&self) -> String {
+        let mut query = String::new();
+        if let Some(id) = self.creator_user_id {
+            query.push_str(&format!("&userId={id}"));
+        }
+        if let Some(id) = self.creator_group_id {
+            query.push_str(&format!("&groupId={id}"));
+        }
+        if self.verified_only == Some(true) {
+            query.push_str("&includeOnlyVerifiedCreators=true");
+        }
+        if self.free_only == Some(true) {
+            query.push_str("&minPriceCents=0&maxPriceCents=0");
+        }
+        let sort = self.sort.unwrap_or_default();
+        if sort != SortOrder::Relevance {
+            query.push_str(&format!(
+                "&sortCategory={}&sortDirection=Descending",
+                sort.sort_category()
+            ));
+        }
+        if let Some(secs) = self.min_duration_secs {
+            query.push_str(&format!("&minDuration={secs}"));
+        }
+        if let Some(secs) = self.max_duration_secs {
+            query.push_str(&format!("&maxDuration={secs}"));
+        }
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(json: serde_json::Value) -> SearchFilters {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn no_filters_add_nothing() {
+        assert_eq!(SearchFilters::default().query_string(), "");
+        let relevance = filters(serde_json::json!({ "sort": "relevance", "free_only": false }));
+        assert_eq!(relevance.query_string(), "");
+    }
+
+    #[test]
+    fn filters_become_query_parameters() {
+        let query = filters(serde_json::json!({
+            "creator_group_id": 7,
+            "verified_only": true,
+            "free_only": true,
+            "min_duration_secs": 5,
+            "max_duration_secs": 30,
+        }))
+        .query_string();
+        assert_eq!(
+            query,
+            "&groupId=7&includeOnlyVerifiedCreators=true&minPriceCents=0&maxPriceCents=0&minDuration=5&maxDuration=30"
+        );
+        let query = filters(serde_json::json!({ "creator_user_id": 1 })).query_string();
+        assert_eq!(query, "&userId=1");
+    }
+
+    #[test]
+    fn sort_orders() {
+        let sort = |order| filters(serde_json::json!({ "sort": order })).query_string();
+        assert_eq!(sort("top"), "&sortCategory=Top&sortDirection=Descending");
+        assert_eq!(
+            sort("recent"),
+            "&sortCategory=CreateTime&sortDirection=Descending"
+        );
+    }
+
+    #[test]
+    fn invalid_combinations_are_rejected() {
+        let both = filters(serde_json::json!({ "creator_user_id": 1, "creator_group_id": 2 }));
+        assert_eq!(
+            both.validate("Model"),
+            Err("Pass creator_user_id or creator_group_id, not both".to_string())
+        );
+        let duration = filters(serde_json::json!({ "min_duration_secs": 5 }));
+        assert!(duration.validate("audio").is_ok());
+        assert_eq!(
+            duration.validate("Model"),
+            Err(
+                "min_duration_secs and max_duration_secs only apply to Audio, not Model"
+                    .to_string()
+            )
+        );
+        let reversed =
+            filters(serde_json::json!({ "min_duration_secs": 9, "max_duration_secs": 3 }));
+        assert_eq!(
+            reversed.validate("Audio"),
+            Err("min_duration_secs (9) is more than max_duration_secs (3)".to_string())
+        );
+    }
+}
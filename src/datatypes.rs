@@ -0,0 +1,474 @@
+//! Roblox datatypes as they travel between the server and the Studio plugin. Values are JSON
+//! objects tagged with `"$type"`, which `DataTypes.luau` in the plugin turns into the real
+//! datatype without guessing from property names.
+
+use rmcp::schemars;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// A Roblox datatype, tagged with its type name.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+#[serde(tag = "$type")]
+pub enum RobloxValue {
+    Vector2 {
+        x: f64,
+        y: f64,
+    },
+    Vector3 {
+        x: f64,
+        y: f64,
+        z: f64,
+    },
+    Vector2int16 {
+        x: i16,
+        y: i16,
+    },
+    Vector3int16 {
+        x: i16,
+        y: i16,
+        z: i16,
+    },
+    /// A position and rotation. The rotation is either a row-major 3x3 matrix, or an
+    /// orientation in degrees as in `BasePart.Orientation`; it is the identity when both are
+    /// left out.
+    CFrame {
+        position: [f64; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rotation: Option<[f64; 9]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        orientation: Option<[f64; 3]>,
+    },
+    /// Components from 0 to 1
+    Color3 {
+        r: f64,
+        g: f64,
+        b: f64,
+    },
+    BrickColor {
+        #[schemars(description = "BrickColor name, e.g. \"Bright red\"")]
+        name: String,
+    },
+    UDim {
+        scale: f64,
+        offset: i32,
+    },
+    UDim2 {
+        x: UDim,
+        y: UDim,
+    },
+    Rect {
+        min: [f64; 2],
+        max: [f64; 2],
+    },
+    NumberRange {
+        min: f64,
+        max: f64,
+    },
+    NumberSequence {
+        keypoints: Vec<NumberKeypoint>,
+    },
+    ColorSequence {
+        keypoints: Vec<ColorKeypoint>,
+    },
+    PhysicalProperties {
+        density: f64,
+        friction: f64,
+        elasticity: f64,
+        #[serde(default = "one")]
+        friction_weight: f64,
+        #[serde(default = "one")]
+        elasticity_weight: f64,
+    },
+    Font {
+        #[schemars(
+            description = "Font family asset, e.g. \"rbxasset://fonts/families/Arial.json\""
+        )]
+        family: String,
+        #[schemars(description = "Enum.FontWeight item name. Default: Regular")]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weight: Option<String>,
+        #[schemars(description = "Enum.FontStyle item name. Default: Normal")]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        style: Option<String>,
+    },
+    EnumItem {
+        #[serde(rename = "enum")]
+        #[schemars(description = "Enum name, e.g. \"Material\"")]
+        enum_type: String,
+        #[schemars(description = "Item name, e.g. \"Plastic\"")]
+        name: String,
+    },
+    /// A reference to another instance
+    Instance {
+        #[schemars(description = "Path to the instance (e.g., \"game.Workspace.Part\")")]
+        path: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct UDim {
+    pub scale: f64,
+    pub offset: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct NumberKeypoint {
+    pub time: f64,
+    pub value: f64,
+    #[serde(default)]
+    pub envelope: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct ColorKeypoint {
+    pub time: f64,
+    /// `[r, g, b]` with components from 0 to 1
+    pub color: [f64; 3],
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+impl RobloxValue {
+    /// Name of the datatype, as used for property types in the API dump.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RobloxValue::Vector2 { .. } => "Vector2",
+            RobloxValue::Vector3 { .. } => "Vector3",
+            RobloxValue::Vector2int16 { .. } => "Vector2int16",
+            RobloxValue::Vector3int16 { .. } => "Vector3int16",
+            RobloxValue::CFrame { .. } => "CFrame",
+            RobloxValue::Color3 { .. } => "Color3",
+            RobloxValue::BrickColor { .. } => "BrickColor",
+            RobloxValue::UDim { .. } => "UDim",
+            RobloxValue::UDim2 { .. } => "UDim2",
+            RobloxValue::Rect { .. } => "Rect",
+            RobloxValue::NumberRange { .. } => "NumberRange",
+            RobloxValue::NumberSequence { .. } => "NumberSequence",
+            RobloxValue::ColorSequence { .. } => "ColorSequence",
+            RobloxValue::PhysicalProperties { .. } => "PhysicalProperties",
+            RobloxValue::Font { .. } => "Font",
+            RobloxValue::EnumItem { .. } => "EnumItem",
+            RobloxValue::Instance { .. } => "Instance",
+        }
+    }
+}
+
+/// A value for a property write: a tagged datatype, a primitive, or one of the untagged
+/// shorthands older clients send.
+#[derive(Debug, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Typed(RobloxValue),
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// `nil`, for clearing references such as `PrimaryPart`
+    Null,
+    /// Shorthand for Vector3 or Color3 (`[x, y, z]`) and Vector2 (`[x, y]`)
+    Components(Vec<f64>),
+    /// Shorthand for Vector3 (`{"X", "Y", "Z"}`) or Color3 (`{"R", "G", "B"}`)
+    Fields(BTreeMap<String, f64>),
+}
+
+impl PropertyValue {
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+// By hand rather than untagged, so a malformed `$type` value reports what is wrong with it
+impl<'de> Deserialize<'de> for PropertyValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        match value {
+            Value::Object(ref object) if object.contains_key("$type") => {
+                let type_name = object["$type"].to_string();
+                RobloxValue::deserialize(value)
+                    .map(PropertyValue::Typed)
+                    .map_err(|e| D::Error::custom(format!("invalid {type_name} value: {e}")))
+            }
+            Value::Null => Ok(PropertyValue::Null),
+            Value::Bool(value) => Ok(PropertyValue::Bool(value)),
+            Value::Number(number) => Ok(PropertyValue::Number(number.as_f64().unwrap_or_default())),
+            Value::String(value) => Ok(PropertyValue::String(value)),
+            Value::Array(_) => serde_json::from_value(value)
+                .map(PropertyValue::Components)
+                .map_err(|e| D::Error::custom(format!("arrays must hold only numbers: {e}"))),
+            Value::Object(_) => serde_json::from_value(value)
+                .map(PropertyValue::Fields)
+                .map_err(|e| {
+                    D::Error::custom(format!(
+                        "objects need a \"$type\", or only numeric fields: {e}"
+                    ))
+                }),
+        }
+    }
+}
+
+/// A value read back from Studio: a datatype, a primitive, or a table of values.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StudioValue {
+    Typed(RobloxValue),
+    Opaque(OpaqueValue),
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Array(Vec<StudioValue>),
+    Table(BTreeMap<String, StudioValue>),
+}
+
+/// A value with no JSON form, such as a function or an `RBXScriptSignal`. Not accepted by
+/// property writes.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct OpaqueValue {
+    #[serde(rename = "$type")]
+    tag: OpaqueTag,
+    /// Result of `typeof` in Luau
+    #[serde(rename = "typeof")]
+    type_of: String,
+    /// Result of `tostring` in Luau
+    text: String,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+enum OpaqueTag {
+    Opaque,
+}
+
+impl StudioValue {
+    /// Decodes a value as the plugin's `DataTypes.encode` writes it. Tagged objects that don't
+    /// match a known datatype are kept as opaque values rather than failing the whole response.
+    pub fn from_json(value: Value) -> Self {
+        match value {
+            Value::Null => StudioValue::Null,
+            Value::Bool(value) => StudioValue::Bool(value),
+            Value::Number(number) => StudioValue::Number(number),
+            Value::String(value) => StudioValue::String(value),
+            Value::Array(values) => {
+                StudioValue::Array(values.into_iter().map(StudioValue::from_json).collect())
+            }
+            Value::Object(object) if object.contains_key("$type") => {
+                let tag = object["$type"].as_str().unwrap_or_default().to_string();
+                let text = |object: &serde_json::Map<String, Value>| {
+                    object
+                        .get("text")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| Value::Object(object.clone()).to_string())
+                };
+                if tag == "Opaque" {
+                    // Lists from the plugin mark their nils this way
+                    if object.get("typeof").and_then(Value::as_str) == Some("nil") {
+                        return StudioValue::Null;
+                    }
+                    let type_of = object
+                        .get("typeof")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown")
+                        .to_string();
+                    return StudioValue::Opaque(OpaqueValue::new(type_of, text(&object)));
+                }
+                match RobloxValue::deserialize(Value::Object(object.clone())) {
+                    Ok(value) => StudioValue::Typed(value),
+                    Err(e) => {
+                        tracing::debug!("Could not decode {tag} from Studio: {e}");
+                        StudioValue::Opaque(OpaqueValue::new(tag, text(&object)))
+                    }
+                }
+            }
+            Value::Object(object) => StudioValue::Table(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, StudioValue::from_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl OpaqueValue {
+    fn new(type_of: String, text: String) -> Self {
+        Self {
+            tag: OpaqueTag::Opaque,
+            type_of,
+            text,
+        }
+    }
+}
+
+/// Decodes the tagged values in a plugin response, leaving the rest of it as it is. Responses
+/// that aren't JSON are returned unchanged.
+pub fn decode_response(response: String) -> String {
+    match serde_json::from_str::<Value>(&response) {
+        Ok(json) => serde_json::to_string(&StudioValue::from_json(json)).unwrap_or(response),
+        Err(_) => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn property(value: Value) -> Result<PropertyValue, String> {
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
+    /// Values that must survive a write to Studio and the read back unchanged.
+    fn typed_values() -> Vec<(Value, RobloxValue)> {
+        vec![
+            (
+                json!({ "$type": "Vector3", "x": 1.0, "y": 2.5, "z": -3.0 }),
+                RobloxValue::Vector3 {
+                    x: 1.0,
+                    y: 2.5,
+                    z: -3.0,
+                },
+            ),
+            (
+                json!({ "$type": "CFrame", "position": [0.0, 5.0, 0.0], "orientation": [0.0, 90.0, 0.0] }),
+                RobloxValue::CFrame {
+                    position: [0.0, 5.0, 0.0],
+                    rotation: None,
+                    orientation: Some([0.0, 90.0, 0.0]),
+                },
+            ),
+            (
+                json!({ "$type": "Color3", "r": 1.0, "g": 0.5, "b": 0.0 }),
+                RobloxValue::Color3 {
+                    r: 1.0,
+                    g: 0.5,
+                    b: 0.0,
+                },
+            ),
+            (
+                json!({ "$type": "EnumItem", "enum": "Material", "name": "Plastic" }),
+                RobloxValue::EnumItem {
+                    enum_type: "Material".to_string(),
+                    name: "Plastic".to_string(),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn typed_values_round_trip() {
+        for (json, expected) in typed_values() {
+            let value = property(json.clone()).unwrap();
+            assert_eq!(value, PropertyValue::Typed(expected.clone()));
+            assert_eq!(value.to_json(), json);
+            let read_back = StudioValue::from_json(value.to_json());
+            assert_eq!(read_back, StudioValue::Typed(expected));
+            assert_eq!(serde_json::to_value(&read_back).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn untyped_values() {
+        assert_eq!(property(json!(true)).unwrap(), PropertyValue::Bool(true));
+        assert_eq!(property(json!(4)).unwrap(), PropertyValue::Number(4.0));
+        assert_eq!(
+            property(json!("Part")).unwrap(),
+            PropertyValue::String("Part".to_string())
+        );
+        assert_eq!(property(json!(null)).unwrap(), PropertyValue::Null);
+        assert_eq!(
+            property(json!([1, 2, 3])).unwrap(),
+            PropertyValue::Components(vec![1.0, 2.0, 3.0])
+        );
+        assert_eq!(
+            property(json!({ "X": 1, "Y": 2 })).unwrap(),
+            PropertyValue::Fields(BTreeMap::from([("X".into(), 1.0), ("Y".into(), 2.0)]))
+        );
+        assert_eq!(property(json!(4)).unwrap().to_json(), json!(4.0));
+
+        assert_eq!(
+            StudioValue::from_json(json!(4)),
+            StudioValue::Number(4.into())
+        );
+        assert_eq!(
+            StudioValue::from_json(json!({ "Size": 2, "Name": "Part" })),
+            StudioValue::Table(BTreeMap::from([
+                ("Name".into(), StudioValue::String("Part".into())),
+                ("Size".into(), StudioValue::Number(2.into())),
+            ]))
+        );
+    }
+
+    #[test]
+    fn bad_property_values_say_what_is_wrong() {
+        let error = property(json!({ "$type": "Vector9", "x": 1 })).unwrap_err();
+        assert!(
+            error.starts_with("invalid \"Vector9\" value: unknown variant `Vector9`"),
+            "{error}"
+        );
+        let error = property(json!({ "$type": "Vector3", "x": 1, "y": 2 })).unwrap_err();
+        assert!(
+            error.starts_with("invalid \"Vector3\" value: missing field `z`"),
+            "{error}"
+        );
+        let error = property(json!({ "X": "one" })).unwrap_err();
+        assert!(error.starts_with("objects need a \"$type\""), "{error}");
+        let error = property(json!([1, "two"])).unwrap_err();
+        assert!(
+            error.starts_with("arrays must hold only numbers"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn unknown_studio_values_become_opaque() {
+        assert_eq!(
+            StudioValue::from_json(json!({ "$type": "Ray", "origin": [0, 0, 0] })),
+            StudioValue::Opaque(OpaqueValue::new(
+                "Ray".to_string(),
+                r#"{"$type":"Ray","origin":[0,0,0]}"#.to_string()
+            ))
+        );
+        assert_eq!(
+            StudioValue::from_json(
+                json!({ "$type": "Opaque", "typeof": "function", "text": "function: 0x1" })
+            ),
+            StudioValue::Opaque(OpaqueValue::new(
+                "function".to_string(),
+                "function: 0x1".to_string()
+            ))
+        );
+        assert_eq!(
+            StudioValue::from_json(json!([1, { "$type": "Opaque", "typeof": "nil" }])),
+            StudioValue::Array(vec![StudioValue::Number(1.into()), StudioValue::Null])
+        );
+    }
+
+    #[test]
+    fn responses_are_decoded() {
+        let response = json!({
+            "Position": { "$type": "Vector3", "x": 1, "y": 2, "z": 3 },
+            "Touched": { "$type": "Opaque", "typeof": "RBXScriptSignal", "text": "Signal Touched" },
+            "Children": ["Part", { "$type": "Opaque", "typeof": "nil" }],
+        });
+        let decoded: Value = serde_json::from_str(&decode_response(response.to_string())).unwrap();
+        assert_eq!(
+            decoded,
+            json!({
+                "Position": { "$type": "Vector3", "x": 1.0, "y": 2.0, "z": 3.0 },
+                "Touched": { "$type": "Opaque", "typeof": "RBXScriptSignal", "text": "Signal Touched" },
+                "Children": ["Part", null],
+            })
+        );
+        assert_eq!(decode_response("42".to_string()), "42");
+    }
+
+    #[test]
+    fn non_json_responses_are_unchanged() {
+        for response in ["", "Workspace.Part", "Error: {not json", "[1, 2"] {
+            assert_eq!(decode_response(response.to_string()), response);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use rmcp::model::Tool;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// A tool advertised by the Studio plugin on behalf of itself or another Studio plugin.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DynamicToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub input_schema: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct DynamicToolList {
+    pub tools: Vec<DynamicToolSpec>,
+}
+
+/// Arguments of a call to a dynamic tool, routed to the plugin like any other tool.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+pub struct DynamicToolCall {
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+pub struct DynamicTools {
+    tools: BTreeMap<String, Tool>,
+    changed: watch::Sender<()>,
+}
+
+impl DynamicTools {
+    pub fn new() -> Self {
+        let (changed, _) = watch::channel(());
+        Self {
+            tools: BTreeMap::new(),
+            changed,
+        }
+    }
+
+    /// Replaces the advertised tools, skipping invalid names and names `is_reserved` claims.
+    /// Returns the names that were rejected.
+    pub fn replace(
+        &mut self,
+        specs: Vec<DynamicToolSpec>,
+        is_reserved: impl Fn(&str) -> bool,
+    ) -> Vec<String> {
+        let mut rejected = Vec::new();
+        let mut tools = BTreeMap::new();
+        for spec in specs {
+            if !is_valid_name(&spec.name) || is_reserved(&spec.name) {
+                rejected.push(spec.name);
+                continue;
+            }
+            let schema = spec.input_schema.unwrap_or_else(|| {
+                serde_json::json!({ "type": "object" })
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default()
+            });
+            tools.insert(
+                spec.name.clone(),
+                Tool::new(spec.name, spec.description, Arc::new(schema)),
+            );
+        }
+        if tools != self.tools {
+            self.tools = tools;
+            self.changed.send_replace(());
+        }
+        rejected
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
+    pub fn list(&self) -> Vec<Tool> {
+        self.tools.values().cloned().collect()
+    }
+
+    pub fn specs(&self) -> Vec<DynamicToolSpec> {
+        self.tools
+            .values()
+            .map(|tool| DynamicToolSpec {
+                name: tool.name.to_string(),
+                description: tool.description.as_deref().unwrap_or_default().to_string(),
+                input_schema: Some(tool.input_schema.as_ref().clone()),
+            })
+            .collect()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.changed.subscribe()
+    }
+}
+
+/// MCP tool names are limited to `[a-zA-Z0-9_-]{1,64}`.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(name: &str, description: &str) -> DynamicToolSpec {
+        DynamicToolSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            input_schema: None,
+        }
+    }
+
+    fn names(tools: &DynamicTools) -> Vec<String> {
+        tools
+            .list()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn invalid_and_reserved_names_are_rejected() {
+        let mut tools = DynamicTools::new();
+        let long = "a".repeat(65);
+        let rejected = tools.replace(
+            vec![
+                spec("paint_terrain", ""),
+                spec("run_command", ""),
+                spec("", ""),
+                spec("has space", ""),
+                spec("dotted.name", ""),
+                spec(&long, ""),
+                spec(&"b".repeat(64), ""),
+                spec("Plugin-Tool_2", ""),
+            ],
+            |name| name == "run_command",
+        );
+        assert_eq!(
+            rejected,
+            ["run_command", "", "has space", "dotted.name", long.as_str()]
+        );
+        assert_eq!(
+            names(&tools),
+            ["Plugin-Tool_2", &"b".repeat(64), "paint_terrain"]
+        );
+        assert!(tools.contains("paint_terrain"));
+        assert!(!tools.contains("run_command"));
+    }
+
+    #[test]
+    fn specs_round_trip_with_a_default_schema() {
+        let mut tools = DynamicTools::new();
+        tools.replace(vec![spec("paint_terrain", "Paints terrain")], |_| false);
+        let specs = tools.specs();
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].description, "Paints terrain");
+        assert_eq!(
+            specs[0].input_schema,
+            serde_json::json!({ "type": "object" }).as_object().cloned()
+        );
+    }
+
+    #[test]
+    fn changes_are_notified_only_when_the_list_changes() {
+        let mut tools = DynamicTools::new();
+        let mut changed = tools.subscribe();
+        let mut replace = |specs: Vec<DynamicToolSpec>| {
+            tools.replace(specs, |name| name == "run_command");
+            let notified = changed.has_changed().unwrap();
+            changed.mark_unchanged();
+            notified
+        };
+
+        assert!(!replace(Vec::new()));
+        assert!(replace(vec![spec("paint_terrain", "Paints terrain")]));
+        assert!(!replace(vec![spec("paint_terrain", "Paints terrain")]));
+        // Rejected tools don't count as a change
+        assert!(!replace(vec![
+            spec("paint_terrain", "Paints terrain"),
+            spec("run_command", "")
+        ]));
+        assert!(replace(vec![spec(
+            "paint_terrain",
+            "Paints terrain and water"
+        )]));
+        assert!(replace(Vec::new()));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::config::FetchConfig;
+use crate::http::{HttpClient, HttpError};
+use crate::markdown;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::Url;
+use rmcp::ErrorData;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// Redirects followed by hand, so every hop goes through the URL policy.
+const MAX_REDIRECTS: usize = 5;
+
+/// A page fetched for `fetch_url_content`, ready to hand to the client.
+pub struct FetchedPage {
+    pub url: Url,
+    pub content_type: String,
+    pub text: String,
+    /// Set when the body was cut off at `fetch.max_bytes`
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+pub enum FetchError {
+    /// The URL (or a redirect target) is not allowed by the fetch policy
+    Blocked(String),
+    /// The response is binary or otherwise not worth returning as text
+    UnsupportedContentType(String),
+    Http(HttpError),
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FetchError::Blocked(reason) => write!(f, "{reason}"),
+            FetchError::UnsupportedContentType(content_type) => write!(
+                f,
+                "Unsupported content type {content_type}. Only text, HTML, JSON and XML are returned; use download_asset for Roblox assets"
+            ),
+            FetchError::Http(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<HttpError> for FetchError {
+    fn from(e: HttpError) -> Self {
+        FetchError::Http(e)
+    }
+}
+
+impl From<FetchError> for ErrorData {
+    fn from(e: FetchError) -> Self {
+        match e {
+            FetchError::Http(e) => e.into(),
+            e => ErrorData::invalid_params(e.to_string(), None),
+        }
+    }
+}
+
+/// Fetches `url` within the limits of `policy`: only allowed public hosts, redirects checked
+/// hop by hop, and at most `max_bytes` of body. HTML is converted to Markdown unless `raw`.
+pub async fn fetch_page(
+    http: &HttpClient,
+    policy: &FetchConfig,
+    url: &str,
+    raw: bool,
+) -> Result<FetchedPage, FetchError> {
+    let mut url = Url::parse(url).map_err(|e| FetchError::Blocked(format!("Invalid URL: {e}")))?;
+    let mut redirects = 0;
+    let mut response = loop {
+        check_url(&url, policy).await?;
+        let response = http.send(http.get_without_redirects(url.as_str())).await?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+        redirects += 1;
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| url.join(location).ok());
+        match location {
+            Some(location) if redirects <= MAX_REDIRECTS => url = location,
+            Some(_) => {
+                return Err(FetchError::Blocked(format!(
+                    "Too many redirects from {url}"
+                )))
+            }
+            None => {
+                return Err(FetchError::Blocked(format!(
+                    "{url} redirected without a valid Location"
+                )))
+            }
+        }
+    };
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/plain")
+        .to_string();
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    let kind = ContentKind::of(&mime)
+        .ok_or_else(|| FetchError::UnsupportedContentType(content_type.clone()))?;
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = response.chunk().await.map_err(HttpError::from)? {
+        let room = policy.max_bytes.saturating_sub(body.len());
+        if chunk.len() > room {
+            body.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let body = String::from_utf8_lossy(&body);
+
+    let text = match kind {
+        ContentKind::Html if !raw => markdown::html_to_markdown(&body, Some(&url)),
+        ContentKind::Json if !truncated => serde_json::from_str::<serde_json::Value>(&body)
+            .and_then(|json| serde_json::to_string_pretty(&json))
+            .unwrap_or_else(|_| body.into_owned()),
+        _ => body.into_owned(),
+    };
+    Ok(FetchedPage {
+        url,
+        content_type,
+        text,
+        truncated,
+    })
+}
+
+enum ContentKind {
+    Html,
+    Json,
+    Text,
+}
+
+impl ContentKind {
+    fn of(mime: &str) -> Option<Self> {
+        match mime {
+            "text/html" | "application/xhtml+xml" => Some(ContentKind::Html),
+            "application/json" => Some(ContentKind::Json),
+            mime if mime.ends_with("+json") => Some(ContentKind::Json),
+            mime if mime.starts_with("text/") || mime.ends_with("+xml") => Some(ContentKind::Text),
+            "application/xml"
+            | "application/javascript"
+            | "application/x-yaml"
+            | "application/toml"
+            | "" => Some(ContentKind::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Checks `url` against the scheme, domain lists and private-network rule of `policy`.
+async fn check_url(url: &Url, policy: &FetchConfig) -> Result<(), FetchError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(FetchError::Blocked(format!(
+            "Only http and https URLs can be fetched, not {}",
+            url.scheme()
+        )));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| FetchError::Blocked(format!("{url} has no host")))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+
+    if policy
+        .denied_domains
+        .iter()
+        .any(|domain| matches_domain(&host, domain))
+    {
+        return Err(FetchError::Blocked(format!(
+            "{host} is on the fetch denylist"
+        )));
+    }
+    if !policy.allowed_domains.is_empty()
+        && !policy
+            .allowed_domains
+            .iter()
+            .any(|domain| matches_domain(&host, domain))
+    {
+        return Err(FetchError::Blocked(format!(
+            "{host} is not on the fetch allowlist"
+        )));
+    }
+    if policy.allow_private_networks {
+        return Ok(());
+    }
+
+    let addresses: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) => {
+            let port = url.port_or_known_default().unwrap_or(443);
+            tokio::net::lookup_host((host.as_str(), port))
+                .await
+                .map_err(|e| FetchError::Blocked(format!("Could not resolve {host}: {e}")))?
+                .map(|address| address.ip())
+                .collect()
+        }
+    };
+    match addresses.into_iter().find(|ip| is_private(*ip)) {
+        Some(ip) => Err(FetchError::Blocked(format!(
+            "{host} resolves to the private address {ip}. Set fetch.allow_private_networks to fetch from local networks"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Resolver of the client `fetch_page` connects with, refusing hosts with a private address.
+/// `check_url` already vetted the host, but resolving again at connect time keeps a host that
+/// rebinds to a private address between the two lookups from getting through.
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if let Some(address) = addresses.iter().find(|address| is_private(address.ip())) {
+                return Err(
+                    format!("{host} resolves to the private address {}", address.ip()).into(),
+                );
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// `host` is `domain` or one of its subdomains. `*.` and `.` prefixes on `domain` are ignored.
+fn matches_domain(host: &str, domain: &str) -> bool {
+    let domain = domain
+        .trim_start_matches("*.")
+        .trim_start_matches('.')
+        .to_ascii_lowercase();
+    host == domain
+        || host
+            .strip_suffix(domain.as_str())
+            .is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Loopback, private, link-local, carrier-grade NAT and other non-public addresses.
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(is_private_v4)
+        }
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (b & 0xc0) == 64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolver_refuses_private_addresses() {
+        let name: Name = "localhost".parse().unwrap();
+        let error = PublicResolver.resolve(name).await.err().unwrap();
+        assert!(
+            error
+                .to_string()
+                .starts_with("localhost resolves to the private address"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use crate::config::Config;
+use crate::fetch;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use rmcp::ErrorData;
+use std::sync::Arc;
+use std::time::Duration;
+
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest `Retry-After` we are willing to sleep through before giving up on a request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// How much of an error response body is kept for the error message.
+const ERROR_BODY_LIMIT: usize = 500;
+
+/// The one HTTP client every web-facing tool goes through: pooled connections, the configured
+/// timeout, proxy and user agent, and retries with jittered backoff.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    /// Same settings, but redirects are returned instead of followed
+    no_redirects: reqwest::Client,
+    max_retries: u32,
+    offline: bool,
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    /// The server answered with a non-success status
+    Status {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// The request never got a usable answer (connection, timeout, decoding)
+    Request(reqwest::Error),
+    /// The request could not be retried because its body is a stream
+    NotRetryable,
+    /// The server runs in offline mode
+    Offline,
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::Status { url, status, body } if body.is_empty() => {
+                write!(f, "{url} returned {status}")
+            }
+            HttpError::Status { url, status, body } => {
+                write!(f, "{url} returned {status}: {body}")
+            }
+            HttpError::Request(e) => write!(f, "Request failed: {e}"),
+            HttpError::NotRetryable => write!(f, "Request body cannot be retried"),
+            HttpError::Offline => write!(
+                f,
+                "The server is in offline mode and does not make web requests"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(e: reqwest::Error) -> Self {
+        HttpError::Request(e)
+    }
+}
+
+impl From<HttpError> for ErrorData {
+    fn from(e: HttpError) -> Self {
+        let message = e.to_string();
+        match e {
+            HttpError::Status { url, status, .. } => {
+                let data = Some(serde_json::json!({ "url": url, "status": status.as_u16() }));
+                match status {
+                    StatusCode::NOT_FOUND => ErrorData::resource_not_found(message, data),
+                    StatusCode::TOO_MANY_REQUESTS => ErrorData::internal_error(message, data),
+                    status if status.is_client_error() => ErrorData::invalid_request(message, data),
+                    _ => ErrorData::internal_error(message, data),
+                }
+            }
+            HttpError::Request(_) | HttpError::NotRetryable => {
+                ErrorData::internal_error(message, None)
+            }
+            HttpError::Offline => {
+                ErrorData::invalid_request(message, Some(serde_json::json!({ "offline": true })))
+            }
+        }
+    }
+}
+
+impl HttpClient {
+    pub fn new(config: &Config) -> reqwest::Result<Self> {
+        let user_agent =
+            config.http.user_agent.clone().unwrap_or_else(|| {
+                format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+            });
+        let builder = || -> reqwest::Result<reqwest::ClientBuilder> {
+            let mut builder = reqwest::Client::builder()
+                .timeout(Duration::from_secs(config.timeouts.http_secs))
+                .connect_timeout(Duration::from_secs(config.timeouts.http_secs.min(10)))
+                .user_agent(user_agent.clone());
+            if let Some(proxy) = &config.http.proxy {
+                builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+            }
+            Ok(builder)
+        };
+        Ok(Self {
+            client: builder()?.build()?,
+            no_redirects: {
+                let mut builder = builder()?.redirect(reqwest::redirect::Policy::none());
+                // Through a proxy the proxy resolves the host, and it may well be local itself
+                if !config.fetch.allow_private_networks && config.http.proxy.is_none() {
+                    builder = builder.dns_resolver(Arc::new(fetch::PublicResolver));
+                }
+                builder.build()?
+            },
+            max_retries: config.http.max_retries,
+            offline: config.offline,
+        })
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// A GET whose redirects come back as 3xx responses, for callers that vet each hop.
+    pub fn get_without_redirects(&self, url: &str) -> RequestBuilder {
+        self.no_redirects.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends the request, retrying timeouts, connection errors, 408, 429 and 5xx responses.
+    /// Only successful responses (and redirects the client did not follow) are returned; anything
+    /// else becomes an [`HttpError`]. Nothing is sent in offline mode.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, HttpError> {
+        if self.offline {
+            return Err(HttpError::Offline);
+        }
+        let mut attempt = 0;
+        loop {
+            let this_try = request.try_clone().ok_or(HttpError::NotRetryable)?;
+            let retry_in = match this_try.send().await {
+                Ok(response)
+                    if response.status().is_success() || response.status().is_redirection() =>
+                {
+                    return Ok(response)
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = retry_after(response.headers());
+                    let url = response.url().to_string();
+                    if attempt >= self.max_retries || !is_retryable(status) {
+                        let mut body = response.text().await.unwrap_or_default();
+                        truncate(&mut body, ERROR_BODY_LIMIT);
+                        return Err(HttpError::Status { url, status, body });
+                    }
+                    match retry_after {
+                        Some(delay) if delay > MAX_RETRY_AFTER => {
+                            return Err(HttpError::Status {
+                                url,
+                                status,
+                                body: format!("retry after {}s", delay.as_secs()),
+                            })
+                        }
+                        Some(delay) => delay,
+                        None => backoff(attempt),
+                    }
+                }
+                Err(e) if attempt < self.max_retries && (e.is_timeout() || e.is_connect()) => {
+                    backoff(attempt)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            attempt += 1;
+            tracing::warn!(
+                "Retrying request (attempt {}/{}) in {}ms",
+                attempt + 1,
+                self.max_retries + 1,
+                retry_in.as_millis()
+            );
+            tokio::time::sleep(retry_in).await;
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+/// Exponential backoff with full jitter.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    ceiling.mul_f64(fastrand::f64())
+}
+
+/// `Retry-After` in its delta-seconds form. HTTP-date values fall back to regular backoff.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+fn truncate(text: &mut String, limit: usize) {
+    if text.len() > limit {
+        let mut end = limit;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str("...");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// One scripted answer: status, `Retry-After` and how long to wait before answering.
+    type Reply = (u16, Option<&'static str>, Duration);
+
+    /// Serves `replies` in order, then 200s. Returns the base URL and the number of requests
+    /// received so far.
+    async fn scripted_server(replies: Vec<Reply>) -> (String, Arc<Mutex<usize>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        (url, serve_script(listener, replies))
+    }
+
+    fn serve_script(listener: tokio::net::TcpListener, replies: Vec<Reply>) -> Arc<Mutex<usize>> {
+        let replies = Arc::new(Mutex::new(VecDeque::from(replies)));
+        let hits = Arc::new(Mutex::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/",
+            get(move || async move {
+                *counter.lock().unwrap() += 1;
+                let reply = replies.lock().unwrap().pop_front();
+                let (status, retry_after, delay) = reply.unwrap_or((200, None, Duration::ZERO));
+                tokio::time::sleep(delay).await;
+                let mut response = (
+                    axum::http::StatusCode::from_u16(status).unwrap(),
+                    format!("status {status}"),
+                )
+                    .into_response();
+                if let Some(retry_after) = retry_after {
+                    response
+                        .headers_mut()
+                        .insert("retry-after", HeaderValue::from_static(retry_after));
+                }
+                response
+            }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        hits
+    }
+
+    fn client(max_retries: u32) -> HttpClient {
+        let mut config = Config::default();
+        config.http.max_retries = max_retries;
+        config.timeouts.http_secs = 1;
+        HttpClient::new(&config).unwrap()
+    }
+
+    async fn fetch(client: &HttpClient, url: &str) -> Result<String, HttpError> {
+        Ok(client.send(client.get(url)).await?.text().await?)
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, " 7 ".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "-1".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn errors_map_to_error_codes() {
+        use rmcp::model::ErrorCode;
+        let status = |status: StatusCode| -> ErrorData {
+            HttpError::Status {
+                url: "https://example.com/a".into(),
+                status,
+                body: String::new(),
+            }
+            .into()
+        };
+
+        let not_found = status(StatusCode::NOT_FOUND);
+        assert_eq!(not_found.code, ErrorCode::RESOURCE_NOT_FOUND);
+        assert_eq!(
+            not_found.message,
+            "https://example.com/a returned 404 Not Found"
+        );
+        assert_eq!(
+            not_found.data,
+            Some(serde_json::json!({ "url": "https://example.com/a", "status": 404 }))
+        );
+        assert_eq!(
+            status(StatusCode::TOO_MANY_REQUESTS).code,
+            ErrorCode::INTERNAL_ERROR
+        );
+        assert_eq!(
+            status(StatusCode::FORBIDDEN).code,
+            ErrorCode::INVALID_REQUEST
+        );
+        assert_eq!(
+            status(StatusCode::BAD_GATEWAY).code,
+            ErrorCode::INTERNAL_ERROR
+        );
+
+        let offline: ErrorData = HttpError::Offline.into();
+        assert_eq!(offline.code, ErrorCode::INVALID_REQUEST);
+        assert_eq!(offline.data, Some(serde_json::json!({ "offline": true })));
+        let not_retryable: ErrorData = HttpError::NotRetryable.into();
+        assert_eq!(not_retryable.code, ErrorCode::INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn error_bodies_are_truncated_on_char_boundaries() {
+        let mut text = "é".repeat(10);
+        truncate(&mut text, 5);
+        assert_eq!(text, "éé...");
+        let mut short = "short".to_string();
+        truncate(&mut short, 5);
+        assert_eq!(short, "short");
+    }
+
+    #[tokio::test]
+    async fn retryable_statuses_are_retried() {
+        let now = Some("0");
+        let (url, hits) = scripted_server(vec![
+            (408, now, Duration::ZERO),
+            (429, now, Duration::ZERO),
+            (503, now, Duration::ZERO),
+        ])
+        .await;
+        assert_eq!(fetch(&client(3), &url).await.unwrap(), "status 200");
+        assert_eq!(*hits.lock().unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn retries_run_out() {
+        let (url, hits) = scripted_server(vec![(500, Some("0"), Duration::ZERO); 3]).await;
+        match fetch(&client(2), &url).await {
+            Err(HttpError::Status { status, body, .. }) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(body, "status 500");
+            }
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(*hits.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn client_errors_and_long_retry_afters_are_not_retried() {
+        let (url, hits) = scripted_server(vec![(404, None, Duration::ZERO)]).await;
+        assert!(matches!(
+            fetch(&client(3), &url).await,
+            Err(HttpError::Status {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })
+        ));
+        assert_eq!(*hits.lock().unwrap(), 1);
+
+        let (url, hits) = scripted_server(vec![(429, Some("120"), Duration::ZERO)]).await;
+        match fetch(&client(3), &url).await {
+            Err(HttpError::Status { body, .. }) => assert_eq!(body, "retry after 120s"),
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(*hits.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_retried() {
+        let (url, hits) = scripted_server(vec![(200, None, Duration::from_secs(3))]).await;
+        assert_eq!(fetch(&client(1), &url).await.unwrap(), "status 200");
+        assert_eq!(*hits.lock().unwrap(), 2);
+
+        let (url, _) = scripted_server(vec![(200, None, Duration::from_secs(3)); 2]).await;
+        match fetch(&client(1), &url).await {
+            Err(HttpError::Request(e)) => assert!(e.is_timeout()),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_errors_are_retried() {
+        // Nothing listens on a port that was just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("http://{addr}/");
+        drop(listener);
+        match fetch(&client(0), &url).await {
+            Err(HttpError::Request(e)) => assert!(e.is_connect()),
+            other => panic!("{other:?}"),
+        }
+
+        // Start listening while the client is backing off
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+            serve_script(listener, Vec::new());
+        });
+        assert_eq!(fetch(&client(10), &url).await.unwrap(), "status 200");
+    }
+
+    #[tokio::test]
+    async fn offline_clients_send_nothing() {
+        let (url, hits) = scripted_server(Vec::new()).await;
+        let client = HttpClient::new(&Config {
+            offline: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            fetch(&client, &url).await,
+            Err(HttpError::Offline)
+        ));
+        assert_eq!(*hits.lock().unwrap(), 0);
+    }
+}
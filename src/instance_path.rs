@@ -0,0 +1,573 @@
+//! Instance paths as tools take them, parsed and checked before anything is sent to Studio.
+//! The plugin resolves the same grammar in `Util.luau`.
+//!
+//! ```text
+//! path    = root { "." segment }
+//! root    = "game" | "workspace" | "game:GetService(" quoted ")"
+//! segment = "**" | name [ "[" index "]" ]
+//! name    = { char | "\" char | quoted }, where an unquoted "*" matches any run of
+//!           characters and "?" any one character
+//! quoted  = '"' { char | "\" char } '"'   (or single quotes at the start of a name)
+//! ```
+//!
+//! A path that doesn't start with a root is looked up under `game`, so `Workspace.Part` still
+//! works. `[n]` picks the n-th child with that name (or the n-th match of a pattern), counting
+//! from 1. `**` stands for any number of levels, including none.
+
+use rmcp::schemars::{self, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Root {
+    Game,
+    Workspace,
+    /// `game:GetService("...")`, for services that aren't found by name
+    Service(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Literal(String),
+    /// `*`
+    AnyChars,
+    /// `?`
+    AnyChar,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Child {
+        name: Vec<Token>,
+        /// 1-based position among the children that match `name`
+        index: Option<u32>,
+    },
+    /// `**`
+    AnyDepth,
+}
+
+/// A parsed path, which may contain wildcards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathPattern {
+    pub root: Root,
+    pub segments: Vec<Segment>,
+}
+
+/// A path to a single instance: a [`PathPattern`] without wildcards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstancePath(PathPattern);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathError {
+    path: String,
+    /// Byte offset of the problem in `path`
+    position: usize,
+    message: String,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid instance path {:?} at character {}: {}",
+            self.path,
+            self.path[..self.position].chars().count() + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for PathError {}
+
+impl PathPattern {
+    pub fn is_glob(&self) -> bool {
+        self.segments.iter().any(|segment| match segment {
+            Segment::AnyDepth => true,
+            Segment::Child { name, .. } => {
+                name.iter().any(|token| !matches!(token, Token::Literal(_)))
+            }
+        })
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = PathError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        Parser { path, position: 0 }.parse()
+    }
+}
+
+impl FromStr for InstancePath {
+    type Err = PathError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let pattern: PathPattern = path.parse()?;
+        if pattern.is_glob() {
+            let position = path.find('*').or_else(|| path.find('?')).unwrap_or(0);
+            return Err(PathError {
+                path: path.to_string(),
+                position,
+                message: "wildcards are only allowed in the `paths` of mass_* tools; quote names that contain * or ?".to_string(),
+            });
+        }
+        Ok(InstancePath(pattern))
+    }
+}
+
+impl From<InstancePath> for PathPattern {
+    fn from(path: InstancePath) -> Self {
+        path.0
+    }
+}
+
+struct Parser<'a> {
+    path: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<PathPattern, PathError> {
+        let mut pattern = PathPattern {
+            root: Root::Game,
+            segments: Vec::new(),
+        };
+        if self.path.trim().is_empty() {
+            return Ok(pattern);
+        }
+        if let Some(rest) = self.path.strip_prefix("game:GetService(") {
+            self.position = self.path.len() - rest.len();
+            let quote = self.peek();
+            if !matches!(quote, Some('"' | '\'')) {
+                return Err(self.error("expected a quoted service name"));
+            }
+            self.position += 1;
+            let name = self.quoted(quote.unwrap_or('"'))?;
+            if name.is_empty() {
+                return Err(self.error("the service name is empty"));
+            }
+            if self.peek() != Some(')') {
+                return Err(self.error("expected `)`"));
+            }
+            self.position += 1;
+            pattern.root = Root::Service(name);
+            if self.peek().is_none() {
+                return Ok(pattern);
+            }
+            self.expect_dot()?;
+        }
+
+        let mut first = matches!(pattern.root, Root::Game);
+        loop {
+            let start = self.position;
+            let segment = self.segment()?;
+            let raw = &self.path[start..self.position];
+            match (first, raw) {
+                (true, "game") => {}
+                (true, "workspace") => pattern.root = Root::Workspace,
+                _ => pattern.segments.push(segment),
+            }
+            first = false;
+            if self.peek().is_none() {
+                return Ok(pattern);
+            }
+            self.expect_dot()?;
+        }
+    }
+
+    fn segment(&mut self) -> Result<Segment, PathError> {
+        let start = self.position;
+        let mut name = Vec::new();
+        let mut literal = String::new();
+        // A quoted empty name is still a name
+        let mut quoted = false;
+        let mut index = None;
+        while let Some(c) = self.peek() {
+            match c {
+                '.' => break,
+                '\\' => {
+                    self.position += 1;
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("`\\` at the end of the path"))?;
+                    literal.push(escaped);
+                    self.position += escaped.len_utf8();
+                }
+                // Apostrophes only quote at the start, so names like Bob's Part still work
+                '"' | '\'' if c == '"' || (name.is_empty() && literal.is_empty()) => {
+                    self.position += 1;
+                    literal.push_str(&self.quoted(c)?);
+                    quoted = true;
+                }
+                '*' if self.path[self.position..].starts_with("**") => {
+                    self.position += 2;
+                    let alone = name.is_empty()
+                        && literal.is_empty()
+                        && matches!(self.peek(), None | Some('.'));
+                    if !alone {
+                        return Err(self.error("`**` must be a whole segment"));
+                    }
+                    return Ok(Segment::AnyDepth);
+                }
+                '*' | '?' => {
+                    if !literal.is_empty() {
+                        name.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    name.push(if c == '*' {
+                        Token::AnyChars
+                    } else {
+                        Token::AnyChar
+                    });
+                    self.position += 1;
+                }
+                '[' => {
+                    self.position += 1;
+                    index = Some(self.index()?);
+                    if !matches!(self.peek(), None | Some('.')) {
+                        return Err(self.error("expected `.` after the index"));
+                    }
+                    break;
+                }
+                ']' => return Err(self.error("unmatched `]`")),
+                c => {
+                    literal.push(c);
+                    self.position += c.len_utf8();
+                }
+            }
+        }
+        if !literal.is_empty() || (quoted && name.is_empty()) {
+            name.push(Token::Literal(literal));
+        }
+        if name.is_empty() {
+            self.position = start;
+            return Err(self.error("empty name; quote names that are empty or contain dots"));
+        }
+        Ok(Segment::Child { name, index })
+    }
+
+    /// Reads up to the closing `quote`, the opening one having been consumed.
+    fn quoted(&mut self, quote: char) -> Result<String, PathError> {
+        let start = self.position - 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    self.position = start;
+                    return Err(self.error("unterminated quote"));
+                }
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    if let Some(escaped) = self.peek() {
+                        text.push(escaped);
+                        self.position += escaped.len_utf8();
+                    }
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.position += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn index(&mut self) -> Result<u32, PathError> {
+        let start = self.position;
+        let end = self.path[start..]
+            .find(']')
+            .map(|offset| start + offset)
+            .ok_or_else(|| self.error("unterminated `[`"))?;
+        let index = self.path[start..end]
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|index| *index > 0)
+            .ok_or_else(|| self.error("the index must be a whole number from 1"))?;
+        self.position = end + 1;
+        Ok(index)
+    }
+
+    fn expect_dot(&mut self) -> Result<(), PathError> {
+        if self.peek() != Some('.') {
+            return Err(self.error("expected `.`"));
+        }
+        self.position += 1;
+        if self.peek().is_none() {
+            return Err(self.error("the path ends with `.`"));
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.path[self.position..].chars().next()
+    }
+
+    fn error(&self, message: &str) -> PathError {
+        PathError {
+            path: self.path.to_string(),
+            position: self.position,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Writes `text` in quotes, escaping quotes and backslashes.
+fn write_quoted(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        if matches!(c, '"' | '\\') {
+            f.write_str("\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    f.write_str("\"")
+}
+
+/// Writes a literal name part, quoted when it would otherwise read as something else.
+fn write_literal(f: &mut fmt::Formatter<'_>, text: &str, whole: bool) -> fmt::Result {
+    let special = text.is_empty()
+        || text.starts_with('\'')
+        || text
+            .chars()
+            .any(|c| matches!(c, '.' | '[' | ']' | '"' | '\\' | '*' | '?'))
+        || (whole && matches!(text, "game" | "workspace"));
+    if special {
+        write_quoted(f, text)
+    } else {
+        f.write_str(text)
+    }
+}
+
+/// `name` as a path segment, quoted when it would otherwise read as something else.
+pub fn format_name(name: &str) -> String {
+    struct Name<'a>(&'a str);
+    impl fmt::Display for Name<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_literal(f, self.0, true)
+        }
+    }
+    Name(name).to_string()
+}
+
+/// The canonical form, which the plugin parses back into the same path.
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.root {
+            Root::Game => f.write_str("game")?,
+            Root::Workspace => f.write_str("workspace")?,
+            Root::Service(name) => {
+                f.write_str("game:GetService(")?;
+                write_quoted(f, name)?;
+                f.write_str(")")?;
+            }
+        }
+        for segment in &self.segments {
+            f.write_str(".")?;
+            match segment {
+                Segment::AnyDepth => f.write_str("**")?,
+                Segment::Child { name, index } => {
+                    for token in name {
+                        match token {
+                            Token::Literal(text) => write_literal(f, text, name.len() == 1)?,
+                            Token::AnyChars => f.write_str("*")?,
+                            Token::AnyChar => f.write_str("?")?,
+                        }
+                    }
+                    if let Some(index) = index {
+                        write!(f, "[{index}]")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for InstancePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+macro_rules! string_serde {
+    ($ty:ty, $description:literal) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let path = String::deserialize(deserializer)?;
+                path.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl JsonSchema for $ty {
+            fn schema_name() -> Cow<'static, str> {
+                stringify!($ty).into()
+            }
+
+            fn inline_schema() -> bool {
+                true
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                schemars::json_schema!({
+                    "type": "string",
+                    "description": $description,
+                })
+            }
+        }
+    };
+}
+
+string_serde!(
+    InstancePath,
+    "Instance path in dot notation, e.g. game.Workspace.Part. Quote names with dots (game.Workspace.\"My.Part\"), pick among same-named siblings with [n] (Part[2]), and start from game:GetService(\"Name\") for services"
+);
+string_serde!(
+    PathPattern,
+    "Instance path as for single-instance tools, which may also use * and ? wildcards in names and ** for any number of levels, e.g. game.Workspace.**.Part*"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(text: &str) -> Segment {
+        Segment::Child {
+            name: vec![Token::Literal(text.to_string())],
+            index: None,
+        }
+    }
+
+    fn error(path: &str) -> String {
+        path.parse::<PathPattern>().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn roots() {
+        let path: PathPattern = "Workspace.Part".parse().unwrap();
+        assert_eq!(path.root, Root::Game);
+        assert_eq!(path.segments, [literal("Workspace"), literal("Part")]);
+
+        let path: PathPattern = "workspace.Part".parse().unwrap();
+        assert_eq!(path.root, Root::Workspace);
+        assert_eq!(path.segments, [literal("Part")]);
+
+        let path: PathPattern = "game:GetService(\"Replicated Storage\").Remotes"
+            .parse()
+            .unwrap();
+        assert_eq!(path.root, Root::Service("Replicated Storage".to_string()));
+        assert_eq!(path.segments, [literal("Remotes")]);
+
+        let path: PathPattern = "game".parse().unwrap();
+        assert!(path.segments.is_empty());
+    }
+
+    #[test]
+    fn quotes_and_escapes() {
+        let path: PathPattern =
+            r#"game.Workspace."My.Part".'Odd"Name'.a\.b.Bob's"#.parse().unwrap();
+        assert_eq!(
+            path.segments,
+            [
+                literal("Workspace"),
+                literal("My.Part"),
+                literal("Odd\"Name"),
+                literal("a.b"),
+                literal("Bob's"),
+            ]
+        );
+    }
+
+    #[test]
+    fn sibling_index() {
+        let path: InstancePath = "game.Workspace.Part[2]".parse().unwrap();
+        assert_eq!(
+            PathPattern::from(path).segments[1],
+            Segment::Child {
+                name: vec![Token::Literal("Part".to_string())],
+                index: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn globs() {
+        let path: PathPattern = "game.Workspace.**.Door?_*".parse().unwrap();
+        assert!(path.is_glob());
+        assert_eq!(path.segments[1], Segment::AnyDepth);
+        assert_eq!(
+            path.segments[2],
+            Segment::Child {
+                name: vec![
+                    Token::Literal("Door".to_string()),
+                    Token::AnyChar,
+                    Token::Literal("_".to_string()),
+                    Token::AnyChars,
+                ],
+                index: None,
+            }
+        );
+        assert!(!"game.Workspace.\"*\""
+            .parse::<PathPattern>()
+            .unwrap()
+            .is_glob());
+        assert!("game.Workspace.*"
+            .parse::<InstancePath>()
+            .unwrap_err()
+            .to_string()
+            .contains("wildcards are only allowed"));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for path in [
+            "game",
+            "workspace.Part",
+            "game.Workspace.\"My.Part\"",
+            "game.Workspace.\"a[1]\"[3]",
+            "game.Workspace.\"say \\\"hi\\\"\"",
+            "game.Workspace.\"back\\\\slash\"",
+            "game.Workspace.\"'quoted\"",
+            "game.Workspace.\"game\"",
+            "game.Workspace.\"\"",
+            "game:GetService(\"ServerStorage\").Tools",
+            "game.Workspace.**.Part*[2]",
+        ] {
+            let parsed: PathPattern = path.parse().unwrap();
+            assert_eq!(parsed.to_string(), path);
+            assert_eq!(parsed.to_string().parse::<PathPattern>().unwrap(), parsed);
+        }
+        assert_eq!(format_name("My.Part"), "\"My.Part\"");
+        assert_eq!(format_name("Part"), "Part");
+    }
+
+    #[test]
+    fn malformed_paths() {
+        assert_eq!(
+            error("game.Workspace.\"Part"),
+            "Invalid instance path \"game.Workspace.\\\"Part\" at character 16: unterminated quote"
+        );
+        assert!(error("game.Part[0]").ends_with("the index must be a whole number from 1"));
+        assert!(error("game.Part[x]").ends_with("the index must be a whole number from 1"));
+        assert!(error("game.Part[2").ends_with("unterminated `[`"));
+        assert!(error("game.Part[1]x").ends_with("expected `.` after the index"));
+        assert!(error("game.Part]").ends_with("unmatched `]`"));
+        assert!(
+            error("game..Part").ends_with("empty name; quote names that are empty or contain dots")
+        );
+        assert!(error("game.Part.").ends_with("the path ends with `.`"));
+        assert!(error("game.a**").ends_with("`**` must be a whole segment"));
+        assert!(error("game:GetService(Lighting)").ends_with("expected a quoted service name"));
+        assert!(error("game.Part\\").ends_with("`\\` at the end of the path"));
+    }
+}
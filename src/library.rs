@@ -0,0 +1,473 @@
+//! The asset library: downloaded files in `asset_dir`, with an index of where each came from
+//! in `library.json` next to them.
+
+use crate::util::{now_secs, sha256_hex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tokio::sync::Mutex;
+
+const INDEX_FILE_NAME: &str = "library.json";
+/// Longest file name, before the extension, `sanitize_file_name` keeps
+const MAX_FILE_STEM_LEN: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LibraryEntry {
+    pub asset_id: u64,
+    /// Asset version downloaded, when one was asked for instead of the latest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+    /// Roblox asset type, e.g. `Model` or `Audio`, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_type: Option<String>,
+    /// File name in the library directory
+    pub file: String,
+    /// Unix time of the download, in seconds
+    pub downloaded_at: u64,
+    /// Hex SHA-256 of the file
+    pub sha256: String,
+    pub size: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl LibraryEntry {
+    /// Whether this is the entry for `version` of `asset_id`, `None` meaning the latest version.
+    fn is(&self, asset_id: u64, version: Option<u64>) -> bool {
+        self.asset_id == asset_id && self.version == version
+    }
+}
+
+/// What is known about an asset when it is added.
+#[derive(Debug, Clone, Default)]
+pub struct AssetInfo {
+    pub asset_id: u64,
+    pub version: Option<u64>,
+    pub name: Option<String>,
+    pub creator: Option<String>,
+    pub asset_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Index {
+    entries: Vec<LibraryEntry>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct DedupeReport {
+    /// Entries dropped because an older entry has the same content
+    pub duplicates: Vec<LibraryEntry>,
+    /// Entries dropped because their file is gone
+    pub missing_files: Vec<LibraryEntry>,
+}
+
+pub struct Library {
+    dir: PathBuf,
+    /// Held across each read-modify-write of the index
+    lock: Mutex<()>,
+}
+
+impl Library {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path_of(&self, entry: &LibraryEntry) -> PathBuf {
+        self.dir.join(&entry.file)
+    }
+
+    /// The file at `path` in the library, trying `.rbxm` then `.rbxmx` when `path` has no
+    /// extension. Fails with `PermissionDenied` for paths that lead out of the library,
+    /// including through symlinks.
+    pub async fn resolve(&self, path: &str) -> std::io::Result<PathBuf> {
+        let outside = || {
+            std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("{path} is outside the asset library"),
+            )
+        };
+        let relative = Path::new(path);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(outside());
+        }
+        let dir = tokio::fs::canonicalize(&self.dir).await?;
+        let mut file = dir.join(relative);
+        if file.extension().is_none() && !tokio::fs::try_exists(&file).await.unwrap_or(false) {
+            file.set_extension("rbxm");
+            if !tokio::fs::try_exists(&file).await.unwrap_or(false) {
+                file.set_extension("rbxmx");
+            }
+        }
+        let file = tokio::fs::canonicalize(&file).await?;
+        if !file.starts_with(&dir) {
+            return Err(outside());
+        }
+        Ok(file)
+    }
+
+    /// Writes `bytes` to `file` in the library and records it, replacing any entry for the
+    /// same asset and version. Entries for other versions of the asset are kept. Fails with
+    /// `AlreadyExists` when `file` belongs to another entry.
+    pub async fn add(
+        &self,
+        info: AssetInfo,
+        file: &str,
+        bytes: &[u8],
+    ) -> std::io::Result<LibraryEntry> {
+        let _guard = self.lock.lock().await;
+        let mut index = self.read_index().await;
+        if let Some(owner) = index
+            .entries
+            .iter()
+            .find(|entry| entry.file == file && !entry.is(info.asset_id, info.version))
+        {
+            let version = owner
+                .version
+                .map(|version| format!(" version {version}"))
+                .unwrap_or_default();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!(
+                    "{file} already holds asset {}{version} in the library, choose another file name",
+                    owner.asset_id
+                ),
+            ));
+        }
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.dir.join(file), bytes).await?;
+        let tags = index
+            .entries
+            .iter()
+            .find(|entry| entry.is(info.asset_id, info.version))
+            .map(|entry| entry.tags.clone())
+            .unwrap_or_default();
+        let entry = LibraryEntry {
+            asset_id: info.asset_id,
+            version: info.version,
+            name: info.name,
+            creator: info.creator,
+            asset_type: info.asset_type,
+            file: file.to_string(),
+            downloaded_at: now_secs(),
+            sha256: sha256_hex(bytes),
+            size: bytes.len() as u64,
+            tags,
+        };
+        let replaced: Vec<LibraryEntry> = index
+            .entries
+            .extract_if(.., |existing| existing.is(entry.asset_id, entry.version))
+            .collect();
+        index.entries.push(entry.clone());
+        self.write_index(&index).await?;
+        for old in &replaced {
+            self.remove_file(&index, old).await;
+        }
+        Ok(entry)
+    }
+
+    pub async fn entries(&self) -> Vec<LibraryEntry> {
+        let _guard = self.lock.lock().await;
+        self.read_index().await.entries
+    }
+
+    /// The entry for `version` of `asset_id`, `None` meaning the latest version.
+    pub async fn get(&self, asset_id: u64, version: Option<u64>) -> Option<LibraryEntry> {
+        self.entries()
+            .await
+            .into_iter()
+            .find(|entry| entry.is(asset_id, version))
+    }
+
+    /// Entries whose name, creator, type, tags or id contain every word of `query`, ignoring
+    /// case.
+    pub async fn search(&self, query: &str) -> Vec<LibraryEntry> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.entries()
+            .await
+            .into_iter()
+            .filter(|entry| {
+                let text = [
+                    entry.name.as_deref().unwrap_or_default(),
+                    entry.creator.as_deref().unwrap_or_default(),
+                    entry.asset_type.as_deref().unwrap_or_default(),
+                    &entry.tags.join(" "),
+                    &entry.asset_id.to_string(),
+                ]
+                .join(" ")
+                .to_lowercase();
+                words.iter().all(|word| text.contains(word.as_str()))
+            })
+            .collect()
+    }
+
+    /// Adds and removes tags of the entry for `version` of `asset_id`, `None` meaning the latest
+    /// version. `None` when that entry isn't in the library.
+    pub async fn tag(
+        &self,
+        asset_id: u64,
+        version: Option<u64>,
+        add: &[String],
+        remove: &[String],
+    ) -> std::io::Result<Option<LibraryEntry>> {
+        let _guard = self.lock.lock().await;
+        let mut index = self.read_index().await;
+        let Some(entry) = index
+            .entries
+            .iter_mut()
+            .find(|entry| entry.is(asset_id, version))
+        else {
+            return Ok(None);
+        };
+        entry.tags.retain(|tag| !remove.contains(tag));
+        for tag in add {
+            if !entry.tags.contains(tag) {
+                entry.tags.push(tag.clone());
+            }
+        }
+        let entry = entry.clone();
+        self.write_index(&index).await?;
+        Ok(Some(entry))
+    }
+
+    /// Removes the entry for `version` of `asset_id`, `None` meaning the latest version, and its
+    /// file. `None` when that entry isn't in the library.
+    pub async fn delete(
+        &self,
+        asset_id: u64,
+        version: Option<u64>,
+    ) -> std::io::Result<Option<LibraryEntry>> {
+        let _guard = self.lock.lock().await;
+        let mut index = self.read_index().await;
+        let Some(position) = index
+            .entries
+            .iter()
+            .position(|entry| entry.is(asset_id, version))
+        else {
+            return Ok(None);
+        };
+        let entry = index.entries.remove(position);
+        self.write_index(&index).await?;
+        self.remove_file(&index, &entry).await;
+        Ok(Some(entry))
+    }
+
+    /// Drops entries whose file is gone, and entries with the same content as an older one,
+    /// whose tags move to the entry that is kept.
+    pub async fn dedupe(&self) -> std::io::Result<DedupeReport> {
+        let _guard = self.lock.lock().await;
+        let mut index = self.read_index().await;
+        let mut report = DedupeReport::default();
+        index.entries.sort_by_key(|entry| entry.downloaded_at);
+
+        let mut kept: Vec<LibraryEntry> = Vec::new();
+        let mut by_hash: HashMap<String, usize> = HashMap::new();
+        for entry in std::mem::take(&mut index.entries) {
+            if !tokio::fs::try_exists(self.path_of(&entry))
+                .await
+                .unwrap_or(false)
+            {
+                report.missing_files.push(entry);
+                continue;
+            }
+            match by_hash.get(&entry.sha256) {
+                Some(&original) => {
+                    for tag in &entry.tags {
+                        if !kept[original].tags.contains(tag) {
+                            kept[original].tags.push(tag.clone());
+                        }
+                    }
+                    report.duplicates.push(entry);
+                }
+                None => {
+                    by_hash.insert(entry.sha256.clone(), kept.len());
+                    kept.push(entry);
+                }
+            }
+        }
+        index.entries = kept;
+        self.write_index(&index).await?;
+        for duplicate in &report.duplicates {
+            self.remove_file(&index, duplicate).await;
+        }
+        Ok(report)
+    }
+
+    /// Deletes the file of a removed entry, unless a remaining entry still uses it.
+    async fn remove_file(&self, index: &Index, removed: &LibraryEntry) {
+        if index.entries.iter().any(|entry| entry.file == removed.file) {
+            return;
+        }
+        let path = self.path_of(removed);
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            tracing::warn!("Could not delete {}: {e}", path.display());
+        }
+    }
+
+    async fn read_index(&self) -> Index {
+        let path = self.dir.join(INDEX_FILE_NAME);
+        let Ok(text) = tokio::fs::read_to_string(&path).await else {
+            return Index::default();
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring corrupt library index {}: {e}", path.display());
+            Index::default()
+        })
+    }
+
+    async fn write_index(&self, index: &Index) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(INDEX_FILE_NAME);
+        // Write then rename so a crash never leaves a partial index
+        let tmp = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(index)?).await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+}
+
+/// `name` reduced to ASCII letters, digits, `-` and `_`, other characters becoming `_`, so it
+/// can't point outside the library directory. `None` when nothing usable is left.
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_FILE_STEM_LEN)
+        .collect();
+    let name = name.trim_matches('_');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A library in a fresh temporary directory.
+    fn library() -> Library {
+        let dir = std::env::temp_dir().join(format!("library-test-{}", uuid::Uuid::new_v4()));
+        Library::new(&dir)
+    }
+
+    fn info(asset_id: u64, version: Option<u64>) -> AssetInfo {
+        AssetInfo {
+            asset_id,
+            version,
+            ..AssetInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn versions_are_separate_entries() {
+        let library = library();
+        let latest = library
+            .add(info(1, None), "1.rbxm", b"latest")
+            .await
+            .unwrap();
+        library
+            .add(info(1, Some(3)), "1_v3.rbxm", b"v3")
+            .await
+            .unwrap();
+        library
+            .tag(1, Some(3), &["old".to_string()], &[])
+            .await
+            .unwrap();
+        // Downloading the latest version again keeps version 3 and the tags of each entry
+        library
+            .add(info(1, None), "1.rbxm", b"latest")
+            .await
+            .unwrap();
+
+        assert_eq!(library.entries().await.len(), 2);
+        assert_eq!(library.get(1, Some(3)).await.unwrap().tags, ["old"]);
+        assert!(library.get(1, None).await.unwrap().tags.is_empty());
+        assert!(library.get(1, Some(4)).await.is_none());
+        assert!(library.path_of(&latest).exists());
+
+        let deleted = library.delete(1, Some(3)).await.unwrap().unwrap();
+        assert!(!library.path_of(&deleted).exists());
+        assert!(library.path_of(&latest).exists());
+        assert!(library.get(1, None).await.is_some());
+        std::fs::remove_dir_all(&library.dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_of_another_entry_is_not_overwritten() {
+        let library = library();
+        let first = library
+            .add(info(1, None), "Sword.rbxm", b"first")
+            .await
+            .unwrap();
+        let error = library
+            .add(info(2, None), "Sword.rbxm", b"second")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(library.path_of(&first)).unwrap(), b"first");
+        assert_eq!(
+            library.get(1, None).await.unwrap().sha256,
+            sha256_hex(b"first")
+        );
+
+        // The same asset may replace its own file
+        library
+            .add(info(1, None), "Sword.rbxm", b"again")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(library.path_of(&first)).unwrap(), b"again");
+        std::fs::remove_dir_all(&library.dir).unwrap();
+    }
+
+    #[test]
+    fn file_names_stay_in_the_library() {
+        assert_eq!(
+            sanitize_file_name("../../etc/passwd").as_deref(),
+            Some("etc_passwd")
+        );
+        assert_eq!(
+            sanitize_file_name("Car Kit v2").as_deref(),
+            Some("Car_Kit_v2")
+        );
+        assert_eq!(sanitize_file_name("../.."), None);
+    }
+
+    #[tokio::test]
+    async fn paths_resolve_inside_the_library() {
+        let library = library();
+        library
+            .add(info(1, None), "Sword.rbxm", b"sword")
+            .await
+            .unwrap();
+        let dir = std::fs::canonicalize(&library.dir).unwrap();
+        assert_eq!(
+            library.resolve("Sword").await.unwrap(),
+            dir.join("Sword.rbxm")
+        );
+        assert_eq!(
+            library.resolve("./Sword.rbxm").await.unwrap(),
+            dir.join("Sword.rbxm")
+        );
+        let name = format!("outside-{}.rbxm", uuid::Uuid::new_v4());
+        let outside = dir.parent().unwrap().join(&name);
+        std::fs::write(&outside, b"outside").unwrap();
+        for path in [&format!("../{name}"), outside.to_str().unwrap()] {
+            let error = library.resolve(path).await.unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied, "{path}");
+        }
+        std::fs::remove_file(outside).unwrap();
+        std::fs::remove_dir_all(&library.dir).unwrap();
+    }
+}
@@ -1,16 +1,45 @@
 use axum::routing::{get, post};
 use clap::Parser;
 use color_eyre::eyre::Result;
+use config::{Config, Overrides};
 use rbx_studio_server::*;
 use rmcp::ServiceExt;
+use secrets::Secrets;
 use std::io;
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing_subscriber::{self, EnvFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{self, EnvFilter, Layer};
+mod api_dump;
+mod asset_details;
+mod asset_file;
+mod cache;
+mod catalog;
+mod client_log;
+mod config;
+mod creator_store;
+mod datatypes;
+mod dynamic_tools;
 mod error;
+mod fetch;
+mod http;
 mod install;
+mod instance_path;
+mod library;
+mod markdown;
+mod pagination;
+mod ranking;
 mod rbx_studio_server;
+mod scanner;
+mod secrets;
+mod selector;
+mod shaping;
+mod util;
+mod validation;
+mod web_search;
 
 /// Simple MCP proxy for Roblox Studio
 /// Run without arguments to install the plugin
@@ -20,31 +49,76 @@ struct Args {
     /// Run as MCP server on stdio
     #[arg(short, long)]
     stdio: bool,
+
+    /// Config file to use instead of <config dir>/rbx-studio-mcp/config.toml
+    #[arg(long, env = "RBX_MCP_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Port the Studio plugin connects to
+    #[arg(long, env = "RBX_MCP_PORT")]
+    port: Option<u16>,
+
+    /// Asset library directory, where downloaded assets and their index are kept
+    #[arg(long, env = "RBX_MCP_ASSET_DIR")]
+    asset_dir: Option<PathBuf>,
+
+    /// Proxy URL for outgoing web requests
+    #[arg(long, env = "RBX_MCP_HTTP_PROXY")]
+    http_proxy: Option<String>,
+
+    /// Never go online: hide the web tools and answer searches from the cache
+    #[arg(long, env = "RBX_MCP_OFFLINE")]
+    offline: bool,
+
+    /// Read a secret (e.g. `tavily`) from stdin and store it in the OS keyring
+    #[arg(long, value_name = "NAME")]
+    store_secret: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_writer(io::stderr)
-        .with_target(false)
-        .with_thread_ids(true)
+    let (client_log_layer, client_log_forwarder) = client_log::layer();
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_target(false)
+                .with_thread_ids(true)
+                .with_filter(EnvFilter::from_default_env()),
+        )
+        .with(client_log_layer)
         .init();
 
     let args = Args::parse();
+    if let Some(name) = args.store_secret {
+        let mut value = String::new();
+        io::stdin().read_line(&mut value)?;
+        secrets::store_in_keyring(&name, &value)?;
+        println!("Stored {name} in the OS keyring");
+        return Ok(());
+    }
     if !args.stdio {
         return install::install().await;
     }
 
     tracing::debug!("Debug MCP tracing enabled");
 
+    let config = Arc::new(Config::load(args.config.as_deref())?.apply(Overrides {
+        port: args.port,
+        asset_dir: args.asset_dir,
+        proxy: args.http_proxy,
+        offline: args.offline,
+    }));
+    let port = config.port;
+    let secrets = Arc::new(Secrets::resolve(&config.api_keys));
+    let http = http::HttpClient::new(&config)?;
+
     let server_state = Arc::new(Mutex::new(AppState::new()));
 
     let (close_tx, close_rx) = tokio::sync::oneshot::channel();
 
-    let listener =
-        tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), STUDIO_PLUGIN_PORT)).await;
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::new(127, 0, 0, 1), port)).await;
 
     let server_state_clone = Arc::clone(&server_state);
     let server_handle = if let Ok(listener) = listener {
@@ -52,8 +126,12 @@ async fn main() -> Result<()> {
             .route("/request", get(request_handler))
             .route("/response", post(response_handler))
             .route("/proxy", post(proxy_handler))
+            .route(
+                "/tools",
+                get(list_tools_handler).post(register_tools_handler),
+            )
             .with_state(server_state_clone);
-        tracing::info!("This MCP instance is HTTP server listening on {STUDIO_PLUGIN_PORT}");
+        tracing::info!("This MCP instance is HTTP server listening on {port}");
         tokio::spawn(async {
             axum::serve(listener, app)
                 .with_graceful_shutdown(async move {
@@ -64,18 +142,30 @@ async fn main() -> Result<()> {
         })
     } else {
         tracing::info!("This MCP instance will use proxy since port is busy");
+        tokio::spawn(dud_tool_sync_loop(Arc::clone(&server_state), port));
         tokio::spawn(async move {
-            dud_proxy_loop(server_state_clone, close_rx).await;
+            dud_proxy_loop(server_state_clone, port, close_rx).await;
         })
     };
 
     // Create an instance of our counter router
-    let service = RBXStudioServer::new(Arc::clone(&server_state))
-        .serve(rmcp::transport::stdio())
-        .await
-        .inspect_err(|e| {
-            tracing::error!("serving error: {:?}", e);
-        })?;
+    let service = RBXStudioServer::new(
+        Arc::clone(&server_state),
+        client_log_forwarder.level(),
+        Arc::clone(&config),
+        secrets,
+        http,
+    )
+    .serve(rmcp::transport::stdio())
+    .await
+    .inspect_err(|e| {
+        tracing::error!("serving error: {:?}", e);
+    })?;
+    tokio::spawn(client_log_forwarder.forward(service.peer().clone()));
+    tokio::spawn(notify_tool_list_changed(
+        Arc::clone(&server_state),
+        service.peer().clone(),
+    ));
     service.waiting().await?;
 
     close_tx.send(()).ok();
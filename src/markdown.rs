@@ -0,0 +1,408 @@
+//! Readable Markdown from fetched HTML pages. Pure functions over the page source, so saved
+//! pages can be fed through [`html_to_markdown`] without going online.
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Elements whose content is never part of the readable page.
+const SKIPPED: [&str; 18] = [
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "embed",
+    "head", "nav", "footer", "aside", "form", "button", "select", "textarea", "input",
+];
+
+/// Elements that start a new block of Markdown.
+const BLOCKS: [&str; 32] = [
+    "html",
+    "body",
+    "main",
+    "article",
+    "section",
+    "header",
+    "div",
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "pre",
+    "blockquote",
+    "hr",
+    "table",
+    "figure",
+    "figcaption",
+    "details",
+    "summary",
+    "address",
+    "fieldset",
+    "hgroup",
+    "center",
+];
+
+/// Converts an HTML page to Markdown, keeping the main content (the first `article`, `main` or
+/// `[role=main]` element, otherwise `body`) and dropping navigation, scripts and forms.
+/// Relative links and images are resolved against `base` when given.
+pub fn html_to_markdown(html: &str, base: Option<&Url>) -> String {
+    let document = Html::parse_document(html);
+    let root = ["article", "main", "[role=main]", "body"]
+        .iter()
+        .filter_map(|selector| Selector::parse(selector).ok())
+        .find_map(|selector| document.select(&selector).next())
+        .unwrap_or_else(|| document.root_element());
+
+    let converter = Converter { base };
+    let mut blocks = Vec::new();
+    converter.blocks(root, &mut blocks);
+
+    let has_heading = blocks.first().is_some_and(|block| block.starts_with("# "));
+    if !has_heading {
+        if let Some(title) = page_title(&document) {
+            blocks.insert(0, format!("# {title}"));
+        }
+    }
+    blocks.join("\n\n")
+}
+
+fn page_title(document: &Html) -> Option<String> {
+    let selector = Selector::parse("title").ok()?;
+    let title = collapse_whitespace(
+        &document
+            .select(&selector)
+            .next()?
+            .text()
+            .collect::<String>(),
+    );
+    (!title.is_empty()).then_some(title)
+}
+
+struct Converter<'a> {
+    base: Option<&'a Url>,
+}
+
+impl Converter<'_> {
+    /// Appends the Markdown blocks of `parent`'s children to `out`.
+    fn blocks(&self, parent: ElementRef, out: &mut Vec<String>) {
+        let mut inline = String::new();
+        for child in parent.children() {
+            match child.value() {
+                Node::Text(text) => inline.push_str(&collapse_whitespace_keep_edges(text)),
+                Node::Element(element) => {
+                    let name = element.name();
+                    let Some(child) = ElementRef::wrap(child) else {
+                        continue;
+                    };
+                    if SKIPPED.contains(&name) {
+                        continue;
+                    }
+                    if BLOCKS.contains(&name) {
+                        flush(&mut inline, out);
+                        self.block(child, name, out);
+                    } else {
+                        inline.push_str(&self.inline(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        flush(&mut inline, out);
+    }
+
+    fn block(&self, element: ElementRef, name: &str, out: &mut Vec<String>) {
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                let text = collapse_whitespace(&self.inline(element));
+                if !text.is_empty() {
+                    out.push(format!("{} {text}", "#".repeat(level)));
+                }
+            }
+            "ul" | "ol" => {
+                let list = self.list(element, name == "ol");
+                if !list.is_empty() {
+                    out.push(list);
+                }
+            }
+            "pre" => {
+                let code: String = element.text().collect();
+                let language = element
+                    .select(&Selector::parse("code").expect("valid selector"))
+                    .next()
+                    .and_then(|code| {
+                        code.value()
+                            .classes()
+                            .find_map(|class| class.strip_prefix("language-"))
+                    })
+                    .unwrap_or("");
+                out.push(format!(
+                    "```{language}\n{}\n```",
+                    code.trim_end_matches('\n')
+                ));
+            }
+            "blockquote" => {
+                let mut inner = Vec::new();
+                self.blocks(element, &mut inner);
+                if !inner.is_empty() {
+                    out.push(prefix_lines(&inner.join("\n\n"), "> ", "> "));
+                }
+            }
+            "hr" => out.push("---".to_string()),
+            "table" => {
+                let table = self.table(element);
+                if !table.is_empty() {
+                    out.push(table);
+                }
+            }
+            _ => self.blocks(element, out),
+        }
+    }
+
+    fn list(&self, list: ElementRef, ordered: bool) -> String {
+        let mut lines = Vec::new();
+        let items = list
+            .child_elements()
+            .filter(|child| child.value().name() == "li");
+        for (index, item) in items.enumerate() {
+            let marker = if ordered {
+                format!("{}. ", index + 1)
+            } else {
+                "- ".to_string()
+            };
+            let mut blocks = Vec::new();
+            self.blocks(item, &mut blocks);
+            let body = blocks.join("\n");
+            if body.is_empty() {
+                continue;
+            }
+            let indent = " ".repeat(marker.len());
+            lines.push(prefix_lines(&body, &marker, &indent));
+        }
+        lines.join("\n")
+    }
+
+    fn table(&self, table: ElementRef) -> String {
+        let rows_selector = Selector::parse("tr").expect("valid selector");
+        let rows: Vec<Vec<String>> = table
+            .select(&rows_selector)
+            .map(|row| {
+                row.child_elements()
+                    .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                    .map(|cell| collapse_whitespace(&self.inline(cell)).replace('|', "\\|"))
+                    .collect()
+            })
+            .filter(|cells: &Vec<String>| !cells.is_empty())
+            .collect();
+        let Some(columns) = rows.iter().map(Vec::len).max() else {
+            return String::new();
+        };
+        let mut lines = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(columns, String::new());
+            lines.push(format!("| {} |", cells.join(" | ")));
+            if index == 0 {
+                lines.push(format!("|{}", " --- |".repeat(columns)));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn inline(&self, element: ElementRef) -> String {
+        let name = element.value().name();
+        if SKIPPED.contains(&name) {
+            return String::new();
+        }
+        match name {
+            "br" => return "\n".to_string(),
+            "img" => {
+                let alt = collapse_whitespace(element.attr("alt").unwrap_or(""));
+                return match element.attr("src") {
+                    Some(src) if !src.starts_with("data:") => {
+                        format!("![{alt}]({})", self.resolve(src))
+                    }
+                    _ => alt,
+                };
+            }
+            "code" | "kbd" | "samp" => {
+                let code: String = element.text().collect();
+                let code = collapse_whitespace(&code);
+                return if code.is_empty() {
+                    code
+                } else {
+                    format!("`{code}`")
+                };
+            }
+            _ => {}
+        }
+
+        let mut text = String::new();
+        for child in element.children() {
+            match child.value() {
+                Node::Text(t) => text.push_str(&collapse_whitespace_keep_edges(t)),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        text.push_str(&self.inline(child));
+                    }
+                }
+                _ => {}
+            }
+        }
+        match name {
+            "a" => match element.attr("href") {
+                Some(href)
+                    if !text.trim().is_empty()
+                        && !href.starts_with('#')
+                        && !href.starts_with("javascript:") =>
+                {
+                    format!("[{}]({})", text.trim(), self.resolve(href))
+                }
+                _ => text,
+            },
+            "strong" | "b" => emphasize(&text, "**"),
+            "em" | "i" => emphasize(&text, "_"),
+            "del" | "s" => emphasize(&text, "~~"),
+            _ => text,
+        }
+    }
+
+    fn resolve(&self, href: &str) -> String {
+        self.base
+            .and_then(|base| base.join(href).ok())
+            .map(|url| url.to_string())
+            .unwrap_or_else(|| href.to_string())
+    }
+}
+
+/// Ends the paragraph collected in `inline`, keeping `br` line breaks.
+fn flush(inline: &mut String, out: &mut Vec<String>) {
+    let paragraph = inline
+        .lines()
+        .map(collapse_whitespace)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !paragraph.is_empty() {
+        out.push(paragraph);
+    }
+    inline.clear();
+}
+
+fn emphasize(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    // Keep surrounding spaces outside the markers, or they stop being emphasis
+    let leading = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{leading}{marker}{trimmed}{marker}{trailing}")
+}
+
+/// Puts `first` before the first line of `text` and `rest` before every other non-empty line.
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| match (index, line.is_empty()) {
+            (0, _) => format!("{first}{line}"),
+            (_, true) => rest.trim_end().to_string(),
+            _ => format!("{rest}{line}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Like [`collapse_whitespace`], but a leading or trailing run becomes a single space, so
+/// words from neighbouring nodes stay apart.
+fn collapse_whitespace_keep_edges(text: &str) -> String {
+    let collapsed = collapse_whitespace(text);
+    if collapsed.is_empty() {
+        return if text.is_empty() {
+            String::new()
+        } else {
+            " ".to_string()
+        };
+    }
+    let leading = if text.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if text.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{leading}{collapsed}{trailing}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converts `tests/fixtures/markdown/{name}.html` and compares it with `{name}.md`.
+    fn assert_fixture(name: &str) {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/markdown");
+        let html = std::fs::read_to_string(format!("{dir}/{name}.html")).unwrap();
+        let expected = std::fs::read_to_string(format!("{dir}/{name}.md")).unwrap();
+        let base = Url::parse("https://example.com/docs/page").unwrap();
+        assert_eq!(html_to_markdown(&html, Some(&base)), expected.trim_end());
+    }
+
+    #[test]
+    fn doc_page() {
+        assert_fixture("doc_page");
+    }
+
+    #[test]
+    fn forum_post() {
+        assert_fixture("forum_post");
+    }
+
+    #[test]
+    fn navigation_and_boilerplate_are_dropped() {
+        assert_fixture("boilerplate");
+    }
+
+    #[test]
+    fn table_rows_are_padded_and_pipes_escaped() {
+        let html = "<table><tr><th>a</th><th>b|c</th></tr><tr><td>1</td></tr></table>";
+        assert_eq!(
+            html_to_markdown(html, None),
+            "| a | b\\|c |\n| --- | --- |\n| 1 |  |"
+        );
+    }
+
+    #[test]
+    fn code_block_keeps_language_and_indentation() {
+        let html = "<pre><code class=\"language-lua\">if x then\n\tprint(x)\nend\n</code></pre>";
+        assert_eq!(
+            html_to_markdown(html, None),
+            "```lua\nif x then\n\tprint(x)\nend\n```"
+        );
+    }
+
+    #[test]
+    fn title_is_used_when_the_page_has_no_heading() {
+        let html =
+            "<html><head><title> Page \n Title </title></head><body><h2>Part</h2></body></html>";
+        assert_eq!(html_to_markdown(html, None), "# Page Title\n\n## Part");
+    }
+}
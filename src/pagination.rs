@@ -0,0 +1,307 @@
+use rmcp::model::{CallToolResult, Content};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+/// Rough character budget of a single page handed back to the MCP client.
+pub const PAGE_CHAR_BUDGET: usize = 20_000;
+/// Schema description of the `next_page` argument of every paginated tool.
+pub const NEXT_PAGE_DESCRIPTION: &str = "Cursor from a previous paginated response. Fetches the next page of that output. The other arguments are ignored, but required ones must still be passed";
+/// How many paginated outputs are kept around before the oldest is evicted.
+const MAX_STORED_OUTPUTS: usize = 16;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PageInfo {
+    page: usize,
+    total_pages: usize,
+    /// What `first`, `last` and `total` count: `results` for result lists, `lines` otherwise.
+    unit: &'static str,
+    first: usize,
+    last: usize,
+    total: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+}
+
+pub struct Page {
+    text: String,
+    info: PageInfo,
+}
+
+impl Page {
+    pub fn into_call_tool_result(self) -> CallToolResult {
+        let info = serde_json::to_string(&self.info).unwrap_or_default();
+        CallToolResult::success(vec![
+            Content::text(self.text),
+            Content::text(format!("[pagination] {info}")),
+        ])
+    }
+}
+
+struct StoredPage {
+    text: String,
+    first: usize,
+    last: usize,
+}
+
+struct StoredOutput {
+    pages: Vec<StoredPage>,
+    unit: &'static str,
+    total: usize,
+}
+
+#[derive(Default)]
+pub struct PageStore {
+    outputs: HashMap<Uuid, StoredOutput>,
+    order: VecDeque<Uuid>,
+}
+
+impl PageStore {
+    /// Splits `output` into pages if it is over budget, keeping the remainder for later
+    /// `next_page` calls. Returns `None` when the output fits on a single page.
+    pub fn paginate(&mut self, output: &str) -> Option<Page> {
+        if output.len() <= PAGE_CHAR_BUDGET {
+            return None;
+        }
+        let stored = split_output(output);
+        let id = Uuid::new_v4();
+        self.outputs.insert(id, stored);
+        self.order.push_back(id);
+        while self.order.len() > MAX_STORED_OUTPUTS {
+            if let Some(evicted) = self.order.pop_front() {
+                self.outputs.remove(&evicted);
+            }
+        }
+        self.page(&cursor_for(id, 0)).ok()
+    }
+
+    /// Returns the page a cursor points to.
+    pub fn page(&self, cursor: &str) -> Result<Page, String> {
+        let (id, index) =
+            parse_cursor(cursor).ok_or_else(|| format!("Invalid cursor: {cursor}"))?;
+        let output = self.outputs.get(&id).ok_or_else(|| {
+            "Cursor has expired, run the tool again without next_page".to_string()
+        })?;
+        let page = output
+            .pages
+            .get(index)
+            .ok_or_else(|| format!("Cursor points past the last page: {cursor}"))?;
+        let next_page = (index + 1 < output.pages.len()).then(|| cursor_for(id, index + 1));
+        Ok(Page {
+            text: page.text.clone(),
+            info: PageInfo {
+                page: index + 1,
+                total_pages: output.pages.len(),
+                unit: output.unit,
+                first: page.first,
+                last: page.last,
+                total: output.total,
+                next_page,
+            },
+        })
+    }
+}
+
+fn cursor_for(id: Uuid, index: usize) -> String {
+    format!("{}.{index}", id.simple())
+}
+
+fn parse_cursor(cursor: &str) -> Option<(Uuid, usize)> {
+    let (id, index) = cursor.trim().split_once('.')?;
+    Some((Uuid::parse_str(id).ok()?, index.parse().ok()?))
+}
+
+/// Result lists are paged per entry and keep the rest of their envelope on every page,
+/// anything else is pretty-printed and paged per line.
+fn split_output(output: &str) -> StoredOutput {
+    let json = serde_json::from_str::<serde_json::Value>(output).ok();
+    if let Some(serde_json::Value::Object(mut envelope)) = json.clone() {
+        if let Some(serde_json::Value::Array(items)) = envelope.remove("results") {
+            return split_results(envelope, items);
+        }
+    }
+    let text = json
+        .and_then(|json| serde_json::to_string_pretty(&json).ok())
+        .unwrap_or_else(|| output.to_string());
+    split_lines(&text)
+}
+
+fn split_results(
+    envelope: serde_json::Map<String, serde_json::Value>,
+    items: Vec<serde_json::Value>,
+) -> StoredOutput {
+    let total = items.len();
+    let mut pages = Vec::new();
+    let mut current = Vec::new();
+    let mut current_len = 0;
+    let mut first = 1;
+    let render = |items: Vec<serde_json::Value>| {
+        let mut page = envelope.clone();
+        page.insert("results".to_string(), serde_json::Value::Array(items));
+        serde_json::Value::Object(page).to_string()
+    };
+    for (index, item) in items.into_iter().enumerate() {
+        let len = item.to_string().len() + 1;
+        if !current.is_empty() && current_len + len > PAGE_CHAR_BUDGET {
+            pages.push(StoredPage {
+                text: render(std::mem::take(&mut current)),
+                first,
+                last: index,
+            });
+            current_len = 0;
+            first = index + 1;
+        }
+        current_len += len;
+        current.push(item);
+    }
+    pages.push(StoredPage {
+        text: render(current),
+        first,
+        last: total,
+    });
+    StoredOutput {
+        pages,
+        unit: "results",
+        total,
+    }
+}
+
+fn split_lines(text: &str) -> StoredOutput {
+    let lines: Vec<&str> = text.lines().collect();
+    let total = lines.len();
+    let mut pages = Vec::new();
+    let mut current = String::new();
+    let mut first = 1;
+    for (index, line) in lines.iter().enumerate() {
+        if !current.is_empty() && current.len() + line.len() + 1 > PAGE_CHAR_BUDGET {
+            pages.push(StoredPage {
+                text: std::mem::take(&mut current),
+                first,
+                last: index,
+            });
+            first = index + 1;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    pages.push(StoredPage {
+        text: current,
+        first,
+        last: total,
+    });
+    StoredOutput {
+        pages,
+        unit: "lines",
+        total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// `count` lines of 100 characters, newline included.
+    fn lines(count: usize) -> String {
+        (0..count).map(|i| format!("{i:099}\n")).collect()
+    }
+
+    #[test]
+    fn small_output_is_not_paged() {
+        let mut store = PageStore::default();
+        assert!(store.paginate(&lines(PAGE_CHAR_BUDGET / 100)).is_none());
+        assert!(store.outputs.is_empty());
+    }
+
+    #[test]
+    fn lines_fill_pages_up_to_the_budget() {
+        let mut store = PageStore::default();
+        let output = lines(450);
+        let first = store.paginate(&output).unwrap();
+        assert_eq!(first.text.len(), PAGE_CHAR_BUDGET);
+        assert_eq!(
+            (first.info.page, first.info.total_pages, first.info.unit),
+            (1, 3, "lines")
+        );
+        assert_eq!(
+            (first.info.first, first.info.last, first.info.total),
+            (1, 200, 450)
+        );
+
+        let second = store.page(first.info.next_page.as_ref().unwrap()).unwrap();
+        assert_eq!((second.info.first, second.info.last), (201, 400));
+        let third = store.page(second.info.next_page.as_ref().unwrap()).unwrap();
+        assert_eq!((third.info.first, third.info.last), (401, 450));
+        assert_eq!(third.info.next_page, None);
+        assert_eq!(first.text + &second.text + &third.text, output);
+    }
+
+    #[test]
+    fn result_lists_keep_their_envelope() {
+        let results: Vec<_> = (0..600)
+            .map(|i| json!({ "name": format!("Part{i}"), "path": format!("Workspace.Model.Part{i}") }))
+            .collect();
+        let output = json!({ "query": "Part", "results": results }).to_string();
+        let mut store = PageStore::default();
+        let mut page = store.paginate(&output).unwrap();
+        let mut seen = Vec::new();
+        loop {
+            assert_eq!(page.info.unit, "results");
+            assert!(
+                page.text.len() <= PAGE_CHAR_BUDGET + 100,
+                "{}",
+                page.text.len()
+            );
+            let json: serde_json::Value = serde_json::from_str(&page.text).unwrap();
+            assert_eq!(json["query"], "Part");
+            let names = json["results"].as_array().unwrap();
+            assert_eq!(names.len(), page.info.last - page.info.first + 1);
+            seen.extend(names.iter().map(|item| item["name"].clone()));
+            match page.info.next_page {
+                Some(cursor) => page = store.page(&cursor).unwrap(),
+                None => break,
+            }
+        }
+        let expected: Vec<_> = (0..600).map(|i| json!(format!("Part{i}"))).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn bad_cursors() {
+        let mut store = PageStore::default();
+        let first = store.paginate(&lines(300)).unwrap();
+        let cursor = first.info.next_page.unwrap();
+        let (id, _) = cursor.split_once('.').unwrap();
+
+        assert_eq!(
+            store.page("not-a-cursor").err().unwrap(),
+            "Invalid cursor: not-a-cursor"
+        );
+        assert_eq!(
+            store.page(&format!("{id}.2")).err().unwrap(),
+            format!("Cursor points past the last page: {id}.2")
+        );
+        let unknown = cursor_for(Uuid::new_v4(), 1);
+        assert_eq!(
+            store.page(&unknown).err().unwrap(),
+            "Cursor has expired, run the tool again without next_page"
+        );
+    }
+
+    #[test]
+    fn oldest_outputs_are_evicted() {
+        let mut store = PageStore::default();
+        let cursors: Vec<_> = (0..=MAX_STORED_OUTPUTS)
+            .map(|_| store.paginate(&lines(300)).unwrap().info.next_page.unwrap())
+            .collect();
+        assert_eq!(store.outputs.len(), MAX_STORED_OUTPUTS);
+        assert!(store
+            .page(&cursors[0])
+            .err()
+            .unwrap()
+            .starts_with("Cursor has expired"));
+        for cursor in &cursors[1..] {
+            assert!(store.page(cursor).is_ok());
+        }
+    }
+}
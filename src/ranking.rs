@@ -0,0 +1,323 @@
+//! Picks what `install_system` installs: candidates from a Creator Store search, scored on who
+//! made them, how players rate them, how recently they were updated and what their scripts do.
+
+use crate::config::BlockLevel;
+use crate::scanner::{ScanReport, Severity, Verdict};
+use crate::util::now_secs;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Lowest score `confident_pick` installs without asking
+const PICK_MIN_SCORE: u32 = 60;
+/// Points the pick must lead the runner-up by
+const PICK_MIN_LEAD: u32 = 10;
+const SECS_PER_DAY: u64 = 86_400;
+
+/// A search result, before it is downloaded.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub asset_id: u64,
+    pub name: String,
+    pub creator: Option<String>,
+    pub verified: bool,
+    pub up_votes: u64,
+    pub down_votes: u64,
+    pub favorites: Option<u64>,
+    /// Last update, or creation when never updated, as an RFC 3339 timestamp
+    pub updated: Option<String>,
+}
+
+impl Candidate {
+    /// Candidates in a toolbox-service `assets:search` response, in search order.
+    pub fn from_search(json: &Value) -> Vec<Self> {
+        let Some(assets) = json.get("creatorStoreAssets").and_then(Value::as_array) else {
+            return Vec::new();
+        };
+        assets
+            .iter()
+            .filter_map(|item| {
+                let asset = item.get("asset")?;
+                let creator = item.get("creator");
+                let voting = item.get("voting");
+                let text =
+                    |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+                let count = |key: &str| {
+                    voting
+                        .and_then(|v| v.get(key))
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0)
+                };
+                Some(Self {
+                    asset_id: asset.get("id")?.as_u64()?,
+                    name: text(asset.get("name")).unwrap_or_else(|| "Unknown".to_string()),
+                    creator: text(creator.and_then(|c| c.get("name"))),
+                    verified: ["verified", "isVerifiedCreator"].iter().any(|key| {
+                        creator
+                            .and_then(|c| c.get(key))
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false)
+                    }),
+                    up_votes: count("upVotes"),
+                    down_votes: count("downVotes"),
+                    favorites: asset.get("favoriteCount").and_then(Value::as_u64),
+                    updated: text(asset.get("updateTime").or_else(|| asset.get("createTime"))),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A scored candidate, with the reasons behind the score.
+#[derive(Debug, Serialize, Clone)]
+pub struct Ranked {
+    pub asset_id: u64,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator: Option<String>,
+    /// 0 to 100
+    pub score: u32,
+    /// Refused by the backdoor scan under `scanner.block`; never picked
+    pub blocked: bool,
+    /// Backdoor scan verdict, `None` when the file couldn't be downloaded or isn't a model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<Verdict>,
+    pub reasons: Vec<String>,
+}
+
+/// Scores `candidate`. `scan` is the backdoor scan of its file, `None` when it couldn't be
+/// downloaded or read.
+pub fn score(candidate: &Candidate, scan: Option<&ScanReport>, block: BlockLevel) -> Ranked {
+    let mut points = 0.0;
+    let mut reasons = Vec::new();
+
+    if candidate.verified {
+        points += 20.0;
+        reasons.push("verified creator (+20)".to_string());
+    }
+
+    let votes = candidate.up_votes + candidate.down_votes;
+    // Smoothed so a single upvote doesn't count as 100% approval
+    let approval = (candidate.up_votes as f64 + 1.0) / (votes as f64 + 2.0);
+    let rating = 15.0 * approval + 10.0 * (((votes + 1) as f64).log10() / 3.0).min(1.0);
+    points += rating;
+    reasons.push(format!(
+        "{} of {votes} votes up (+{rating:.0})",
+        candidate.up_votes
+    ));
+
+    if let Some(favorites) = candidate.favorites {
+        let popularity = 10.0 * (((favorites + 1) as f64).log10() / 4.0).min(1.0);
+        points += popularity;
+        reasons.push(format!("{favorites} favorites (+{popularity:.0})"));
+    }
+
+    match candidate.updated.as_deref().and_then(unix_days) {
+        Some(days) => {
+            let age_years = now_secs().saturating_sub(days * SECS_PER_DAY) as f64
+                / (365.0 * SECS_PER_DAY as f64);
+            // Full marks within a year, nothing after five
+            let recency = 15.0 * (1.0 - (age_years - 1.0).max(0.0) / 4.0).max(0.0);
+            points += recency;
+            reasons.push(format!("updated {age_years:.1} years ago (+{recency:.0})"));
+        }
+        None => reasons.push("update date unknown (+0)".to_string()),
+    }
+
+    let mut blocked = false;
+    match scan {
+        Some(report) => {
+            blocked = report.blocked_by(block);
+            match report.scripts_scanned {
+                0 => reasons.push("no scripts, may not be a working system (+0)".to_string()),
+                1..=100 => {
+                    points += 20.0;
+                    reasons.push(format!("{} scripts (+20)", report.scripts_scanned));
+                }
+                scripts => {
+                    points += 10.0;
+                    reasons.push(format!("{scripts} scripts, unusually many (+10)"));
+                }
+            }
+            let danger = report
+                .findings
+                .iter()
+                .filter(|finding| finding.severity == Severity::Danger)
+                .count();
+            let warnings = report.findings.len() - danger;
+            if danger > 0 {
+                points -= 50.0;
+                reasons.push(format!(
+                    "{danger} backdoor patterns such as require(<id>) or loadstring (-50)"
+                ));
+            }
+            if warnings > 0 {
+                let penalty = (3 * warnings).min(15);
+                points -= penalty as f64;
+                reasons.push(format!("{warnings} scan warnings (-{penalty})"));
+            }
+            if blocked {
+                reasons.push(format!(
+                    "refused by the backdoor scan, which found it {}",
+                    report.verdict.as_str()
+                ));
+            }
+        }
+        None => reasons.push("could not be downloaded and scanned (+0)".to_string()),
+    }
+
+    Ranked {
+        asset_id: candidate.asset_id,
+        name: candidate.name.clone(),
+        creator: candidate.creator.clone(),
+        score: points.clamp(0.0, 100.0).round() as u32,
+        blocked,
+        verdict: scan.map(|report| report.verdict),
+        reasons,
+    }
+}
+
+/// Sorts best first, blocked candidates last.
+pub fn sort(ranked: &mut [Ranked]) {
+    ranked.sort_by_key(|candidate| (candidate.blocked, std::cmp::Reverse(candidate.score)));
+}
+
+/// The top of a sorted ranking, when it scanned clean and clearly beats the rest.
+pub fn confident_pick(ranked: &[Ranked]) -> Option<&Ranked> {
+    let best = ranked.first()?;
+    let runner_up = ranked.get(1).filter(|r| !r.blocked).map_or(0, |r| r.score);
+    (!best.blocked
+        && best.verdict == Some(Verdict::Clean)
+        && best.score >= PICK_MIN_SCORE
+        && best.score >= runner_up + PICK_MIN_LEAD)
+        .then_some(best)
+}
+
+/// Days from 1970-01-01 to the date an RFC 3339 timestamp starts with.
+fn unix_days(timestamp: &str) -> Option<u64> {
+    let mut parts = timestamp.get(..10)?.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    // Howard Hinnant's days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    u64::try_from(era * 146_097 + day_of_era - 719_468).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Finding;
+
+    fn popular() -> Candidate {
+        Candidate {
+            asset_id: 1,
+            name: "Door".to_string(),
+            creator: Some("Builder".to_string()),
+            verified: true,
+            up_votes: 1000,
+            down_votes: 0,
+            favorites: Some(9_999),
+            updated: None,
+        }
+    }
+
+    fn report(verdict: Verdict, severities: &[Severity]) -> ScanReport {
+        ScanReport {
+            verdict,
+            scripts_scanned: 5,
+            findings: severities
+                .iter()
+                .map(|&severity| Finding {
+                    rule: "test",
+                    severity,
+                    script: "Door.Script".to_string(),
+                    line: Some(1),
+                    excerpt: String::new(),
+                    message: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn ranked(asset_id: u64, score: u32, blocked: bool) -> Ranked {
+        Ranked {
+            asset_id,
+            name: asset_id.to_string(),
+            creator: None,
+            score,
+            blocked,
+            verdict: Some(if blocked {
+                Verdict::Malicious
+            } else {
+                Verdict::Clean
+            }),
+            reasons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn clean_popular_model_scores_well() {
+        let scan = report(Verdict::Clean, &[]);
+        let ranked = score(&popular(), Some(&scan), BlockLevel::Malicious);
+        // 20 verified + 25 votes + 10 favorites + 20 scripts
+        assert_eq!(ranked.score, 75);
+        assert!(!ranked.blocked);
+    }
+
+    #[test]
+    fn backdoor_patterns_cost_points_and_block() {
+        let scan = report(Verdict::Malicious, &[Severity::Danger, Severity::Warning]);
+        let ranked = score(&popular(), Some(&scan), BlockLevel::Malicious);
+        assert_eq!(ranked.score, 75 - 50 - 3);
+        assert!(ranked.blocked);
+        let ranked = score(&popular(), Some(&scan), BlockLevel::Never);
+        assert!(!ranked.blocked);
+    }
+
+    #[test]
+    fn unscanned_model_gets_no_script_points() {
+        let ranked = score(&popular(), None, BlockLevel::Malicious);
+        assert_eq!(ranked.score, 55);
+        assert_eq!(ranked.verdict, None);
+        assert_eq!(confident_pick(&[ranked]).map(|r| r.asset_id), None);
+    }
+
+    #[test]
+    fn pick_needs_the_minimum_score() {
+        assert_eq!(
+            confident_pick(&[ranked(1, 60, false)]).map(|r| r.asset_id),
+            Some(1)
+        );
+        assert_eq!(
+            confident_pick(&[ranked(1, 59, false)]).map(|r| r.asset_id),
+            None
+        );
+    }
+
+    #[test]
+    fn pick_needs_a_clear_lead() {
+        let close = [ranked(1, 80, false), ranked(2, 71, false)];
+        assert_eq!(confident_pick(&close).map(|r| r.asset_id), None);
+        let clear = [ranked(1, 80, false), ranked(2, 70, false)];
+        assert_eq!(confident_pick(&clear).map(|r| r.asset_id), Some(1));
+    }
+
+    #[test]
+    fn blocked_candidates_are_never_picked_or_compared() {
+        let mut candidates = vec![
+            ranked(1, 95, true),
+            ranked(2, 80, false),
+            ranked(3, 78, true),
+        ];
+        sort(&mut candidates);
+        assert_eq!(confident_pick(&candidates).map(|r| r.asset_id), Some(2));
+        assert_eq!(
+            confident_pick(&[ranked(1, 95, true)]).map(|r| r.asset_id),
+            None
+        );
+    }
+}
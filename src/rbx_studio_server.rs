@@ -1,27 +1,57 @@
+use crate::api_dump::{self, ApiIndex, MemberRef};
+use crate::asset_details::{AssetDetails, EconomyDetails};
+use crate::asset_file::{self, AssetFile, ContentKind, InspectOptions};
+use crate::cache::{self, CacheControl, CacheNamespace, ResponseCache};
+use crate::catalog;
+use crate::client_log::ClientLogLevel;
+use crate::config::{BlockLevel, Config};
+use crate::creator_store::SearchFilters;
+use crate::datatypes::{self, PropertyValue};
+use crate::dynamic_tools::{DynamicToolCall, DynamicToolList, DynamicTools};
 use crate::error::Result;
+use crate::fetch;
+use crate::http::{HttpClient, HttpError};
+use crate::instance_path::{InstancePath, PathPattern};
+use crate::library::{self, AssetInfo, Library, LibraryEntry};
+use crate::pagination::{PageStore, NEXT_PAGE_DESCRIPTION};
+use crate::ranking::{self, Candidate};
+use crate::scanner::{self, ScanReport};
+use crate::secrets::{self, Secret, Secrets};
+use crate::selector::{Selector, SelectorError};
+use crate::shaping::OutputShape;
+use crate::validation;
+use crate::web_search::{self, WebSearchRequest};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{extract::State, Json};
+use base64::prelude::{Engine, BASE64_STANDARD};
 use color_eyre::eyre::{Error, OptionExt};
 use rmcp::{
-    handler::server::tool::Parameters,
+    handler::server::tool::{Parameters, ToolCallContext},
     model::{
-        CallToolResult, Content, Implementation, ProtocolVersion, ServerCapabilities, ServerInfo,
+        CallToolRequestParam, CallToolResult, Content, Implementation, ListToolsResult,
+        PaginatedRequestParam, ProtocolVersion, ServerCapabilities, ServerInfo,
+        SetLevelRequestParam,
     },
-    schemars, tool, tool_handler, tool_router, ErrorData, ServerHandler,
+    schemars,
+    service::{Peer, RequestContext},
+    tool, tool_router, ErrorData, RoleServer, ServerHandler,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 use std::sync::Arc;
 use tokio::sync::oneshot::Receiver;
-use tokio::sync::{mpsc, watch, Mutex};
+use tokio::sync::{mpsc, watch, Mutex, OnceCell};
 use tokio::time::Duration;
 use uuid::Uuid;
-// use scraper::{Html, Selector};
 
 pub const STUDIO_PLUGIN_PORT: u16 = 44755;
 const LONG_POLL_DURATION: Duration = Duration::from_secs(15);
+/// Most distinct meshes `inspect_asset_file` downloads to count triangles.
+const MAX_INSPECTED_MESHES: usize = 50;
+/// Most search results `install_system` downloads and ranks.
+const MAX_INSTALL_CANDIDATES: u32 = 30;
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ToolArguments {
@@ -40,6 +70,8 @@ pub struct AppState {
     output_map: HashMap<Uuid, mpsc::UnboundedSender<Result<String>>>,
     waiter: watch::Receiver<()>,
     trigger: watch::Sender<()>,
+    pages: PageStore,
+    dynamic_tools: DynamicTools,
 }
 pub type PackedState = Arc<Mutex<AppState>>;
 
@@ -51,6 +83,8 @@ impl AppState {
             output_map: HashMap::new(),
             waiter,
             trigger,
+            pages: PageStore::default(),
+            dynamic_tools: DynamicTools::new(),
         }
     }
 }
@@ -73,15 +107,25 @@ impl ToolArguments {
 #[derive(Clone)]
 pub struct RBXStudioServer {
     state: PackedState,
+    log_level: ClientLogLevel,
+    config: Arc<Config>,
+    secrets: Arc<Secrets>,
+    http: HttpClient,
+    cache: ResponseCache,
+    library: Arc<Library>,
+    api: Arc<OnceCell<ApiIndex>>,
     tool_router: rmcp::handler::server::tool::ToolRouter<Self>,
 }
 
-#[tool_handler]
 impl ServerHandler for RBXStudioServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some(
                 "User run_command to query data from Roblox Studio place or to change it"
@@ -89,6 +133,145 @@ impl ServerHandler for RBXStudioServer {
             ),
         }
     }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.log_level.set(request.level);
+        tracing::debug!("Client log level set to {:?}", request.level);
+        Ok(())
+    }
+
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if !self.config.is_tool_enabled(&request.name) {
+            return Err(ErrorData::invalid_params(
+                format!("Tool {} is disabled in the server config", request.name),
+                None,
+            ));
+        }
+        if self.config.is_blocked_offline(&request.name) {
+            let error = serde_json::json!({
+                "error": "offline",
+                "tool": request.name,
+                "message": format!("{} needs network access, but the server is running in offline mode", request.name),
+            });
+            return Ok(CallToolResult::error(vec![Content::text(
+                error.to_string(),
+            )]));
+        }
+        if let Some(secret) = Secret::required_by(&request.name, &self.config) {
+            if !self.secrets.is_configured(secret) {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    secrets::not_configured(secret),
+                )]));
+            }
+        }
+        let max_tokens = self
+            .config
+            .policy(&request.name)
+            .and_then(|policy| policy.max_tokens)
+            .filter(|_| self.shapes_output(&request.name));
+        if let Some(max_tokens) = max_tokens {
+            request
+                .arguments
+                .get_or_insert_with(Default::default)
+                .entry("max_tokens")
+                .or_insert(max_tokens.into());
+        }
+        if let Some(timeout) = self.config.tool_timeout(&request.name) {
+            let name = request.name.clone();
+            return tokio::time::timeout(timeout, self.route_tool_call(request, context))
+                .await
+                .unwrap_or_else(|_| {
+                    Ok(CallToolResult::error(vec![Content::text(format!(
+                        "Tool {name} timed out after {}s",
+                        timeout.as_secs()
+                    ))]))
+                });
+        }
+        self.route_tool_call(request, context).await
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        let mut tools = self.tool_router.list_all();
+        tools.extend(self.state.lock().await.dynamic_tools.list());
+        tools.retain(|tool| {
+            self.config.is_tool_enabled(&tool.name)
+                && !self.config.is_blocked_offline(&tool.name)
+                && Secret::required_by(&tool.name, &self.config)
+                    .is_none_or(|secret| self.secrets.is_configured(secret))
+        });
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+}
+
+impl RBXStudioServer {
+    async fn route_tool_call(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if !self.tool_router.has_route(&request.name) {
+            let is_dynamic = self
+                .state
+                .lock()
+                .await
+                .dynamic_tools
+                .contains(&request.name);
+            if is_dynamic {
+                return self
+                    .generic_tool_run(ToolArgumentValues::DynamicTool(DynamicToolCall {
+                        name: request.name.to_string(),
+                        arguments: serde_json::Value::Object(request.arguments.unwrap_or_default()),
+                    }))
+                    .await;
+            }
+        }
+        let tcc = ToolCallContext::new(self, request, context);
+        self.tool_router.call(tcc).await
+    }
+
+    /// Whether built-in tool `name` takes the `max_tokens` of [`OutputShape`].
+    fn shapes_output(&self, name: &str) -> bool {
+        self.tool_router.map.get(name).is_some_and(|route| {
+            route
+                .attr
+                .input_schema
+                .get("properties")
+                .and_then(serde_json::Value::as_object)
+                .is_some_and(|properties| properties.contains_key("max_tokens"))
+        })
+    }
+
+    /// The API dump index, loaded on first use.
+    async fn api_index(&self) -> &ApiIndex {
+        self.api
+            .get_or_init(|| async {
+                let cache_dir = self.config.cache_dir();
+                let index =
+                    ApiIndex::load(&self.config.api_dump, cache_dir.as_deref(), &self.http).await;
+                tracing::info!("Loaded API index from {}", index.source);
+                index
+            })
+            .await
+    }
+
+    fn tavily_api_key(&self) -> Result<String, ErrorData> {
+        self.secrets
+            .require(Secret::TavilyApiKey)
+            .map(str::to_string)
+            .map_err(|e| ErrorData::invalid_request(e, None))
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -100,6 +283,7 @@ struct RunCommand {
 }
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct InsertModel {
+    #[schemars(description = "Search query; the top Creator Store model result is inserted")]
     query: Option<String>,
     #[schemars(description = "Optional Asset ID to insert directly")]
     asset_id: Option<u64>,
@@ -109,36 +293,127 @@ struct InsertModel {
 struct SearchMarketplace {
     #[schemars(description = "Search query")]
     query: String,
-    #[schemars(description = "Asset type (e.g. Model, Plugin, Audio). Default: Model")]
-    asset_type: Option<String>,
-    #[schemars(description = "Number of results. Default: 10")]
+    #[schemars(description = "Catalog category to search. Default: Model")]
+    asset_type: Option<catalog::Category>,
+    #[schemars(
+        description = "Results per page: 10, 28 or 30, other values round up to the next of these. Default: 10"
+    )]
     limit: Option<u32>,
+    #[schemars(
+        description = "next_cursor from a previous search with the same query, to fetch the page after it"
+    )]
+    cursor: Option<String>,
+    #[serde(flatten)]
+    cache: CacheControl,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct DownloadAsset {
     #[schemars(description = "Asset ID to download")]
     asset_id: u64,
-    #[schemars(description = "Filename to save as (without extension). Defaults to asset_id")]
+    #[schemars(
+        description = "Filename to save as, without extension; only letters, digits, - and _ are kept. Defaults to asset_id, or asset_id_vVERSION"
+    )]
     file_name: Option<String>,
+    #[schemars(description = "Asset version to download. Default: the latest")]
+    version: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
-struct GetInstanceProperties {
+struct InspectAssetFile {
+    #[schemars(
+        description = "Model or place file (.rbxm, .rbxmx, .rbxl, .rbxlx): its path in the asset library, such as \"12345.rbxm\""
+    )]
+    path: String,
+    #[schemars(description = "Include the source of each script. Default: true")]
+    include_sources: Option<bool>,
+    #[schemars(description = "Levels of the instance tree to list. Default: all, up to 100")]
+    tree_depth: Option<u32>,
+    #[schemars(
+        description = "Download the meshes the parts use to count their triangles. Default: true; skipped offline"
+    )]
+    count_mesh_triangles: Option<bool>,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct ListLibraryAssets {
+    #[schemars(description = "Only assets of this type (e.g. Model, Audio)")]
+    asset_type: Option<String>,
+    #[schemars(description = "Only assets with this tag")]
+    tag: Option<String>,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct SearchLibrary {
+    #[schemars(description = "Words to look for in names, creators, types, tags and ids")]
+    query: String,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct TagLibraryAsset {
+    asset_id: u64,
+    #[schemars(
+        description = "Version of the asset's entry. Default: the entry of the latest version"
+    )]
+    version: Option<u64>,
+    #[schemars(description = "Tags to add")]
+    add: Option<Vec<String>>,
+    #[schemars(description = "Tags to remove")]
+    remove: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct DedupeLibrary {}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct DeleteLibraryAsset {
+    asset_id: u64,
+    #[schemars(
+        description = "Version of the asset's entry. Default: the entry of the latest version"
+    )]
+    version: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct InsertLibraryAsset {
+    #[schemars(description = "Asset ID of a library entry")]
+    asset_id: u64,
     #[schemars(
-        description = "Roblox instance path using dot notation (e.g., \"game.Workspace.Part\")"
+        description = "Version of the asset's entry. Default: the entry of the latest version"
     )]
-    instance_path: String,
+    version: Option<u64>,
+}
+
+/// A library model sent to the plugin: binary model file contents, base64 encoded.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct LibraryInsert {
+    name: String,
+    data: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct GetInstanceProperties {
+    instance_path: InstancePath,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct SetProperty {
-    #[schemars(description = "Path to the instance (e.g., \"game.Workspace.Part\")")]
-    instance_path: String,
+    instance_path: InstancePath,
     #[schemars(description = "Name of the property to set")]
     property_name: String,
-    #[schemars(description = "Value to set the property to (any type)")]
-    property_value: serde_json::Value,
+    #[schemars(
+        description = "Value to set. Datatypes are objects tagged with \"$type\", e.g. {\"$type\": \"CFrame\", \"position\": [0, 5, 0]}"
+    )]
+    property_value: PropertyValue,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -161,8 +436,12 @@ struct TavilyExtract {
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct FetchUrlContent {
-    #[schemars(description = "URL to fetch raw content from")]
+    #[schemars(description = "http(s) URL to fetch")]
     url: String,
+    #[schemars(
+        description = "Return HTML pages as-is instead of converting them to Markdown. Default: false"
+    )]
+    raw: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -171,6 +450,16 @@ struct InstallSystem {
         description = "Name of the system to install (e.g. 'Quest System', 'Fireball Skill')"
     )]
     system_name: String,
+    #[schemars(
+        description = "Install this asset, e.g. one from a shortlist, instead of searching. It is still scanned for backdoors"
+    )]
+    asset_id: Option<u64>,
+    #[schemars(description = "Search results to download and rank. Default: 10, at most 30")]
+    candidates: Option<u32>,
+    #[schemars(
+        description = "Install the top candidate when it clearly wins. Default: true; false always returns the ranked shortlist"
+    )]
+    auto_pick: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -191,6 +480,29 @@ struct SearchCreatorStore {
     limit: Option<u32>,
     #[schemars(description = "Download found assets to Desktop folder. Default: false")]
     download: Option<bool>,
+    #[serde(flatten)]
+    filters: SearchFilters,
+    #[schemars(
+        description = "next_page_token from a previous search with the same query and filters, to fetch the page after it"
+    )]
+    page_token: Option<String>,
+    #[serde(flatten)]
+    cache: CacheControl,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct GetAssetDetails {
+    asset_id: u64,
+    #[serde(flatten)]
+    cache: CacheControl,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct ClearCache {
+    #[schemars(
+        description = "Cache to clear: \"creator_store\", \"marketplace\" or \"asset_details\". Clears all cached responses when omitted"
+    )]
+    namespace: Option<CacheNamespace>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -204,7 +516,7 @@ struct CreateScript {
     #[schemars(description = "Name of the script")]
     name: String,
     #[schemars(description = "Parent instance path")]
-    parent: String,
+    parent: InstancePath,
     #[schemars(
         description = "Type of script: 'Script', 'LocalScript', or 'ModuleScript'. Default: Script"
     )]
@@ -216,7 +528,7 @@ struct CreateScript {
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct UpdateScript {
     #[schemars(description = "Path to the script instance")]
-    instance_path: String,
+    instance_path: InstancePath,
     #[schemars(description = "New source code")]
     source: String,
 }
@@ -224,7 +536,7 @@ struct UpdateScript {
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct ReadScript {
     #[schemars(description = "Path to the script instance")]
-    instance_path: String,
+    instance_path: InstancePath,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -232,7 +544,12 @@ struct GetFileTree {
     #[schemars(
         description = "Roblox instance path to start from using dot notation. Defaults to game root if empty."
     )]
-    path: Option<String>,
+    path: Option<InstancePath>,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+    #[serde(flatten)]
+    shape: OutputShape,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -244,17 +561,23 @@ struct GetServices {
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct GetProjectStructure {
     #[schemars(description = "Optional path to start from")]
-    path: Option<String>,
+    path: Option<InstancePath>,
     #[schemars(description = "Maximum depth to traverse (default: 3)")]
     max_depth: Option<u32>,
     #[schemars(description = "Show only scripts and script containers")]
     scripts_only: Option<bool>,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+    #[serde(flatten)]
+    shape: OutputShape,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct GetInstanceChildren {
-    #[schemars(description = "Roblox instance path")]
-    instance_path: String,
+    instance_path: InstancePath,
+    #[serde(flatten)]
+    shape: OutputShape,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -263,6 +586,11 @@ struct SearchFiles {
     query: String,
     #[schemars(description = "Type of search: \"name\", \"type\", or \"content\"")]
     search_type: Option<String>,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+    #[serde(flatten)]
+    shape: OutputShape,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -273,6 +601,31 @@ struct SearchObjects {
     search_type: Option<String>,
     #[schemars(description = "Property name when searchType is \"property\"")]
     property_name: Option<String>,
+    #[serde(flatten)]
+    shape: OutputShape,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct QueryInstances {
+    #[schemars(
+        description = "Selector, e.g. `Workspace BasePart[Anchored=false][Material=Neon].Tagged(\"Kill\") > Script`"
+    )]
+    selector: String,
+    #[schemars(description = "Instance to search under. Default: game")]
+    root: Option<InstancePath>,
+    #[schemars(description = "Maximum number of matches. Default: 200")]
+    limit: Option<u32>,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
+}
+
+/// A parsed `query_instances` selector, as the plugin evaluates it.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct RunQuery {
+    selector: Selector,
+    root: Option<InstancePath>,
+    limit: u32,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
@@ -281,38 +634,52 @@ struct SearchByProperty {
     property_name: String,
     #[schemars(description = "Value to search for")]
     property_value: String,
+    #[serde(flatten)]
+    shape: OutputShape,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct CreateObject {
     #[serde(rename = "className")]
     class_name: String,
-    parent: String,
+    parent: InstancePath,
     name: Option<String>,
-    properties: Option<std::collections::HashMap<String, serde_json::Value>>,
+    properties: Option<std::collections::HashMap<String, PropertyValue>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct DeleteObject {
-    instance_path: String,
+    instance_path: InstancePath,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct MassSetProperty {
-    paths: Vec<String>,
+    paths: Vec<PathPattern>,
     property_name: String,
-    property_value: serde_json::Value,
+    #[schemars(
+        description = "Value to set. Datatypes are objects tagged with \"$type\", as for set_property"
+    )]
+    property_value: PropertyValue,
+}
+
+/// Class names of the instances at `paths`, used to validate property writes.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct GetClassNames {
+    paths: Vec<PathPattern>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct MassGetProperty {
-    paths: Vec<String>,
+    paths: Vec<PathPattern>,
     property_name: String,
+    #[schemars(description = NEXT_PAGE_DESCRIPTION)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_page: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct SetCalculatedProperty {
-    paths: Vec<String>,
+    paths: Vec<PathPattern>,
     #[schemars(description = "Property to set")]
     property_name: String,
     #[schemars(description = "Formula to calculate value (uses 'index', 'Position.X', etc)")]
@@ -323,7 +690,7 @@ struct SetCalculatedProperty {
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 struct SetRelativeProperty {
-    paths: Vec<String>,
+    paths: Vec<PathPattern>,
     property_name: String,
     #[schemars(description = "Operation: add, subtract, multiply, divide, power")]
     operation: String,
@@ -339,12 +706,37 @@ struct GetPlaceInfo {}
 struct GetClassInfo {
     #[serde(rename = "className")]
     class_name: String,
+    #[schemars(description = "Also list members inherited from superclasses. Default: false")]
+    inherited: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct GetMemberInfo {
+    #[schemars(description = "Class the member belongs to, or inherits it (e.g. \"Part\")")]
+    class_name: String,
+    #[schemars(description = "Property, function, event or callback name")]
+    member_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct GetEnumInfo {
+    #[schemars(description = "Enum name without the `Enum.` prefix (e.g. \"Material\")")]
+    enum_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
+struct SearchApi {
+    #[schemars(description = "Text to look for in class, enum and member names")]
+    query: String,
+    #[schemars(description = "Maximum number of matches. Default: 50")]
+    limit: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone)]
 enum ToolArgumentValues {
     RunCommand(RunCommand),
     InsertModel(InsertModel),
+    InsertLibraryAsset(LibraryInsert),
     GetInstanceProperties(GetInstanceProperties),
     SetProperty(SetProperty),
     GetFileTree(GetFileTree),
@@ -354,14 +746,15 @@ enum ToolArgumentValues {
     SearchFiles(SearchFiles),
     SearchObjects(SearchObjects),
     SearchByProperty(SearchByProperty),
+    QueryInstances(RunQuery),
     CreateObject(CreateObject),
     DeleteObject(DeleteObject),
     MassSetProperty(MassSetProperty),
     MassGetProperty(MassGetProperty),
+    GetClassNames(GetClassNames),
     SetCalculatedProperty(SetCalculatedProperty),
     SetRelativeProperty(SetRelativeProperty),
     GetPlaceInfo(GetPlaceInfo),
-    GetClassInfo(GetClassInfo),
     SearchMarketplace(SearchMarketplace),
     DownloadAsset(DownloadAsset),
     SearchWebScripts(SearchWebScripts),
@@ -374,18 +767,78 @@ enum ToolArgumentValues {
     CreateScript(CreateScript),
     UpdateScript(UpdateScript),
     ReadScript(ReadScript),
+    DynamicTool(DynamicToolCall),
+}
+impl ToolArgumentValues {
+    /// Tools whose output grows with the size of the place and is served page by page.
+    fn is_paginated(&self) -> bool {
+        matches!(
+            self,
+            Self::GetProjectStructure(_)
+                | Self::GetFileTree(_)
+                | Self::SearchFiles(_)
+                | Self::MassGetProperty(_)
+                | Self::QueryInstances(_)
+        )
+    }
+
+    /// Tools whose output holds Roblox values tagged by the plugin's `DataTypes.encode`.
+    fn returns_values(&self) -> bool {
+        matches!(
+            self,
+            Self::RunCommand(_) | Self::GetInstanceProperties(_) | Self::MassGetProperty(_)
+        )
+    }
+
+    fn output_shape(&self) -> Option<&OutputShape> {
+        match self {
+            Self::GetFileTree(args) => Some(&args.shape),
+            Self::GetProjectStructure(args) => Some(&args.shape),
+            Self::GetInstanceChildren(args) => Some(&args.shape),
+            Self::SearchFiles(args) => Some(&args.shape),
+            Self::SearchObjects(args) => Some(&args.shape),
+            Self::SearchByProperty(args) => Some(&args.shape),
+            _ => None,
+        }
+    }
+
+    fn next_page(&self) -> Option<&str> {
+        match self {
+            Self::GetProjectStructure(args) => args.next_page.as_deref(),
+            Self::GetFileTree(args) => args.next_page.as_deref(),
+            Self::SearchFiles(args) => args.next_page.as_deref(),
+            Self::MassGetProperty(args) => args.next_page.as_deref(),
+            _ => None,
+        }
+    }
 }
+
 #[tool_router]
 impl RBXStudioServer {
-    pub fn new(state: PackedState) -> Self {
+    pub fn new(
+        state: PackedState,
+        log_level: ClientLogLevel,
+        config: Arc<Config>,
+        secrets: Arc<Secrets>,
+        http: HttpClient,
+    ) -> Self {
+        let cache = ResponseCache::new(&config);
+        let library = Arc::new(Library::new(&config.asset_dir));
         Self {
             state,
+            log_level,
+            config,
+            secrets,
+            http,
+            cache,
+            library,
+            api: Arc::new(OnceCell::new()),
             tool_router: Self::tool_router(),
         }
     }
 
     #[tool(
-        description = "Execute a Luau command or script snippet directly in Roblox Studio. Use this to modify the game state or query information not available via other tools."
+        description = "Execute a Luau command or script snippet directly in Roblox Studio. Use this to modify the game state or query information not available via other tools. Returns the printed output and the chunk's return values, with Roblox datatypes tagged by \"$type\" as set_property accepts them."
     )]
     async fn run_command(
         &self,
@@ -396,17 +849,58 @@ impl RBXStudioServer {
     }
 
     #[tool(
-        description = "Inserts a model from the Roblox marketplace into the workspace. Returns the inserted model name."
+        description = "Inserts a model from the Roblox marketplace into the workspace. A query inserts the top Creator Store model for it. The model is scanned for backdoors first. Returns the inserted model name."
     )]
     async fn insert_model(
         &self,
         Parameters(args): Parameters<InsertModel>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::InsertModel(args))
-            .await
+        let (asset_id, name) = match (args.asset_id, &args.query) {
+            (Some(id), _) => {
+                let name = self
+                    .economy_details(id)
+                    .await
+                    .ok()
+                    .map(|details| details.name);
+                (id, name)
+            }
+            (None, Some(query)) => {
+                let top = self.top_model(query).await?.ok_or_else(|| {
+                    ErrorData::invalid_params(format!("No model found for {query:?}"), None)
+                })?;
+                (top.asset_id, Some(top.name))
+            }
+            (None, None) => return Err(ErrorData::invalid_params("Pass query or asset_id", None)),
+        };
+        let screened = match self.screen_asset(asset_id).await {
+            Ok(screened) => screened,
+            Err(refusal) => return Ok(CallToolResult::error(vec![Content::text(refusal)])),
+        };
+        let mut result = match &screened.file {
+            // Insert the file that was scanned, rather than have Studio download it again
+            Some(file) => {
+                let name = name
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| format!("Asset{asset_id}"));
+                self.insert_file(name, file).await?
+            }
+            // Left unscanned, as `scanner.block = "never"` allows
+            None => {
+                let insert = InsertModel {
+                    query: None,
+                    asset_id: Some(asset_id),
+                };
+                self.generic_tool_run(ToolArgumentValues::InsertModel(insert))
+                    .await?
+            }
+        };
+        result.content.push(Content::text(screened.note));
+        Ok(result)
     }
 
-    #[tool(description = "Get all properties of a specific Roblox instance in Studio")]
+    #[tool(
+        description = "Get all properties of a specific Roblox instance in Studio. Datatype values are tagged with \"$type\" and can be passed back to set_property."
+    )]
     async fn get_instance_properties(
         &self,
         Parameters(args): Parameters<GetInstanceProperties>,
@@ -420,11 +914,19 @@ impl RBXStudioServer {
         &self,
         Parameters(args): Parameters<SetProperty>,
     ) -> Result<CallToolResult, ErrorData> {
+        self.check_writes(
+            &[args.instance_path.clone().into()],
+            &args.property_name,
+            &args.property_value,
+        )
+        .await?;
         self.generic_tool_run(ToolArgumentValues::SetProperty(args))
             .await
     }
 
-    #[tool(description = "Get the Roblox instance hierarchy tree from Roblox Studio.")]
+    #[tool(
+        description = "Get the Roblox instance hierarchy tree from Roblox Studio. Large trees are paginated, pass next_page to continue."
+    )]
     async fn get_file_tree(
         &self,
         Parameters(args): Parameters<GetFileTree>,
@@ -443,7 +945,7 @@ impl RBXStudioServer {
     }
 
     #[tool(
-        description = "Get complete game hierarchy. IMPORTANT: Use maxDepth parameter to explore deeper levels"
+        description = "Get complete game hierarchy. IMPORTANT: Use maxDepth parameter to explore deeper levels. Large outputs are paginated, pass next_page to continue."
     )]
     async fn get_project_structure(
         &self,
@@ -462,7 +964,9 @@ impl RBXStudioServer {
             .await
     }
 
-    #[tool(description = "Search for Roblox instances by name, class type, or script content")]
+    #[tool(
+        description = "Search for Roblox instances by name, class type, or script content. Large result lists are paginated, pass next_page to continue."
+    )]
     async fn search_files(
         &self,
         Parameters(args): Parameters<SearchFiles>,
@@ -489,11 +993,54 @@ impl RBXStudioServer {
             .await
     }
 
+    #[tool(
+        description = "Find instances with a selector and return their paths. Compounds are a class name (matched with IsA, or * for any) followed by #Name (with * and ? wildcards), [Property=value] tests (also !=, <, <=, >, >=, *= contains, ^= starts with, $= ends with; [Size.Y>4] reads a component, [Property] alone tests truthiness), [@Attribute...] tests and .Tagged(\"Tag\") for CollectionService tags. Separate compounds with a space for any descendant or > for a direct child, and alternatives with commas, e.g. `Workspace BasePart[Anchored=false][Material=Neon].Tagged(\"Kill\") > Script`."
+    )]
+    async fn query_instances(
+        &self,
+        Parameters(args): Parameters<QueryInstances>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Some(cursor) = &args.next_page {
+            return self.cached_page(cursor).await;
+        }
+        let selector: Selector = args
+            .selector
+            .parse()
+            .map_err(|e: SelectorError| ErrorData::invalid_params(e.to_string(), None))?;
+        selector
+            .validate(self.api_index().await)
+            .map_err(|e| ErrorData::invalid_params(e, None))?;
+        self.generic_tool_run(ToolArgumentValues::QueryInstances(RunQuery {
+            selector,
+            root: args.root,
+            limit: args.limit.unwrap_or(200),
+        }))
+        .await
+    }
+
     #[tool(description = "Create a new Instance")]
     async fn create_object(
         &self,
         Parameters(args): Parameters<CreateObject>,
     ) -> Result<CallToolResult, ErrorData> {
+        if self.config.api_dump.validate_writes {
+            let api = self.api_index().await;
+            let mut errors = Vec::new();
+            if let Err(e) = validation::check_creatable(api, &args.class_name) {
+                errors.push(e);
+            } else {
+                for (property, value) in args.properties.iter().flatten() {
+                    if let Err(e) =
+                        validation::check_property_write(api, &args.class_name, property, value)
+                    {
+                        errors.push(e);
+                    }
+                }
+            }
+            if !errors.is_empty() {
+                return Err(ErrorData::invalid_params(errors.join("\n"), None));
+            }
+        }
         self.generic_tool_run(ToolArgumentValues::CreateObject(args))
             .await
     }
@@ -507,16 +1054,22 @@ impl RBXStudioServer {
             .await
     }
 
-    #[tool(description = "Set property on multiple instances")]
+    #[tool(
+        description = "Set property on multiple instances. Paths may use wildcards, e.g. game.Workspace.**.Part* sets it on every match"
+    )]
     async fn mass_set_property(
         &self,
         Parameters(args): Parameters<MassSetProperty>,
     ) -> Result<CallToolResult, ErrorData> {
+        self.check_writes(&args.paths, &args.property_name, &args.property_value)
+            .await?;
         self.generic_tool_run(ToolArgumentValues::MassSetProperty(args))
             .await
     }
 
-    #[tool(description = "Get property from multiple instances")]
+    #[tool(
+        description = "Get property from multiple instances. Paths may use wildcards as in mass_set_property. Values are tagged like get_instance_properties. Large result lists are paginated, pass next_page to continue."
+    )]
     async fn mass_get_property(
         &self,
         Parameters(args): Parameters<MassGetProperty>,
@@ -552,139 +1105,444 @@ impl RBXStudioServer {
             .await
     }
 
-    #[tool(description = "Get API information for a specific class")]
+    #[tool(
+        description = "Get API information for a class from the Roblox API dump: superclass chain, subclasses, tags, and members with their types, security and deprecation. Works without Studio connected"
+    )]
     async fn get_class_info(
         &self,
         Parameters(args): Parameters<GetClassInfo>,
     ) -> Result<CallToolResult, ErrorData> {
-        self.generic_tool_run(ToolArgumentValues::GetClassInfo(args))
-            .await
+        let api = self.api_index().await;
+        let class = api
+            .class(&args.class_name)
+            .ok_or_else(|| ErrorData::invalid_params(api.unknown_class(&args.class_name), None))?;
+        let members: Vec<_> = if args.inherited.unwrap_or(false) {
+            api.members(&class.name)
+        } else {
+            class
+                .members
+                .iter()
+                .map(|member| MemberRef { class, member })
+                .collect()
+        };
+        let mut info = serde_json::json!({
+            "Name": class.name,
+            "Superclass": class.superclass,
+            "Deprecated": api_dump::has_tag(&class.tags, "Deprecated"),
+            "Inheritance": api.inheritance(&class.name).iter().map(|class| &class.name).collect::<Vec<_>>(),
+            "Subclasses": api.subclasses(&class.name),
+            "Tags": class.tags,
+            "MemoryCategory": class.memory_category,
+            "Members": members.iter().map(|member| member_json(member, &class.name)).collect::<Vec<_>>(),
+            "Source": api.source,
+        });
+        if let Some(note) = api.limitations() {
+            info["Note"] = note.into();
+        }
+        json_result(&info)
     }
 
-    #[tool(description = "Search the official Roblox Marketplace/Catalog")]
-    async fn search_marketplace(
+    #[tool(
+        description = "Get one member of a class (or of its superclasses) from the Roblox API dump: type or signature, security, tags and deprecation"
+    )]
+    async fn get_member_info(
         &self,
-        Parameters(args): Parameters<SearchMarketplace>,
+        Parameters(args): Parameters<GetMemberInfo>,
     ) -> Result<CallToolResult, ErrorData> {
-        // Rust-side implementation
-        let query = args.query;
-        let limit = args.limit.unwrap_or(10);
-        // Category 11 = Models
-        let category = match args.asset_type.as_deref() {
-            Some("Audio") => 9, // Example category IDs needed, using Models (11) as default for now or finding documented ones
-            _ => 11,            // Models
-        };
+        let api = self.api_index().await;
+        if api.class(&args.class_name).is_none() {
+            return Err(ErrorData::invalid_params(
+                api.unknown_class(&args.class_name),
+                None,
+            ));
+        }
+        let member = api
+            .member(&args.class_name, &args.member_name)
+            .ok_or_else(|| {
+                ErrorData::invalid_params(
+                    api.unknown_member(&args.class_name, &args.member_name),
+                    None,
+                )
+            })?;
+        let mut info = member_json(&member, &args.class_name);
+        info["Source"] = api.source.clone().into();
+        if let Some(note) = api.limitations() {
+            info["Note"] = note.into();
+        }
+        json_result(&info)
+    }
 
-        let client = reqwest::Client::new();
-        // Using catalog API. Note: Many useful endpoints require auth.
-        // Trying generic search items details
-        let url = format!(
-            "https://catalog.roblox.com/v1/search/items/details?Keyword={}&Category={}&Limit={}",
-            query, category, limit
-        );
+    #[tool(description = "List the items and values of a Roblox enum from the API dump")]
+    async fn get_enum_info(
+        &self,
+        Parameters(args): Parameters<GetEnumInfo>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let api = self.api_index().await;
+        let name = args.enum_name.trim_start_matches("Enum.");
+        let item = api
+            .enum_(name)
+            .ok_or_else(|| ErrorData::invalid_params(api.unknown_enum(name), None))?;
+        json_result(item)
+    }
 
-        // This likely needs a robust implementation or proxy for stability, but we try direct first.
-        let res = client.get(&url).send().await;
+    #[tool(
+        description = "Search the Roblox API dump for classes, enums and members whose name contains the query"
+    )]
+    async fn search_api(
+        &self,
+        Parameters(args): Parameters<SearchApi>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let api = self.api_index().await;
+        let limit = args.limit.unwrap_or(50) as usize;
+        json_result(&serde_json::json!({
+            "results": api.search(&args.query, limit),
+            "source": api.source,
+        }))
+    }
 
-        match res {
-            Ok(response) => {
-                let text = response.text().await.unwrap_or_default();
-                // Return raw JSON for now, or parse it?
-                // Raw JSON is better for the LLM to inspect.
-                Ok(CallToolResult::success(vec![Content::text(text)]))
-            }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Request failed: {}",
-                e
-            ))])),
+    #[tool(
+        description = "Search the official Roblox Marketplace/Catalog. Returns compact results (id, name, creator and verification, price, favorites) and a next_cursor for the following page"
+    )]
+    async fn search_marketplace(
+        &self,
+        Parameters(args): Parameters<SearchMarketplace>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let category = args.asset_type.unwrap_or_default();
+        let page_size = catalog::page_size(args.limit.unwrap_or(10));
+        let url = catalog::search_url(&args.query, category, page_size, args.cursor.as_deref());
+
+        let request = serde_json::json!({
+            "query": cache::normalize_query(&args.query),
+            "category": category.id(),
+            "limit": page_size,
+            "cursor": args.cursor,
+        });
+        let cached = self
+            .cache
+            .get_or_fetch(
+                CacheNamespace::Marketplace,
+                request,
+                &args.cache,
+                || async { Ok(self.http.send(self.http.get(&url)).await?.text().await?) },
+            )
+            .await?;
+        let response: catalog::SearchResponse =
+            serde_json::from_str(&cached.body).map_err(|e| {
+                ErrorData::internal_error(format!("Invalid catalog response: {e}"), None)
+            })?;
+        if let Some(error) = response.errors.first() {
+            return Err(ErrorData::invalid_request(
+                format!("The catalog refused the search: {}", error.message),
+                Some(serde_json::json!({ "code": error.code })),
+            ));
         }
+        let results: Vec<catalog::ItemSummary> =
+            response.data.into_iter().map(Into::into).collect();
+        let text = serde_json::json!({
+            "category": category,
+            "count": results.len(),
+            "results": results,
+            "next_cursor": response.next_page_cursor,
+        })
+        .to_string();
+        let mut content = vec![Content::text(text)];
+        content.extend(cached.notice());
+        Ok(CallToolResult::success(content))
     }
 
-    #[tool(description = "Download a Roblox Asset (.rbxm) to the computer")]
+    #[tool(
+        description = "Download a Roblox asset to the asset library. The file type is detected from its contents and saved with the matching extension (.rbxm, .rbxmx, .ogg, .png, .mesh). Returns the path and the file's SHA-256."
+    )]
     async fn download_asset(
         &self,
         Parameters(args): Parameters<DownloadAsset>,
     ) -> Result<CallToolResult, ErrorData> {
         let asset_id = args.asset_id;
-        let file_name = args.file_name.unwrap_or_else(|| asset_id.to_string());
-        let path = format!("assets/{}.rbxm", file_name);
-
-        // Ensure assets dir exists
-        let _ = tokio::fs::create_dir_all("assets").await;
+        let stem = match &args.file_name {
+            Some(file_name) => library::sanitize_file_name(file_name).ok_or_else(|| {
+                ErrorData::invalid_params(
+                    format!("File name {file_name:?} has no letters or digits"),
+                    None,
+                )
+            })?,
+            None => match args.version {
+                Some(version) => format!("{asset_id}_v{version}"),
+                None => asset_id.to_string(),
+            },
+        };
 
-        let client = reqwest::Client::new();
-        let url = format!("https://assetdelivery.roblox.com/v1/asset?id={}", asset_id);
+        if self.http.is_offline() {
+            if let Some(entry) = self.library.get(asset_id, args.version).await {
+                return Ok(CallToolResult::success(vec![Content::text(format!(
+                    "Offline: asset {} was already downloaded to {}\nSHA-256: {}",
+                    asset_id,
+                    self.library.path_of(&entry).display(),
+                    entry.sha256
+                ))]));
+            }
+        }
 
-        let res = client.get(&url).send().await;
+        let mut url = format!("https://assetdelivery.roblox.com/v1/asset?id={}", asset_id);
+        if let Some(version) = args.version {
+            url.push_str(&format!("&version={version}"));
+        }
 
-        match res {
-            Ok(response) => {
-                if response.status().is_success() {
-                    let bytes = response
-                        .bytes()
-                        .await
-                        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-                    tokio::fs::write(&path, bytes)
-                        .await
-                        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-                    Ok(CallToolResult::success(vec![Content::text(format!(
-                        "Saved asset {} to {}",
-                        asset_id, path
-                    ))]))
-                } else {
-                    Ok(CallToolResult::error(vec![Content::text(format!(
-                        "Failed to download: Status {}",
-                        response.status()
-                    ))]))
-                }
+        let response = self.http.send(self.http.get(&url)).await?;
+        let bytes = response.bytes().await.map_err(HttpError::from)?;
+        let kind = ContentKind::sniff(&bytes);
+        let file_name = format!("{stem}.{}", kind.extension());
+        // Indexed by name and creator when the economy API knows them
+        let details = match self.economy_details(asset_id).await {
+            Ok(details) => Some(details),
+            Err(e) => {
+                tracing::debug!("No details for asset {asset_id}: {e}");
+                None
             }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Request failed: {}",
-                e
-            ))])),
-        }
+        };
+        let info = AssetInfo {
+            asset_id,
+            version: args.version,
+            name: details
+                .as_ref()
+                .map(|details| details.name.clone())
+                .filter(|name| !name.is_empty()),
+            creator: details
+                .and_then(|details| details.creator)
+                .map(|creator| creator.name),
+            asset_type: kind.asset_type().map(str::to_string),
+        };
+        let entry = self
+            .library
+            .add(info, &file_name, &bytes)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => ErrorData::invalid_params(e.to_string(), None),
+                _ => ErrorData::internal_error(e.to_string(), None),
+            })?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Saved asset {} ({}, {} bytes) to {}\nSHA-256: {}",
+            asset_id,
+            entry.asset_type.as_deref().unwrap_or("unknown type"),
+            entry.size,
+            self.library.path_of(&entry).display(),
+            entry.sha256
+        ))]))
     }
 
     #[tool(
-        description = "Search the web using Tavily AI. Use 'basic' depth for quick results or 'advanced' for more comprehensive searches."
+        description = "Inspect a downloaded model or place file without opening it in Studio: the instance tree, instance counts per class, every script with its source, and the number of parts and triangles. Use it to judge an asset before inserting it."
     )]
-    async fn search_web_scripts(
+    async fn inspect_asset_file(
         &self,
-        Parameters(args): Parameters<SearchWebScripts>,
+        Parameters(args): Parameters<InspectAssetFile>,
     ) -> Result<CallToolResult, ErrorData> {
-        let api_key = std::env::var("TAVILY_API_KEY")
-            .unwrap_or_else(|_| "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".to_string());
-
-        // If still using placeholder/hardcoded, warn or proceed.
-        // We kept the user's provided key as default for convenience,
-        // but now they can override it in JSON.
-
-        let client = reqwest::Client::new();
-
-        // Use Tavily Search API with configurable depth
-        // Research mode is disabled as it requires additional undocumented parameters
-        let search_url = "https://api.tavily.com/search";
-        let body = serde_json::json!({
-            "query": args.query,
-            "search_depth": args.depth.unwrap_or("basic".to_string()),
-            "include_answer": true,
-            "include_raw_content": true,
-            "max_results": 5
-        });
-
-        let res = client
-            .post(search_url)
-            .bearer_auth(&api_key)
-            .json(&body)
-            .send()
+        if let Some(cursor) = &args.next_page {
+            return self.cached_page(cursor).await;
+        }
+        let path = self
+            .library
+            .resolve(&args.path)
             .await
-            .map_err(|e| {
-                ErrorData::internal_error(format!("Search request failed: {}", e), None)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::PermissionDenied => {
+                    ErrorData::invalid_params(e.to_string(), None)
+                }
+                _ => ErrorData::invalid_params(format!("Could not read {}: {e}", args.path), None),
             })?;
+        let bytes = tokio::fs::read(&path).await.map_err(|e| {
+            ErrorData::invalid_params(format!("Could not read {}: {e}", args.path), None)
+        })?;
+        let file = tokio::task::spawn_blocking(move || AssetFile::read(&bytes))
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+            .map_err(|e| ErrorData::invalid_params(e, None))?;
+
+        let api = self.api_index().await;
+        let mut mesh_triangles = HashMap::new();
+        if args.count_mesh_triangles.unwrap_or(true) && !self.http.is_offline() {
+            for id in file.mesh_ids(api).into_iter().take(MAX_INSPECTED_MESHES) {
+                match self.mesh_triangles(id).await {
+                    Ok(Some(count)) => {
+                        mesh_triangles.insert(id, count);
+                    }
+                    Ok(None) => tracing::debug!("Mesh {id} has no plain triangle count"),
+                    Err(e) => tracing::warn!("Could not download mesh {id}: {e:?}"),
+                }
+            }
+        }
+        let options = InspectOptions {
+            include_sources: args.include_sources.unwrap_or(true),
+            tree_depth: args.tree_depth.map(|depth| depth as usize),
+        };
+        // Off the runtime, like decoding: the walk and scan grow with the file
+        let api = Arc::clone(&self.api);
+        let report = tokio::task::spawn_blocking(move || {
+            let api = api.get()?;
+            let mut report = file.inspect(api, &mesh_triangles, &options);
+            report.scan = Some(scanner::scan(&file, api));
+            Some(report)
+        })
+        .await
+        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+        .ok_or_else(|| ErrorData::internal_error("The API index is not loaded", None))?;
+        let text = serde_json::to_string(&report)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let page = self.state.lock().await.pages.paginate(&text);
+        Ok(match page {
+            Some(page) => page.into_call_tool_result(),
+            None => CallToolResult::success(vec![Content::text(text)]),
+        })
+    }
+
+    #[tool(
+        description = "List the assets in the local library, newest first: the files downloaded by download_asset and install_system, with asset id, name, creator, type, download date, SHA-256 and tags."
+    )]
+    async fn list_library_assets(
+        &self,
+        Parameters(args): Parameters<ListLibraryAssets>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Some(cursor) = &args.next_page {
+            return self.cached_page(cursor).await;
+        }
+        let entries = self
+            .library
+            .entries()
+            .await
+            .into_iter()
+            .filter(|entry| {
+                args.asset_type.as_ref().is_none_or(|asset_type| {
+                    entry
+                        .asset_type
+                        .as_ref()
+                        .is_some_and(|t| t.eq_ignore_ascii_case(asset_type))
+                })
+            })
+            .filter(|entry| args.tag.as_ref().is_none_or(|tag| entry.tags.contains(tag)))
+            .collect();
+        self.library_listing(entries).await
+    }
+
+    #[tool(
+        description = "Search the local asset library. Matches entries whose name, creator, type, tags or asset id contain every word of the query, ignoring case."
+    )]
+    async fn search_library(
+        &self,
+        Parameters(args): Parameters<SearchLibrary>,
+    ) -> Result<CallToolResult, ErrorData> {
+        if let Some(cursor) = &args.next_page {
+            return self.cached_page(cursor).await;
+        }
+        let entries = self.library.search(&args.query).await;
+        self.library_listing(entries).await
+    }
+
+    #[tool(description = "Add or remove tags on an asset in the local library")]
+    async fn tag_library_asset(
+        &self,
+        Parameters(args): Parameters<TagLibraryAsset>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self
+            .library
+            .tag(
+                args.asset_id,
+                args.version,
+                &args.add.unwrap_or_default(),
+                &args.remove.unwrap_or_default(),
+            )
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+            .ok_or_else(|| not_in_library(args.asset_id, args.version))?;
+        json_result(&entry)
+    }
+
+    #[tool(
+        description = "Clean up the local asset library: entries with the same content (SHA-256) as an older entry are removed along with their files, their tags moving to the entry kept, and entries whose file is gone are dropped."
+    )]
+    async fn dedupe_library(
+        &self,
+        Parameters(_): Parameters<DedupeLibrary>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let report = self
+            .library
+            .dedupe()
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        json_result(&report)
+    }
+
+    #[tool(description = "Delete an asset and its file from the local library")]
+    async fn delete_library_asset(
+        &self,
+        Parameters(args): Parameters<DeleteLibraryAsset>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self
+            .library
+            .delete(args.asset_id, args.version)
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+            .ok_or_else(|| not_in_library(args.asset_id, args.version))?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Deleted asset {} ({}) from the library",
+            entry.asset_id, entry.file
+        ))]))
+    }
 
-        let text = res
-            .text()
+    #[tool(
+        description = "Insert a model from the local asset library into the workspace without downloading it again. The file is scanned for backdoors first. Returns the inserted model name."
+    )]
+    async fn insert_library_asset(
+        &self,
+        Parameters(args): Parameters<InsertLibraryAsset>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self
+            .library
+            .get(args.asset_id, args.version)
             .await
+            .ok_or_else(|| not_in_library(args.asset_id, args.version))?;
+        let path = self.library.path_of(&entry);
+        let bytes = tokio::fs::read(&path).await.map_err(|e| {
+            ErrorData::invalid_params(format!("Could not read {}: {e}", path.display()), None)
+        })?;
+        let file = tokio::task::spawn_blocking(move || AssetFile::read(&bytes))
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+            .map_err(|e| ErrorData::invalid_params(e, None))?;
+        let report = scanner::scan(&file, self.api_index().await);
+        if report.blocked_by(self.config.scanner.block) {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Refused to insert asset {}, the backdoor scan found it {}",
+                entry.asset_id,
+                report.summary()
+            ))]));
+        }
+        let name = entry
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("Asset{}", entry.asset_id));
+        let mut result = self.insert_file(name, &file).await?;
+        result.content.push(Content::text(format!(
+            "Backdoor scan of asset {}: {}",
+            entry.asset_id,
+            report.summary()
+        )));
+        Ok(result)
+    }
+
+    #[tool(
+        description = "Search the web for scripts, tutorials and docs using the configured search provider (Tavily by default). Use 'basic' depth for quick results or 'advanced' for more comprehensive searches."
+    )]
+    async fn search_web_scripts(
+        &self,
+        Parameters(args): Parameters<SearchWebScripts>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let provider = web_search::provider(&self.config.web_search, &self.http, &self.secrets)?;
+        let request = WebSearchRequest {
+            query: args.query,
+            advanced: args.depth.as_deref() == Some("advanced"),
+            max_results: 5,
+        };
+        let response = provider.search(&request).await?;
+        let text = serde_json::to_string_pretty(&response)
             .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
         Ok(CallToolResult::success(vec![Content::text(text)]))
     }
@@ -694,163 +1552,233 @@ impl RBXStudioServer {
         &self,
         Parameters(args): Parameters<TavilyExtract>,
     ) -> Result<CallToolResult, ErrorData> {
-        let api_key = std::env::var("TAVILY_API_KEY")
-            .unwrap_or_else(|_| "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".to_string());
-
-        let client = reqwest::Client::new();
+        let api_key = self.tavily_api_key()?;
 
         let url = "https://api.tavily.com/extract";
         let body = serde_json::json!({
             "urls": args.urls,
         });
 
-        let res = client
-            .post(url)
-            .bearer_auth(&api_key)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| {
-                ErrorData::internal_error(format!("Extract request failed: {}", e), None)
-            })?;
+        let res = self
+            .http
+            .send(self.http.post(url).bearer_auth(&api_key).json(&body))
+            .await?;
 
-        let text = res
-            .text()
-            .await
-            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let text = res.text().await.map_err(HttpError::from)?;
         Ok(CallToolResult::success(vec![Content::text(text)]))
     }
 
-    #[tool(description = "Fetch raw text content from a URL (e.g. raw GitHub file)")]
+    #[tool(
+        description = "Fetch text content from a public URL (e.g. a raw GitHub file or a docs page). HTML pages are converted to readable Markdown"
+    )]
     async fn fetch_url_content(
         &self,
         Parameters(args): Parameters<FetchUrlContent>,
     ) -> Result<CallToolResult, ErrorData> {
-        let res = reqwest::get(&args.url)
-            .await
-            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-        let text = res
-            .text()
-            .await
-            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let page = fetch::fetch_page(
+            &self.http,
+            &self.config.fetch,
+            &args.url,
+            args.raw.unwrap_or(false),
+        )
+        .await?;
+        let mut text = format!(
+            "Source: {}\nContent-Type: {}\n\n{}",
+            page.url, page.content_type, page.text
+        );
+        if page.truncated {
+            text.push_str(&format!(
+                "\n\n[truncated after {} bytes]",
+                self.config.fetch.max_bytes
+            ));
+        }
         Ok(CallToolResult::success(vec![Content::text(text)]))
     }
 
     #[tool(
-        description = "Automated System Installer: Searches Marketplace for best match and installs it."
+        description = "Automated System Installer: searches the Creator Store for candidates, downloads and scans each, and ranks them on creator verification, votes, favorites, recency, script count and backdoor patterns. Installs the best one when it clearly wins, explaining why; otherwise returns a ranked shortlist to choose from with asset_id."
     )]
     async fn install_system(
         &self,
         Parameters(args): Parameters<InstallSystem>,
     ) -> Result<CallToolResult, ErrorData> {
-        // 1. Search Marketplace
-        let query = args.system_name.clone();
-        let category = 11; // Models
-        let url = format!(
-            "https://catalog.roblox.com/v1/search/items/details?Keyword={}&Category={}&Limit=1",
-            query, category
-        );
-        let client = reqwest::Client::new();
-        let search_res = client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| ErrorData::internal_error(format!("Search failed: {}", e), None))?;
-
-        let search_json: serde_json::Value = search_res.json().await.map_err(|e| {
-            ErrorData::internal_error(format!("Failed to parse search JSON: {}", e), None)
-        })?;
-
-        // Extract first asset ID
-        let data =
-            search_json
-                .get("data")
-                .and_then(|v| v.as_array())
-                .ok_or(ErrorData::internal_error(
-                    "Invalid search response format",
-                    None,
-                ))?;
-
-        if let Some(first_item) = data.first() {
-            let asset_id = first_item
-                .get("id")
-                .and_then(|v| v.as_u64())
-                .ok_or(ErrorData::internal_error("Item has no ID", None))?;
-            let name = first_item
-                .get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown");
+        let (candidate, bytes, reason) = match args.asset_id {
+            Some(asset_id) => {
+                // Named and credited as on the site, not after the system asked for
+                let details = self.economy_details(asset_id).await.ok();
+                let candidate = Candidate {
+                    asset_id,
+                    name: details
+                        .as_ref()
+                        .map(|details| details.name.clone())
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| args.system_name.clone()),
+                    creator: details
+                        .and_then(|details| details.creator)
+                        .map(|creator| creator.name),
+                    verified: false,
+                    up_votes: 0,
+                    down_votes: 0,
+                    favorites: None,
+                    updated: None,
+                };
+                let bytes = self.fetch_asset(asset_id).await?;
+                (candidate, bytes, "asset_id given".to_string())
+            }
+            None => {
+                // 1. Search the Creator Store for candidates
+                let limit = args
+                    .candidates
+                    .unwrap_or(10)
+                    .clamp(1, MAX_INSTALL_CANDIDATES);
+                let url = format!(
+                    "https://apis.roblox.com/toolbox-service/v2/assets:search?searchCategoryType=Model&query={}&maxPageSize={}",
+                    urlencoding::encode(&args.system_name),
+                    limit
+                );
+                let search_res = self.http.send(self.http.get(&url)).await?;
+                let search_json: serde_json::Value = search_res.json().await.map_err(|e| {
+                    ErrorData::internal_error(format!("Failed to parse search JSON: {}", e), None)
+                })?;
+                let mut candidates = Candidate::from_search(&search_json);
+                if candidates.is_empty() {
+                    return Ok(CallToolResult::error(vec![Content::text(format!(
+                        "No results found for system: {}",
+                        args.system_name
+                    ))]));
+                }
 
-            // 2. Download Asset
-            let file_name = format!("{}_{}", name.replace(" ", "_"), asset_id);
-            let path = format!("assets/{}.rbxm", file_name);
-            let _ = tokio::fs::create_dir_all("assets").await;
+                // 2. Download, scan and score each candidate
+                let mut downloads = HashMap::new();
+                let mut ranked = Vec::new();
+                for candidate in &mut candidates {
+                    if candidate.favorites.is_none() {
+                        candidate.favorites = self.favorite_count(candidate.asset_id).await;
+                    }
+                    let scan = match self.fetch_asset(candidate.asset_id).await {
+                        Ok(bytes) => {
+                            let scan = self.scan_model(bytes.clone()).await;
+                            downloads.insert(candidate.asset_id, bytes);
+                            scan
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                "Could not download candidate {}: {e:?}",
+                                candidate.asset_id
+                            );
+                            None
+                        }
+                    };
+                    ranked.push(ranking::score(
+                        candidate,
+                        scan.as_ref(),
+                        self.config.scanner.block,
+                    ));
+                }
+                ranking::sort(&mut ranked);
 
-            let download_url = format!("https://assetdelivery.roblox.com/v1/asset?id={}", asset_id);
-            let download_res =
-                client.get(&download_url).send().await.map_err(|e| {
-                    ErrorData::internal_error(format!("Download failed: {}", e), None)
+                let pick = if args.auto_pick.unwrap_or(true) {
+                    ranking::confident_pick(&ranked)
+                } else {
+                    None
+                };
+                let Some(pick) = pick else {
+                    let shortlist = serde_json::to_string_pretty(&ranked)
+                        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+                    return Ok(CallToolResult::success(vec![Content::text(format!(
+                        "Nothing was installed: no candidate for '{}' is a clear, clean winner. Pick one from this ranking and call install_system again with its asset_id.\n{}",
+                        args.system_name, shortlist
+                    ))]));
+                };
+                let reason = format!(
+                    "scored {} of 100, the best of {} candidates: {}",
+                    pick.score,
+                    ranked.len(),
+                    pick.reasons.join(", ")
+                );
+                let bytes = downloads.remove(&pick.asset_id).ok_or_else(|| {
+                    ErrorData::internal_error("Picked an asset that wasn't downloaded", None)
                 })?;
+                let candidate = candidates
+                    .into_iter()
+                    .find(|candidate| candidate.asset_id == pick.asset_id)
+                    .ok_or_else(|| ErrorData::internal_error("Picked an unknown asset", None))?;
+                (candidate, bytes, reason)
+            }
+        };
+        let asset_id = candidate.asset_id;
+        let name = candidate.name.as_str();
 
-            if !download_res.status().is_success() {
+        // 3. Scan the download, and keep it in the library only once it passes
+        let (file, bytes) = tokio::task::spawn_blocking(move || (AssetFile::read(&bytes), bytes))
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
                 return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Failed to download asset {}: Status {}",
-                    asset_id,
-                    download_res.status()
+                    "Refused to install '{name}' (Asset ID: {asset_id}): it could not be read as a model to scan it: {e}"
                 ))]));
             }
+        };
+        let report = scanner::scan(&file, self.api_index().await);
+        if report.blocked_by(self.config.scanner.block) {
+            return Ok(CallToolResult::error(vec![Content::text(format!(
+                "Refused to install '{}' (Asset ID: {}): the backdoor scan found it {}",
+                name,
+                asset_id,
+                report.summary()
+            ))]));
+        }
+        let scan = report.summary();
 
-            let bytes = download_res
-                .bytes()
-                .await
-                .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-            tokio::fs::write(&path, bytes)
-                .await
-                .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
-
-            // 3. Insert into Studio
-            // Convert struct to ToolArguments like generic_tool_run does, but we are inside the server.
-            // We need to construct the ToolArguments message manually and send it.
-            let insert_args = InsertModel {
-                query: None,
-                asset_id: Some(asset_id),
-            };
-
-            let tool_val = ToolArgumentValues::InsertModel(insert_args);
-            let insert_result_raw = self.generic_tool_run(tool_val).await?;
-            // insert_result is CallToolResult. We need the text content.
-            // generic_tool_run returns CallToolResult.
-            // The content[0].text is the model name.
-            let model_name = insert_result_raw
-                .content
-                .first()
-                .and_then(|c| match &c.raw {
-                    rmcp::model::RawContent::Text(t) => Some(t.text.clone()),
-                    _ => None,
-                })
-                .ok_or(ErrorData::internal_error(
-                    "Failed to get model name from insert result",
-                    None,
-                ))?;
+        // 4. Save it to the library
+        let file_name = match library::sanitize_file_name(name) {
+            Some(name) => format!("{name}_{asset_id}.rbxm"),
+            None => format!("{asset_id}.rbxm"),
+        };
+        let path = self.config.asset_dir.join(&file_name).display().to_string();
+        let info = AssetInfo {
+            asset_id,
+            version: None,
+            name: Some(name.to_string()),
+            creator: candidate.creator.clone(),
+            asset_type: Some("Model".to_string()),
+        };
+        self.library
+            .add(info, &file_name, &bytes)
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
 
-            // 4. Smart Unpack
-            let unpack_args = SmartUnpack {
-                target_name: model_name.clone(),
-            };
-            let unpack_val = ToolArgumentValues::SmartUnpack(unpack_args);
-            let unpack_result = self.generic_tool_run(unpack_val).await?;
-
-            // Return combined result
-            Ok(CallToolResult::success(vec![Content::text(format!(
-                 "Successfully installed System '{}' (Asset ID: {}).\nFile saved to: {}\nStudio Response: {}\nUnpack Response: {:?}", 
-                 name, asset_id, path, model_name, unpack_result
-             ))]))
-        } else {
-            Ok(CallToolResult::error(vec![Content::text(format!(
-                "No results found for system: {}",
-                query
-            ))]))
-        }
+        // 5. Insert the file that was scanned, rather than have Studio download the asset again
+        let insert_result_raw = self.insert_file(name.to_string(), &file).await?;
+        // insert_result is CallToolResult. We need the text content.
+        // generic_tool_run returns CallToolResult.
+        // The content[0].text is the model name.
+        let model_name = insert_result_raw
+            .content
+            .first()
+            .and_then(|c| match &c.raw {
+                rmcp::model::RawContent::Text(t) => Some(t.text.clone()),
+                _ => None,
+            })
+            .ok_or(ErrorData::internal_error(
+                "Failed to get model name from insert result",
+                None,
+            ))?;
+
+        // 6. Smart Unpack
+        let unpack_args = SmartUnpack {
+            target_name: model_name.clone(),
+        };
+        let unpack_val = ToolArgumentValues::SmartUnpack(unpack_args);
+        let unpack_result = self.generic_tool_run(unpack_val).await?;
+
+        // Return combined result
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Successfully installed System '{}' (Asset ID: {}).\nWhy this one: {}\nFile saved to: {}\nBackdoor scan: {}\nStudio Response: {}\nUnpack Response: {:?}",
+            name, asset_id, reason, path, scan, model_name, unpack_result
+        ))]))
     }
 
     #[allow(dead_code)]
@@ -892,7 +1820,7 @@ impl RBXStudioServer {
     }
 
     #[tool(
-        description = "Search Roblox Creator Store/Toolbox for assets (models, scripts, audio, etc.) and optionally download them"
+        description = "Search Roblox Creator Store/Toolbox for assets (models, scripts, audio, etc.) and optionally download them. Filter by creator (user or group), verified creators, free assets and audio duration, sort by relevance, top or recent, and continue with page_token"
     )]
     async fn search_creator_store(
         &self,
@@ -911,98 +1839,41 @@ impl RBXStudioServer {
         // Map asset type to searchCategoryType
         let asset_type = args.asset_type.unwrap_or("Model".to_string());
         let limit = args.limit.unwrap_or(10).min(100);
+        args.filters
+            .validate(&asset_type)
+            .map_err(|e| ErrorData::invalid_params(e, None))?;
 
         // Build the search URL
-        let url = format!(
-            "https://apis.roblox.com/toolbox-service/v2/assets:search?searchCategoryType={}&query={}&maxPageSize={}",
-            asset_type,
+        let mut url = format!(
+            "https://apis.roblox.com/toolbox-service/v2/assets:search?searchCategoryType={}&query={}&maxPageSize={}{}",
+            urlencoding::encode(&asset_type),
             urlencoding::encode(&args.query),
-            limit
+            limit,
+            args.filters.query_string()
         );
-
-        // Retry logic with exponential backoff
-        let max_retries = 3;
-        let mut search_json: Option<serde_json::Value> = None;
-        let mut last_error = String::new();
-
-        for attempt in 0..max_retries {
-            if attempt > 0 {
-                let backoff_ms = 1000 * (2_u64.pow(attempt as u32));
-                tracing::warn!("Retry attempt {} after {}ms delay", attempt + 1, backoff_ms);
-                tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms)).await;
-            }
-
-            // Make the request with timeout
-            let client = reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .map_err(|e| {
-                    ErrorData::internal_error(format!("Failed to build client: {}", e), None)
-                })?;
-
-            let res = client.get(&url).send().await;
-
-            match res {
-                Ok(response) => {
-                    let status = response.status();
-
-                    if status.is_success() {
-                        match response.json().await {
-                            Ok(json) => {
-                                search_json = Some(json);
-                                break;
-                            }
-                            Err(e) => {
-                                last_error = format!("Failed to parse response: {}", e);
-                                tracing::error!(
-                                    "Parse error on attempt {}: {}",
-                                    attempt + 1,
-                                    last_error
-                                );
-                                continue;
-                            }
-                        }
-                    } else if status == reqwest::StatusCode::GATEWAY_TIMEOUT
-                        || status == reqwest::StatusCode::REQUEST_TIMEOUT
-                    {
-                        last_error = format!("Request timeout ({})", status);
-                        tracing::warn!("Timeout on attempt {}: {}", attempt + 1, last_error);
-                        continue;
-                    } else if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
-                        last_error = "Rate limit exceeded".to_string();
-                        tracing::warn!("Rate limited on attempt {}", attempt + 1);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                        continue;
-                    } else {
-                        let error_text = response.text().await.unwrap_or_default();
-                        last_error =
-                            format!("Search failed with status {}: {}", status, error_text);
-                        tracing::error!("Error on attempt {}: {}", attempt + 1, last_error);
-                        if status.is_client_error()
-                            && status != reqwest::StatusCode::TOO_MANY_REQUESTS
-                        {
-                            break;
-                        }
-                        continue;
-                    }
-                }
-                Err(e) => {
-                    last_error = format!("Search request failed: {}", e);
-                    tracing::error!("Network error on attempt {}: {}", attempt + 1, last_error);
-                    continue;
-                }
-            }
+        if let Some(token) = &args.page_token {
+            url.push_str(&format!("&pageToken={}", urlencoding::encode(token)));
         }
 
-        let search_json = match search_json {
-            Some(json) => json,
-            None => {
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Failed after {} attempts. Last error: {}\n\n💡 Tip: The Roblox Creator Store API may be experiencing issues or rate limiting. Try again in a few moments.",
-                    max_retries, last_error
-                ))]));
-            }
-        };
+        let request = serde_json::json!({
+            "query": cache::normalize_query(&args.query),
+            "asset_type": asset_type.to_lowercase(),
+            "limit": limit,
+            "filters": args.filters,
+            "page_token": args.page_token,
+        });
+        // Retries and rate limiting are handled by the shared client
+        let cached = self
+            .cache
+            .get_or_fetch(
+                CacheNamespace::CreatorStore,
+                request,
+                &args.cache,
+                || async { Ok(self.http.send(self.http.get(&url)).await?.text().await?) },
+            )
+            .await?;
+        let search_json: serde_json::Value = serde_json::from_str(&cached.body)
+            .map_err(|e| ErrorData::internal_error(format!("Invalid response: {e}"), None))?;
 
         // Extract results
         let assets = search_json
@@ -1018,7 +1889,7 @@ impl RBXStudioServer {
         }
 
         let mut result_text = format!("Found {} {} assets:\n\n", assets.len(), asset_type);
-        let mut asset_ids = Vec::new();
+        let mut to_insert = Vec::new();
 
         for (idx, asset) in assets.iter().enumerate() {
             let asset_obj = asset.get("asset");
@@ -1046,51 +1917,80 @@ impl RBXStudioServer {
                 name,
                 id,
                 creator,
-                if description.len() > 100 {
-                    &description[..100]
-                } else {
-                    description
+                match description.char_indices().nth(100) {
+                    Some((cut, _)) => &description[..cut],
+                    None => description,
                 }
             ));
 
-            // Collect asset IDs for insertion
+            // Collect assets for insertion
             if download_enabled && id > 0 {
-                asset_ids.push(id);
+                to_insert.push((id, name.to_string()));
             }
         }
 
         // If download is enabled, insert assets into Roblox Studio via plugin
-        if download_enabled && !asset_ids.is_empty() {
+        let mut scanned = Vec::new();
+        let mut asset_ids = Vec::new();
+        if download_enabled {
+            for (id, name) in to_insert {
+                match self.screen_asset(id).await {
+                    Ok(screened) => {
+                        result_text.push_str(&format!("{}\n", screened.note));
+                        match screened.file {
+                            Some(file) => scanned.push((id, name, file)),
+                            None => asset_ids.push(id),
+                        }
+                    }
+                    Err(refusal) => result_text.push_str(&format!("⚠ {refusal}\n")),
+                }
+            }
+        }
+        if download_enabled && !(scanned.is_empty() && asset_ids.is_empty()) {
             result_text.push_str(&format!(
                 "\n🎮 Inserting {} assets into Roblox Studio...\n",
-                asset_ids.len()
+                scanned.len() + asset_ids.len()
             ));
 
-            // Create InsertAssets command for the plugin
-            let _insert_args = serde_json::json!({
-                "InsertAssets": {
-                    "asset_ids": asset_ids
-                }
-            });
-
-            // Send to plugin via generic_tool_run
-            let insert_result = self
-                .generic_tool_run(ToolArgumentValues::InsertAssets(InsertAssetsArgs {
-                    asset_ids: asset_ids.clone(),
-                }))
-                .await;
+            // Scanned models go in as the files that were scanned
+            let mut results = Vec::new();
+            for (id, name, file) in &scanned {
+                let name = if name.is_empty() || name == "Unknown" {
+                    format!("Asset{id}")
+                } else {
+                    name.clone()
+                };
+                results.push((Some(*id), self.insert_file(name, file).await));
+            }
+            // Whatever wasn't scanned, such as audio or images, Studio downloads itself
+            if !asset_ids.is_empty() {
+                let result = self
+                    .generic_tool_run(ToolArgumentValues::InsertAssets(InsertAssetsArgs {
+                        asset_ids: asset_ids.clone(),
+                    }))
+                    .await;
+                results.push((None, result));
+            }
 
-            match insert_result {
-                Ok(tool_result) => {
-                    // Extract text from the result
-                    for content in &tool_result.content {
-                        if let Some(text_content) = content.as_text() {
-                            result_text.push_str(&format!("\n{}\n", text_content.text));
+            for (id, insert_result) in results {
+                match insert_result {
+                    Ok(tool_result) => {
+                        // Extract text from the result
+                        for content in &tool_result.content {
+                            if let Some(text_content) = content.as_text() {
+                                let text = match id {
+                                    Some(id) => {
+                                        format!("Inserted asset {id} as {}", text_content.text)
+                                    }
+                                    None => text_content.text.clone(),
+                                };
+                                result_text.push_str(&format!("\n{text}\n"));
+                            }
                         }
                     }
-                }
-                Err(e) => {
-                    result_text.push_str(&format!("\n⚠ Failed to insert assets: {}\n", e));
+                    Err(e) => {
+                        result_text.push_str(&format!("\n⚠ Failed to insert assets: {}\n", e));
+                    }
                 }
             }
         } else if !download_enabled {
@@ -1099,13 +1999,323 @@ impl RBXStudioServer {
                 .push_str("   Example: search_creator_store(query=\"sword\", download=true)\n");
         }
 
-        Ok(CallToolResult::success(vec![Content::text(result_text)]))
+        if let Some(token) = search_json.get("nextPageToken").and_then(|v| v.as_str()) {
+            if !token.is_empty() {
+                result_text.push_str(&format!(
+                    "\nMore results: pass next_page_token as page_token to continue.\nnext_page_token: {token}\n"
+                ));
+            }
+        }
+
+        let mut content = vec![Content::text(result_text)];
+        content.extend(cached.notice());
+        Ok(CallToolResult::success(content))
+    }
+
+    #[tool(
+        description = "Everything known about an asset id: name, full description, creator and whether they are verified, created and updated dates, price, favorites, asset type, and whether it is archived or removed by moderation. Served from the cache when possible."
+    )]
+    async fn get_asset_details(
+        &self,
+        Parameters(args): Parameters<GetAssetDetails>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let asset_id = args.asset_id;
+        let request = serde_json::json!({ "asset_id": asset_id });
+        let cached = self
+            .cache
+            .get_or_fetch(
+                CacheNamespace::AssetDetails,
+                request,
+                &args.cache,
+                || async {
+                    let details = self.economy_details(asset_id).await?;
+                    let favorites = self.favorite_count(asset_id).await;
+                    let archived = self.is_archived(asset_id).await;
+                    Ok(
+                        serde_json::json!(AssetDetails::new(details, favorites, archived))
+                            .to_string(),
+                    )
+                },
+            )
+            .await?;
+        let mut details: serde_json::Value = serde_json::from_str(&cached.body)
+            .map_err(|e| ErrorData::internal_error(format!("Invalid cached details: {e}"), None))?;
+        if let Some(entry) = self.library.get(asset_id, None).await {
+            details["library"] = serde_json::json!(entry);
+        }
+        let text = serde_json::to_string_pretty(&details)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let mut content = vec![Content::text(text)];
+        content.extend(cached.notice());
+        Ok(CallToolResult::success(content))
+    }
+
+    #[tool(
+        description = "Clear cached Creator Store and Marketplace search responses and asset details, so the next request goes to the API"
+    )]
+    async fn clear_cache(
+        &self,
+        Parameters(args): Parameters<ClearCache>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let removed = self
+            .cache
+            .clear(args.namespace)
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Cleared {removed} cached responses"
+        ))]))
     }
 
     async fn generic_tool_run(
         &self,
         args: ToolArgumentValues,
     ) -> Result<CallToolResult, ErrorData> {
+        if let Some(cursor) = args.next_page() {
+            return self.cached_page(cursor).await;
+        }
+        let paginated = args.is_paginated();
+        let shape = args.output_shape().cloned();
+        let returns_values = args.returns_values();
+        let mut result = self.send_to_plugin(args).await?;
+        if returns_values {
+            result = result.map(datatypes::decode_response);
+        }
+        tracing::debug!("Sending to MCP: {result:?}");
+        let result = match shape {
+            Some(shape) => result.map(|output| shape.apply(output)),
+            None => result,
+        };
+        match result {
+            Ok(result) if paginated => {
+                let page = self.state.lock().await.pages.paginate(&result);
+                Ok(match page {
+                    Some(page) => page.into_call_tool_result(),
+                    None => CallToolResult::success(vec![Content::text(result)]),
+                })
+            }
+            Ok(result) => Ok(CallToolResult::success(vec![Content::text(result)])),
+            Err(err) => Ok(CallToolResult::error(vec![Content::text(err.to_string())])),
+        }
+    }
+
+    /// Scans the scripts in a downloaded file. `None` for files that aren't models, such as
+    /// audio or images, which have no scripts.
+    async fn scan_model(&self, bytes: Vec<u8>) -> Option<ScanReport> {
+        let file = tokio::task::spawn_blocking(move || AssetFile::read(&bytes))
+            .await
+            .ok()?
+            .ok()?;
+        Some(scanner::scan(&file, self.api_index().await))
+    }
+
+    /// Downloads and scans asset `id` before it is inserted. Returns the scanned model with a
+    /// note on the scan for the tool output, or the refusal when `scanner.block` says the asset
+    /// must not go in. A model that can't be downloaded or read is refused too, unless
+    /// `scanner.block` is `never`.
+    async fn screen_asset(&self, id: u64) -> Result<Screened, String> {
+        let unscanned = |reason: String| {
+            if self.config.scanner.block == BlockLevel::Never {
+                Ok(Screened {
+                    note: format!("Asset {id} was not scanned for backdoors, {reason}"),
+                    file: None,
+                })
+            } else {
+                Err(format!(
+                    "Refused to insert asset {id}, it could not be scanned for backdoors: {reason}"
+                ))
+            }
+        };
+        let url = format!("https://assetdelivery.roblox.com/v1/asset?id={id}");
+        let bytes = match self.http.send(self.http.get(&url)).await {
+            Ok(response) => response.bytes().await.map_err(HttpError::from),
+            Err(e) => Err(e),
+        };
+        let bytes = match bytes {
+            Ok(bytes) => bytes,
+            Err(e) => return unscanned(format!("the download failed: {e}")),
+        };
+        let is_model = matches!(
+            ContentKind::sniff(&bytes),
+            ContentKind::BinaryModel | ContentKind::XmlModel
+        );
+        if !is_model {
+            return Ok(Screened {
+                note: format!("Asset {id} is not a model, nothing to scan"),
+                file: None,
+            });
+        }
+        let file = tokio::task::spawn_blocking(move || AssetFile::read(&bytes))
+            .await
+            .ok()
+            .and_then(Result::ok);
+        let Some(file) = file else {
+            return unscanned("the model file could not be read".to_string());
+        };
+        let report = scanner::scan(&file, self.api_index().await);
+        if report.blocked_by(self.config.scanner.block) {
+            return Err(format!(
+                "Refused to insert asset {id}, the backdoor scan found it {}",
+                report.summary()
+            ));
+        }
+        Ok(Screened {
+            note: format!("Backdoor scan of asset {id}: {}", report.summary()),
+            file: Some(file),
+        })
+    }
+
+    /// The top Creator Store model result for `query`, `None` when there are no results.
+    async fn top_model(&self, query: &str) -> Result<Option<Candidate>, ErrorData> {
+        let url = format!(
+            "https://apis.roblox.com/toolbox-service/v2/assets:search?searchCategoryType=Model&query={}&maxPageSize=1",
+            urlencoding::encode(query)
+        );
+        let response = self.http.send(self.http.get(&url)).await?;
+        let json: serde_json::Value = response.json().await.map_err(|e| {
+            ErrorData::internal_error(format!("Failed to parse search JSON: {e}"), None)
+        })?;
+        Ok(Candidate::from_search(&json).into_iter().next())
+    }
+
+    /// Sends a model file to the plugin to insert into the workspace under `name`.
+    async fn insert_file(
+        &self,
+        name: String,
+        file: &AssetFile,
+    ) -> Result<CallToolResult, ErrorData> {
+        let data = file
+            .to_binary()
+            .map_err(|e| ErrorData::internal_error(e, None))?;
+        self.generic_tool_run(ToolArgumentValues::InsertLibraryAsset(LibraryInsert {
+            name,
+            data: BASE64_STANDARD.encode(data),
+        }))
+        .await
+    }
+
+    /// Asset `id` as the economy API describes it.
+    async fn economy_details(&self, id: u64) -> Result<EconomyDetails, HttpError> {
+        let url = format!("https://economy.roblox.com/v2/assets/{id}/details");
+        Ok(self.http.send(self.http.get(&url)).await?.json().await?)
+    }
+
+    /// Triangles in the most detailed level of mesh asset `id`.
+    async fn mesh_triangles(&self, id: u64) -> Result<Option<u64>, ErrorData> {
+        let bytes = self.fetch_asset(id).await?;
+        Ok(asset_file::mesh_triangles(&bytes))
+    }
+
+    /// The latest version of asset `id`, from the asset delivery API.
+    async fn fetch_asset(&self, id: u64) -> Result<Vec<u8>, ErrorData> {
+        let url = format!("https://assetdelivery.roblox.com/v1/asset?id={id}");
+        let response = self.http.send(self.http.get(&url)).await?;
+        let bytes = response.bytes().await.map_err(HttpError::from)?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Whether asset `id` is archived, when the develop API answers without a login.
+    async fn is_archived(&self, id: u64) -> Option<bool> {
+        let url = format!("https://develop.roblox.com/v1/assets?assetIds={id}");
+        let response = self.http.send(self.http.get(&url)).await.ok()?;
+        let json: serde_json::Value = response.json().await.ok()?;
+        json.get("data")?.get(0)?.get("isArchived")?.as_bool()
+    }
+
+    /// How many users favorited asset `id`, when the catalog API answers.
+    async fn favorite_count(&self, id: u64) -> Option<u64> {
+        let url = format!("https://catalog.roblox.com/v1/favorites/assets/{id}/count");
+        let response = self.http.send(self.http.get(&url)).await.ok()?;
+        response.json().await.ok()
+    }
+
+    /// Library entries as a paginated `results` list, newest first.
+    async fn library_listing(
+        &self,
+        mut entries: Vec<LibraryEntry>,
+    ) -> Result<CallToolResult, ErrorData> {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.downloaded_at));
+        let listing = serde_json::json!({
+            "dir": self.config.asset_dir,
+            "count": entries.len(),
+            "results": entries,
+        });
+        let text = listing.to_string();
+        let page = self.state.lock().await.pages.paginate(&text);
+        Ok(match page {
+            Some(page) => page.into_call_tool_result(),
+            None => CallToolResult::success(vec![Content::text(text)]),
+        })
+    }
+
+    /// The page `cursor` points to in an output paginated earlier.
+    async fn cached_page(&self, cursor: &str) -> Result<CallToolResult, ErrorData> {
+        let page = self.state.lock().await.pages.page(cursor);
+        page.map(|page| page.into_call_tool_result())
+            .map_err(|e| ErrorData::invalid_params(e, None))
+    }
+
+    /// Rejects a write of `property` on the instances at `paths` that the API index says would
+    /// fail, before it is queued. Skipped when the plugin can't report the classes.
+    async fn check_writes(
+        &self,
+        paths: &[PathPattern],
+        property: &str,
+        value: &PropertyValue,
+    ) -> Result<(), ErrorData> {
+        if !self.config.api_dump.validate_writes || paths.is_empty() {
+            return Ok(());
+        }
+        let args = ToolArgumentValues::GetClassNames(GetClassNames {
+            paths: paths.to_vec(),
+        });
+        let response = match self.send_to_plugin(args).await? {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!("Not validating {property}: {e}");
+                return Ok(());
+            }
+        };
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap_or_default();
+        let paths: Vec<String> = paths.iter().map(PathPattern::to_string).collect();
+        // Classes in order of first appearance, each with the paths that have it. Patterns
+        // get the list of classes they matched
+        let mut classes: Vec<(&str, Vec<&str>)> = Vec::new();
+        for path in &paths {
+            let matched = &response["classNames"][path.as_str()];
+            let matched = match matched.as_array() {
+                Some(matched) => matched.iter().filter_map(|class| class.as_str()).collect(),
+                None => matched.as_str().into_iter().collect::<Vec<_>>(),
+            };
+            for class in matched {
+                match classes.iter_mut().find(|(name, _)| *name == class) {
+                    Some((_, class_paths)) => class_paths.push(path),
+                    None => classes.push((class, vec![path])),
+                }
+            }
+        }
+
+        let api = self.api_index().await;
+        let errors: Vec<String> = classes
+            .iter()
+            .filter_map(|(class, class_paths)| {
+                let error = validation::check_property_write(api, class, property, value).err()?;
+                Some(if paths.len() > 1 {
+                    format!("{error} ({})", class_paths.join(", "))
+                } else {
+                    error
+                })
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ErrorData::invalid_params(errors.join("\n"), None))
+        }
+    }
+
+    /// Queues `args` for the Studio plugin and waits for its response.
+    async fn send_to_plugin(&self, args: ToolArgumentValues) -> Result<Result<String>, ErrorData> {
         let (command, id) = ToolArguments::new(args);
         tracing::debug!("Running command: {:?}", command);
         let (tx, mut rx) = mpsc::unbounded_channel::<Result<String>>();
@@ -1115,23 +2325,78 @@ impl RBXStudioServer {
             state.output_map.insert(id, tx);
             state.trigger.clone()
         };
+        let _pending = PendingCall {
+            state: self.state.clone(),
+            id,
+        };
         trigger
             .send(())
             .map_err(|e| ErrorData::internal_error(format!("Unable to trigger send {e}"), None))?;
-        let result = rx
-            .recv()
+        rx.recv()
             .await
-            .ok_or(ErrorData::internal_error("Couldn't receive response", None))?;
-        {
-            let mut state = self.state.lock().await;
-            state.output_map.remove_entry(&id);
-        }
-        tracing::debug!("Sending to MCP: {result:?}");
-        match result {
-            Ok(result) => Ok(CallToolResult::success(vec![Content::text(result)])),
-            Err(err) => Ok(CallToolResult::error(vec![Content::text(err.to_string())])),
+            .ok_or(ErrorData::internal_error("Couldn't receive response", None))
+    }
+}
+
+/// An asset `screen_asset` let through.
+struct Screened {
+    /// The scan result, or why there was none, for the tool output
+    note: String,
+    /// The scanned model, to insert in place of a fresh download; `None` when nothing was
+    /// scanned
+    file: Option<AssetFile>,
+}
+
+/// A command sent to the plugin, withdrawn when dropped: answered, or abandoned by a tool
+/// timeout or a cancelled request. Once withdrawn, a late response finds no caller and the
+/// plugin no longer picks the command up.
+struct PendingCall {
+    state: PackedState,
+    id: Uuid,
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        let state = self.state.clone();
+        let id = self.id;
+        tokio::spawn(async move {
+            let mut state = state.lock().await;
+            state.output_map.remove(&id);
+            state.process_queue.retain(|command| command.id != Some(id));
+        });
+    }
+}
+
+fn json_result(value: &impl Serialize) -> Result<CallToolResult, ErrorData> {
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+    Ok(CallToolResult::success(vec![Content::text(text)]))
+}
+
+fn not_in_library(asset_id: u64, version: Option<u64>) -> ErrorData {
+    let asset = match version {
+        Some(version) => format!("Version {version} of asset {asset_id}"),
+        None => format!("Asset {asset_id}"),
+    };
+    ErrorData::invalid_params(
+        format!("{asset} is not in the library, download it with download_asset"),
+        None,
+    )
+}
+
+/// A member as it appears in the API dump, plus `DeclaredBy` when `class` inherits it.
+fn member_json(member: &MemberRef, class: &str) -> serde_json::Value {
+    let mut json = serde_json::to_value(member.member).unwrap_or_default();
+    if member.class.name != class {
+        json["DeclaredBy"] = member.class.name.clone().into();
+    }
+    if member.member.is_deprecated() {
+        json["Deprecated"] = true.into();
+        if let Some(preferred) = api_dump::preferred_name(member.member.tags()) {
+            json["PreferredName"] = preferred.into();
         }
     }
+    json
 }
 
 pub async fn request_handler(State(state): State<PackedState>) -> Result<impl IntoResponse> {
@@ -1159,12 +2424,47 @@ pub async fn response_handler(
     Json(payload): Json<RunCommandResponse>,
 ) -> Result<impl IntoResponse> {
     tracing::debug!("Received reply from studio {payload:?}");
-    let mut state = state.lock().await;
-    let tx = state
-        .output_map
-        .remove(&payload.id)
-        .ok_or_eyre("Unknown ID")?;
-    Ok(tx.send(Ok(payload.response))?)
+    let tx = state.lock().await.output_map.remove(&payload.id);
+    match tx {
+        Some(tx) => {
+            if tx.send(Ok(payload.response)).is_err() {
+                tracing::debug!("Caller of {} is gone, dropping its response", payload.id);
+            }
+        }
+        None => tracing::debug!("No pending call {}, dropping its response", payload.id),
+    }
+    Ok(())
+}
+
+pub async fn register_tools_handler(
+    State(state): State<PackedState>,
+    Json(payload): Json<DynamicToolList>,
+) -> Result<impl IntoResponse> {
+    let static_tools = RBXStudioServer::tool_router();
+    let rejected = state
+        .lock()
+        .await
+        .dynamic_tools
+        .replace(payload.tools, |name| static_tools.has_route(name));
+    if !rejected.is_empty() {
+        tracing::warn!("Rejected plugin tools with invalid or reserved names: {rejected:?}");
+    }
+    Ok(Json(serde_json::json!({ "rejected": rejected })))
+}
+
+pub async fn list_tools_handler(State(state): State<PackedState>) -> Result<impl IntoResponse> {
+    let tools = state.lock().await.dynamic_tools.specs();
+    Ok(Json(DynamicToolList { tools }))
+}
+
+/// Tells the client to refetch `tools/list` whenever the plugin advertises a different set of tools.
+pub async fn notify_tool_list_changed(state: PackedState, peer: Peer<RoleServer>) {
+    let mut changed = state.lock().await.dynamic_tools.subscribe();
+    while changed.changed().await.is_ok() {
+        if peer.notify_tool_list_changed().await.is_err() {
+            break;
+        }
+    }
 }
 
 pub async fn proxy_handler(
@@ -1188,7 +2488,7 @@ pub async fn proxy_handler(
     Ok(Json(RunCommandResponse { response, id }))
 }
 
-pub async fn dud_proxy_loop(state: PackedState, exit: Receiver<()>) {
+pub async fn dud_proxy_loop(state: PackedState, port: u16, exit: Receiver<()>) {
     let client = reqwest::Client::new();
 
     let mut waiter = { state.lock().await.waiter.clone() };
@@ -1196,25 +2496,24 @@ pub async fn dud_proxy_loop(state: PackedState, exit: Receiver<()>) {
         let entry = { state.lock().await.process_queue.pop_front() };
         if let Some(entry) = entry {
             let res = client
-                .post(format!("http://127.0.0.1:{STUDIO_PLUGIN_PORT}/proxy"))
+                .post(format!("http://127.0.0.1:{port}/proxy"))
                 .json(&entry)
                 .send()
                 .await;
             if let Ok(res) = res {
-                let tx = {
-                    state
-                        .lock()
-                        .await
-                        .output_map
-                        .remove(&entry.id.unwrap())
-                        .unwrap()
+                let id = entry.id.unwrap();
+                let Some(tx) = state.lock().await.output_map.remove(&id) else {
+                    tracing::debug!("No pending call {id}, dropping its proxied response");
+                    continue;
                 };
                 let res = res
                     .json::<RunCommandResponse>()
                     .await
                     .map(|r| r.response)
                     .map_err(Into::into);
-                tx.send(res).unwrap();
+                if tx.send(res).is_err() {
+                    tracing::debug!("Caller of {id} is gone, dropping its proxied response");
+                }
             } else {
                 tracing::error!("Failed to proxy: {res:?}");
             };
@@ -1223,3 +2522,116 @@ pub async fn dud_proxy_loop(state: PackedState, exit: Receiver<()>) {
         }
     }
 }
+
+/// Mirrors the tools registered with the instance that owns the plugin port, so they can be
+/// listed and called through the proxy as well.
+pub async fn dud_tool_sync_loop(state: PackedState, port: u16) {
+    let client = reqwest::Client::new();
+    loop {
+        let res = client
+            .get(format!("http://127.0.0.1:{port}/tools"))
+            .send()
+            .await;
+        match res {
+            Ok(res) => match res.json::<DynamicToolList>().await {
+                Ok(list) => {
+                    state
+                        .lock()
+                        .await
+                        .dynamic_tools
+                        .replace(list.tools, |_| false);
+                }
+                Err(e) => tracing::debug!("Failed to parse proxied tool list: {e}"),
+            },
+            Err(e) => tracing::debug!("Failed to fetch proxied tool list: {e}"),
+        }
+        tokio::time::sleep(LONG_POLL_DURATION).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+
+    /// Stands in for the instance that owns the plugin port, answering every proxied command
+    /// after `delay`.
+    async fn slow_proxy(delay: Duration) -> u16 {
+        let app = axum::Router::new().route(
+            "/proxy",
+            post(move |Json(command): Json<ToolArguments>| async move {
+                tokio::time::sleep(delay).await;
+                Json(RunCommandResponse {
+                    response: "done".to_string(),
+                    id: command.id.unwrap(),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        port
+    }
+
+    fn run_command() -> ToolArgumentValues {
+        ToolArgumentValues::RunCommand(RunCommand {
+            command: "print(1)".to_string(),
+        })
+    }
+
+    #[test]
+    fn max_tokens_policy_only_reaches_shaped_tools() {
+        let config = Config::default();
+        let server = RBXStudioServer::new(
+            Arc::new(Mutex::new(AppState::new())),
+            crate::client_log::layer().1.level(),
+            Arc::new(config.clone()),
+            Arc::new(Secrets::default()),
+            HttpClient::new(&config).unwrap(),
+        );
+        for tool in [
+            "get_file_tree",
+            "get_project_structure",
+            "get_instance_children",
+            "search_files",
+            "search_objects",
+            "search_by_property",
+        ] {
+            assert!(server.shapes_output(tool), "{tool}");
+        }
+        for tool in ["run_command", "query_instances", "plugin_tool"] {
+            assert!(!server.shapes_output(tool), "{tool}");
+        }
+    }
+
+    #[tokio::test]
+    async fn proxy_loop_survives_a_timed_out_call() {
+        let port = slow_proxy(Duration::from_millis(300)).await;
+        let state = Arc::new(Mutex::new(AppState::new()));
+        let (_exit_tx, exit_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(dud_proxy_loop(Arc::clone(&state), port, exit_rx));
+        let config = Arc::new(Config::default());
+        let server = RBXStudioServer::new(
+            state,
+            crate::client_log::layer().1.level(),
+            Arc::clone(&config),
+            Arc::new(Secrets::default()),
+            HttpClient::new(&config).unwrap(),
+        );
+
+        let timed_out = tokio::time::timeout(
+            Duration::from_millis(50),
+            server.send_to_plugin(run_command()),
+        )
+        .await;
+        assert!(timed_out.is_err());
+
+        let answered =
+            tokio::time::timeout(Duration::from_secs(5), server.send_to_plugin(run_command()))
+                .await
+                .expect("the proxy loop stopped answering")
+                .unwrap()
+                .unwrap();
+        assert_eq!(answered, "done");
+    }
+}
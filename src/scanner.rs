@@ -0,0 +1,549 @@
+//! Static scan of the scripts in a model for the usual backdoor and scam patterns of free
+//! models. Sources are matched after comments are blanked out, so explanations such as
+//! `-- never use loadstring` don't count.
+
+use crate::api_dump::{self, ApiIndex};
+use crate::asset_file::{self, AssetFile, ScriptSource};
+use crate::config::BlockLevel;
+use serde::Serialize;
+
+/// Lines longer than this are flagged as minified or obfuscated code.
+const LONG_LINE: usize = 1500;
+/// Escape sequences in a row (`\114\101...`) that count as a hidden string.
+const ESCAPE_RUN: usize = 8;
+/// Findings listed in [`ScanReport::summary`]; the report itself keeps all of them.
+const SUMMARY_FINDINGS: usize = 10;
+
+/// Classes scripts have no business being parented to. A script inside one of these, or
+/// named like one, is hiding.
+const ODD_PARENTS: [&str; 20] = [
+    "JointInstance",
+    "WeldConstraint",
+    "Constraint",
+    "Attachment",
+    "DataModelMesh",
+    "FaceInstance",
+    "Sound",
+    "Light",
+    "ValueBase",
+    "Camera",
+    "Humanoid",
+    "ParticleEmitter",
+    "Fire",
+    "Smoke",
+    "Sparkles",
+    "SurfaceAppearance",
+    "Animation",
+    "Beam",
+    "Trail",
+    "Clothing",
+];
+
+const PURCHASE_PROMPTS: [&str; 6] = [
+    "PromptPurchase",
+    "PromptProductPurchase",
+    "PromptGamePassPurchase",
+    "PromptPremiumPurchase",
+    "PromptBundlePurchase",
+    "PromptSubscriptionPurchase",
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Clean,
+    Suspicious,
+    Malicious,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Legitimate code does this too, but it deserves a look
+    Warning,
+    /// Hardly ever found outside backdoors
+    Danger,
+}
+
+impl Verdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Clean => "clean",
+            Verdict::Suspicious => "suspicious",
+            Verdict::Malicious => "malicious",
+        }
+    }
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Danger => "danger",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// Path of the script in the file
+    pub script: String,
+    /// 1-based; `None` for findings about the script itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub excerpt: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanReport {
+    pub verdict: Verdict,
+    pub scripts_scanned: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl ScanReport {
+    /// Whether inserts should be refused under the `scanner.block` setting.
+    pub fn blocked_by(&self, level: BlockLevel) -> bool {
+        match level {
+            BlockLevel::Never => false,
+            BlockLevel::Suspicious => self.verdict >= Verdict::Suspicious,
+            BlockLevel::Malicious => self.verdict >= Verdict::Malicious,
+        }
+    }
+
+    /// A few lines for tool output: the verdict and the first findings.
+    pub fn summary(&self) -> String {
+        let mut text = match self.verdict {
+            Verdict::Clean => format!("clean ({} scripts scanned)", self.scripts_scanned),
+            verdict => format!(
+                "{} ({} findings in {} scripts):",
+                verdict.as_str(),
+                self.findings.len(),
+                self.scripts_scanned
+            ),
+        };
+        for finding in self.findings.iter().take(SUMMARY_FINDINGS) {
+            let location = match finding.line {
+                Some(line) => format!("{}:{line}", finding.script),
+                None => finding.script.clone(),
+            };
+            text.push_str(&format!(
+                "\n  - [{}] {location}: {}",
+                finding.severity.as_str(),
+                finding.message
+            ));
+            if !finding.excerpt.is_empty() {
+                text.push_str(&format!("\n      {}", finding.excerpt));
+            }
+        }
+        if self.findings.len() > SUMMARY_FINDINGS {
+            text.push_str(&format!(
+                "\n  ... and {} more",
+                self.findings.len() - SUMMARY_FINDINGS
+            ));
+        }
+        text
+    }
+}
+
+/// Scans every script in `file`.
+pub fn scan(file: &AssetFile, api: &ApiIndex) -> ScanReport {
+    let scripts = file.scripts(api);
+    let mut findings = Vec::new();
+    for script in &scripts {
+        findings.extend(check_placement(api, script));
+        findings.extend(scan_source(&script.path, &script.source));
+    }
+    let verdict = match findings.iter().map(|finding| finding.severity).max() {
+        None => Verdict::Clean,
+        Some(Severity::Warning) => Verdict::Suspicious,
+        Some(Severity::Danger) => Verdict::Malicious,
+    };
+    ScanReport {
+        verdict,
+        scripts_scanned: scripts.len(),
+        findings,
+    }
+}
+
+/// Scripts tucked inside welds, meshes, sounds and the like, or named like them.
+fn check_placement(api: &ApiIndex, script: &ScriptSource) -> Option<Finding> {
+    let odd = |class: &str| {
+        ODD_PARENTS
+            .iter()
+            .any(|base| asset_file::is_a(api, class, base))
+    };
+    let message = if let Some(parent) = script.parent_class.filter(|parent| odd(parent)) {
+        format!("{} hidden inside a {parent}", script.class)
+    } else if script.name.trim().is_empty() {
+        format!("{} with a blank name", script.class)
+    } else if api
+        .class(script.name)
+        .is_some_and(|class| odd(script.name) || api_dump::has_tag(&class.tags, "Service"))
+    {
+        format!(
+            "{} named like a {}, to pass as one",
+            script.class, script.name
+        )
+    } else {
+        return None;
+    };
+    Some(Finding {
+        rule: "hidden-script",
+        severity: Severity::Warning,
+        script: script.path.clone(),
+        line: None,
+        excerpt: String::new(),
+        message,
+    })
+}
+
+/// Line by line checks of a script's source.
+fn scan_source(script: &str, source: &str) -> Vec<Finding> {
+    let code = strip_comments(source);
+    let original: Vec<&str> = source.lines().collect();
+    let mut findings = Vec::new();
+    for (index, line) in code.lines().enumerate() {
+        let mut flag = |rule, severity, message: &str| {
+            findings.push(Finding {
+                rule,
+                severity,
+                script: script.to_string(),
+                line: Some(index + 1),
+                excerpt: excerpt(original.get(index).unwrap_or(&line)),
+                message: message.to_string(),
+            });
+        };
+        if numeric_require(line) {
+            flag(
+                "require-id",
+                Severity::Danger,
+                "requires a module by asset id, the usual way backdoors load their payload",
+            );
+        }
+        if has_word(line, "loadstring") {
+            flag(
+                "loadstring",
+                Severity::Danger,
+                "runs code from a string with loadstring",
+            );
+        }
+        if has_word(line, "setfenv") {
+            flag(
+                "setfenv",
+                Severity::Danger,
+                "replaces a function environment with setfenv",
+            );
+        } else if has_word(line, "getfenv") {
+            flag(
+                "getfenv",
+                Severity::Warning,
+                "reaches into a function environment with getfenv",
+            );
+        }
+        if line.contains("discord.com/api/webhooks") || line.contains("discordapp.com/api/webhooks")
+        {
+            flag(
+                "webhook",
+                Severity::Danger,
+                "posts to a Discord webhook, often to report on the game it was inserted into",
+            );
+        } else if has_word(line, "HttpService") || has_method(line, "PostAsync") {
+            flag(
+                "http",
+                Severity::Warning,
+                "makes web requests, which can send data out of the game",
+            );
+        }
+        if PURCHASE_PROMPTS.iter().any(|prompt| has_word(line, prompt)) {
+            flag(
+                "purchase-prompt",
+                Severity::Warning,
+                "prompts players to buy something, which may earn the model's author Robux",
+            );
+        }
+        if has_method(line, "LoadAsset") {
+            flag(
+                "load-asset",
+                Severity::Warning,
+                "loads an asset at run time, which can bring in code that isn't in this model",
+            );
+        }
+        if obfuscated(line) {
+            flag(
+                "obfuscation",
+                Severity::Warning,
+                "builds strings from character codes, escapes or reversed text, which hides what the code does",
+            );
+        }
+        if line.len() > LONG_LINE {
+            flag(
+                "long-line",
+                Severity::Warning,
+                "has a very long line, typical of minified or obfuscated code",
+            );
+        }
+    }
+    findings
+}
+
+/// The start of `line`, trimmed, for showing a finding in context.
+fn excerpt(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(120) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Byte offsets where `word` occurs in `line` as a whole identifier.
+fn word_positions<'a>(line: &'a str, word: &'a str) -> impl Iterator<Item = usize> + 'a {
+    line.match_indices(word).filter_map(move |(start, _)| {
+        let before = line[..start].chars().next_back();
+        let after = line[start + word.len()..].chars().next();
+        let whole =
+            !before.is_some_and(is_identifier_char) && !after.is_some_and(is_identifier_char);
+        whole.then_some(start)
+    })
+}
+
+fn has_word(line: &str, word: &str) -> bool {
+    word_positions(line, word).next().is_some()
+}
+
+/// `:name(` or `.name(`, allowing spaces before the parenthesis.
+fn has_method(line: &str, name: &str) -> bool {
+    word_positions(line, name).any(|start| {
+        let before = line[..start].trim_end().chars().next_back();
+        let after = line[start + name.len()..].trim_start().chars().next();
+        matches!(before, Some(':' | '.')) && after == Some('(')
+    })
+}
+
+/// `require(12345)`, `require 0x3039` or `require(tonumber(...))`.
+fn numeric_require(line: &str) -> bool {
+    word_positions(line, "require").any(|start| {
+        let rest = line[start + "require".len()..].trim_start();
+        let rest = rest.strip_prefix('(').unwrap_or(rest).trim_start();
+        rest.starts_with(|c: char| c.is_ascii_digit()) || rest.starts_with("tonumber")
+    })
+}
+
+/// Character code strings (`string.char(104, 116, ...)`), long runs of escapes and reversed
+/// strings.
+fn obfuscated(line: &str) -> bool {
+    let char_codes = word_positions(line, "char").any(|start| {
+        let before = line[..start].chars().next_back();
+        let args = &line[start + "char".len()..];
+        let args = &args[..args.find(')').unwrap_or(args.len())];
+        matches!(before, Some('.' | ':')) && args.matches(',').count() >= 3
+    });
+    char_codes || has_method(line, "reverse") || escape_run(line) >= ESCAPE_RUN
+}
+
+/// Longest run of `\ddd` or `\xhh` escapes in a row.
+fn escape_run(line: &str) -> usize {
+    let bytes = line.as_bytes();
+    let (mut longest, mut run, mut i) = (0, 0, 0);
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            let digits = bytes[i + 1..]
+                .iter()
+                .take(3)
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            let hex = bytes.get(i + 1) == Some(&b'x')
+                && bytes
+                    .get(i + 2..i + 4)
+                    .is_some_and(|h| h.iter().all(u8::is_ascii_hexdigit));
+            if digits > 0 || hex {
+                run += 1;
+                longest = longest.max(run);
+                i += 1 + if hex { 3 } else { digits };
+                continue;
+            }
+        }
+        run = 0;
+        i += 1;
+    }
+    longest
+}
+
+/// `source` with comments replaced by spaces, keeping strings and line numbers.
+fn strip_comments(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                i += 2;
+                if let Some(level) = long_bracket(&chars, i) {
+                    let end = long_bracket_end(&chars, i + level + 2, level);
+                    blank(&mut out, &chars[i - 2..end]);
+                    i = end;
+                } else {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+            }
+            '[' if long_bracket(&chars, i).is_some() => {
+                let level = long_bracket(&chars, i).unwrap_or_default();
+                let end = long_bracket_end(&chars, i + level + 2, level);
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            '"' | '\'' | '`' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(chars.len());
+                out.extend(&chars[start..i]);
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Level of the long bracket (`[[`, `[==[`) opening at `i`, if there is one.
+fn long_bracket(chars: &[char], i: usize) -> Option<usize> {
+    if chars.get(i) != Some(&'[') {
+        return None;
+    }
+    let level = chars[i + 1..].iter().take_while(|c| **c == '=').count();
+    (chars.get(i + 1 + level) == Some(&'[')).then_some(level)
+}
+
+/// Position just past the `]=*]` closing a long bracket of `level`, or the end of the source.
+fn long_bracket_end(chars: &[char], from: usize, level: usize) -> usize {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == ']'
+            && chars
+                .get(i + 1..i + 1 + level)
+                .is_some_and(|equals| equals.iter().all(|c| *c == '='))
+            && chars.get(i + 1 + level) == Some(&']')
+        {
+            return i + level + 2;
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+/// Spaces in place of `text`, keeping its line breaks.
+fn blank(out: &mut String, text: &[char]) {
+    out.extend(text.iter().map(|c| if *c == '\n' { '\n' } else { ' ' }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan_fixture(file_name: &str) -> ScanReport {
+        let path = format!(
+            "{}/tests/fixtures/scanner/{file_name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let bytes = std::fs::read(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+        let file = AssetFile::read(&bytes).unwrap();
+        scan(&file, &ApiIndex::bundled())
+    }
+
+    fn rules(report: &ScanReport) -> Vec<(&'static str, &str, Option<usize>)> {
+        report
+            .findings
+            .iter()
+            .map(|finding| (finding.rule, finding.script.as_str(), finding.line))
+            .collect()
+    }
+
+    #[test]
+    fn clean_model_ignores_comments() {
+        let report = scan_fixture("clean.rbxmx");
+        assert_eq!(report.verdict, Verdict::Clean);
+        assert_eq!(report.scripts_scanned, 1);
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+        assert!(!report.blocked_by(BlockLevel::Suspicious));
+    }
+
+    #[test]
+    fn require_by_id_is_malicious() {
+        let report = scan_fixture("require_id.rbxmx");
+        assert_eq!(report.verdict, Verdict::Malicious);
+        assert_eq!(
+            rules(&report),
+            [("require-id", "Sword.Handle.Damage", Some(2))]
+        );
+        assert_eq!(report.findings[0].severity, Severity::Danger);
+        assert_eq!(report.findings[0].excerpt, "require(4837261940).load(game)");
+        assert!(report.blocked_by(BlockLevel::Malicious));
+        assert!(!report.blocked_by(BlockLevel::Never));
+    }
+
+    #[test]
+    fn loadstring_and_getfenv() {
+        let report = scan_fixture("loadstring_getfenv.rbxmx");
+        assert_eq!(report.verdict, Verdict::Malicious);
+        assert_eq!(report.scripts_scanned, 2);
+        assert_eq!(
+            rules(&report),
+            [
+                ("loadstring", "AdminKit.Loader", Some(2)),
+                ("getfenv", "AdminKit.Env", Some(1)),
+            ]
+        );
+        assert_eq!(report.findings[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn character_code_strings_are_suspicious() {
+        let report = scan_fixture("obfuscated.rbxmx");
+        assert_eq!(report.verdict, Verdict::Suspicious);
+        assert_eq!(rules(&report), [("obfuscation", "Tree.Leaves", Some(1))]);
+        assert!(report.blocked_by(BlockLevel::Suspicious));
+        assert!(!report.blocked_by(BlockLevel::Malicious));
+    }
+
+    #[test]
+    fn script_inside_a_weld_is_hidden() {
+        let report = scan_fixture("hidden_script.rbxmx");
+        assert_eq!(report.verdict, Verdict::Suspicious);
+        assert_eq!(
+            rules(&report),
+            [("hidden-script", "Car.Seat.SeatWeld.Weld", None)]
+        );
+        assert_eq!(report.findings[0].message, "Script hidden inside a Weld");
+    }
+
+    #[test]
+    fn binary_models_are_scanned() {
+        let report = scan_fixture("clean.rbxm");
+        assert_eq!(report.verdict, Verdict::Clean);
+        assert_eq!(report.scripts_scanned, 1);
+
+        let report = scan_fixture("require_id.rbxm");
+        assert_eq!(report.verdict, Verdict::Malicious);
+        assert_eq!(
+            rules(&report),
+            [("require-id", "Sword.Handle.Damage", Some(2))]
+        );
+        assert_eq!(report.findings[0].excerpt, "require(4837261940).load(game)");
+    }
+}
@@ -0,0 +1,137 @@
+use crate::config::{ApiKeys, Config, WebSearchConfig};
+use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
+
+/// Keyring service name secrets are stored under.
+const KEYRING_SERVICE: &str = "rbx-studio-mcp";
+/// Whether the `keyring` features enabled in Cargo.toml give this platform a real store.
+/// Elsewhere keyring falls back to an in-memory mock that forgets everything on exit.
+const KEYRING_SUPPORTED: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Secret {
+    TavilyApiKey,
+}
+
+impl Secret {
+    pub const ALL: [Secret; 1] = [Secret::TavilyApiKey];
+
+    /// Name used for the config file key and the keyring entry.
+    pub fn name(self) -> &'static str {
+        match self {
+            Secret::TavilyApiKey => "tavily",
+        }
+    }
+
+    fn env_var(self) -> &'static str {
+        match self {
+            Secret::TavilyApiKey => "TAVILY_API_KEY",
+        }
+    }
+
+    fn in_config(self, api_keys: &ApiKeys) -> Option<String> {
+        match self {
+            Secret::TavilyApiKey => api_keys.tavily.clone(),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|secret| secret.name() == name)
+    }
+
+    /// Secret a tool cannot work without under `config`, if any.
+    pub fn required_by(tool: &str, config: &Config) -> Option<Self> {
+        match tool {
+            "search_web_scripts" => match config.web_search {
+                WebSearchConfig::Tavily => Some(Secret::TavilyApiKey),
+                WebSearchConfig::Searxng { .. } | WebSearchConfig::Fixture { .. } => None,
+            },
+            "tavily_extract" => Some(Secret::TavilyApiKey),
+            _ => None,
+        }
+    }
+}
+
+/// Secrets resolved once at startup, from the environment, then the config file, then the OS
+/// keyring.
+#[derive(Debug, Default)]
+pub struct Secrets {
+    values: HashMap<Secret, String>,
+}
+
+impl Secrets {
+    pub fn resolve(api_keys: &ApiKeys) -> Self {
+        let mut values = HashMap::new();
+        for secret in Secret::ALL {
+            let value = std::env::var(secret.env_var())
+                .ok()
+                .or_else(|| secret.in_config(api_keys))
+                .or_else(|| read_keyring(secret))
+                .filter(|value| !value.trim().is_empty());
+            if let Some(value) = value {
+                values.insert(secret, value);
+            }
+        }
+        Self { values }
+    }
+
+    pub fn get(&self, secret: Secret) -> Option<&str> {
+        self.values.get(&secret).map(String::as_str)
+    }
+
+    pub fn is_configured(&self, secret: Secret) -> bool {
+        self.values.contains_key(&secret)
+    }
+
+    /// The secret, or a message explaining how to configure it.
+    pub fn require(&self, secret: Secret) -> Result<&str, String> {
+        self.get(secret).ok_or_else(|| not_configured(secret))
+    }
+}
+
+pub fn not_configured(secret: Secret) -> String {
+    let keyring = if KEYRING_SUPPORTED {
+        format!(
+            ", or store it in the OS keyring with `--store-secret {}`",
+            secret.name()
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "The {name} API key is not configured. Set the {env} environment variable or add `{name} = \"...\"` under [api_keys] in the config file{keyring}.",
+        name = secret.name(),
+        env = secret.env_var(),
+    )
+}
+
+fn read_keyring(secret: Secret) -> Option<String> {
+    if !KEYRING_SUPPORTED {
+        return None;
+    }
+    let entry = keyring::Entry::new(KEYRING_SERVICE, secret.name()).ok()?;
+    match entry.get_password() {
+        Ok(value) => Some(value),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            tracing::debug!("Could not read {} from the keyring: {e}", secret.name());
+            None
+        }
+    }
+}
+
+/// Stores a secret in the OS keyring, for `--store-secret`.
+pub fn store_in_keyring(name: &str, value: &str) -> Result<()> {
+    let secret = Secret::from_name(name).ok_or_else(|| {
+        let known: Vec<_> = Secret::ALL.iter().map(|secret| secret.name()).collect();
+        eyre!("Unknown secret {name}, expected one of {known:?}")
+    })?;
+    if !KEYRING_SUPPORTED {
+        return Err(eyre!(
+            "There is no OS keyring to store {name} in on this platform. Set the {} environment variable or add `{name} = \"...\"` under [api_keys] in the config file instead.",
+            secret.env_var()
+        ));
+    }
+    keyring::Entry::new(KEYRING_SERVICE, secret.name())?.set_password(value.trim())?;
+    Ok(())
+}
@@ -0,0 +1,617 @@
+//! Selectors for finding instances, compiled into a single query the plugin runs in
+//! `Tools/Query.luau`.
+//!
+//! ```text
+//! selector   = complex { "," complex }
+//! complex    = compound { [ ">" ] compound }       (space: any descendant, ">": a child)
+//! compound   = [ class | "*" ] { "#" name | "[" test "]" | "." method }
+//! test       = [ "@" ] property [ op value ]        ("@" reads an attribute)
+//! op         = "=" | "!=" | "<" | "<=" | ">" | ">=" | "*=" | "^=" | "$="
+//! method     = "Tagged(" string ")"
+//! ```
+//!
+//! A class matches with `IsA`, so `BasePart` also finds `MeshPart`s. `#name` may use `*` and
+//! `?` wildcards. Properties can reach into components, as in `[Size.Y>4]`; enum items compare
+//! by name. A test without an operator checks that the value is truthy (or, for attributes,
+//! set).
+
+use crate::api_dump::{ApiIndex, ApiMember};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Alternatives separated by commas; an instance matching any of them is a match.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default, PartialEq)]
+pub struct Selector {
+    pub alternatives: Vec<Complex>,
+}
+
+/// Compounds from the outermost ancestor to the instance that is returned.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct Complex {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Combinator {
+    /// The previous step matches any ancestor
+    Descendant,
+    /// The previous step matches the parent
+    Child,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default, PartialEq)]
+pub struct Step {
+    /// How this step relates to the previous one; `None` for the first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combinator: Option<Combinator>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Lua pattern for the name, anchored at both ends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<Test>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<Test>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+pub struct Test {
+    /// Property or attribute name, followed by components to index into
+    pub path: Vec<String>,
+    /// `None` tests that the value is truthy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op: Option<Op>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Literal>,
+}
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, PartialEq)]
+pub enum Op {
+    #[serde(rename = "=")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    /// Contains
+    #[serde(rename = "*=")]
+    Contains,
+    #[serde(rename = "^=")]
+    StartsWith,
+    #[serde(rename = "$=")]
+    EndsWith,
+}
+
+/// A value to compare with. Bare words are strings, so `[Material=Neon]` needs no quotes.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Literal {
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// `nil`, sent as `null`
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorError {
+    selector: String,
+    /// Byte offset of the problem in `selector`
+    position: usize,
+    message: String,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid selector at character {}: {}\n  {}\n  {}^",
+            self.selector[..self.position].chars().count() + 1,
+            self.message,
+            self.selector,
+            " ".repeat(self.selector[..self.position].chars().count())
+        )
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        Parser {
+            source: selector,
+            position: 0,
+        }
+        .selector()
+    }
+}
+
+impl Selector {
+    /// Checks class and property names against the API index, suggesting the closest name for
+    /// typos. Properties are only checked on compounds that name a class.
+    pub fn validate(&self, api: &ApiIndex) -> Result<(), String> {
+        for step in self.alternatives.iter().flat_map(|complex| &complex.steps) {
+            let Some(class) = &step.class else {
+                continue;
+            };
+            if api.class(class).is_none() {
+                return Err(api.unknown_class(class));
+            }
+            for test in &step.properties {
+                let property = &test.path[0];
+                let Some(member) = api.member(class, property) else {
+                    return Err(api.unknown_member(class, property));
+                };
+                if !matches!(member.member, ApiMember::Property(_)) {
+                    return Err(format!(
+                        "`{class}.{property}` is a {}, not a property",
+                        member.member.kind()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn selector(&mut self) -> Result<Selector, SelectorError> {
+        let mut selector = Selector::default();
+        loop {
+            self.skip_whitespace();
+            selector.alternatives.push(self.complex()?);
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Ok(selector),
+                Some(',') => self.position += 1,
+                Some(_) => return Err(self.error("expected `,` or the end of the selector")),
+            }
+        }
+    }
+
+    fn complex(&mut self) -> Result<Complex, SelectorError> {
+        let mut steps = vec![self.compound(None)?];
+        loop {
+            let had_space = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.position += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                None | Some(',') => break,
+                Some(_) if had_space => Combinator::Descendant,
+                Some(_) => return Err(self.error("unexpected character")),
+            };
+            steps.push(self.compound(Some(combinator))?);
+        }
+        Ok(Complex { steps })
+    }
+
+    fn compound(&mut self, combinator: Option<Combinator>) -> Result<Step, SelectorError> {
+        let start = self.position;
+        let mut step = Step {
+            combinator,
+            ..Step::default()
+        };
+        if self.peek() == Some('*') {
+            self.position += 1;
+        } else if self.peek().is_some_and(is_identifier_start) {
+            step.class = Some(self.identifier()?);
+        }
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.position += 1;
+                    if step.name.is_some() {
+                        return Err(self.error("a compound can only have one #name"));
+                    }
+                    step.name = Some(self.name_pattern()?);
+                }
+                Some('[') => {
+                    self.position += 1;
+                    self.skip_whitespace();
+                    let attribute = self.peek() == Some('@');
+                    if attribute {
+                        self.position += 1;
+                    }
+                    let test = self.test()?;
+                    self.skip_whitespace();
+                    if self.peek() != Some(']') {
+                        return Err(self.error("expected `]`"));
+                    }
+                    self.position += 1;
+                    if attribute {
+                        step.attributes.push(test);
+                    } else {
+                        step.properties.push(test);
+                    }
+                }
+                Some('.') => {
+                    self.position += 1;
+                    let method_start = self.position;
+                    let method = self.identifier()?;
+                    if method != "Tagged" {
+                        self.position = method_start;
+                        return Err(self.error(&format!(
+                            "unknown method `{method}`; only .Tagged(\"Tag\") is supported"
+                        )));
+                    }
+                    self.expect('(')?;
+                    self.skip_whitespace();
+                    let tag = match self.peek() {
+                        Some(quote @ ('"' | '\'')) => {
+                            self.position += 1;
+                            self.quoted(quote)?
+                        }
+                        _ => self.bare_word(),
+                    };
+                    if tag.is_empty() {
+                        return Err(self.error("expected a tag name"));
+                    }
+                    self.skip_whitespace();
+                    self.expect(')')?;
+                    step.tags.push(tag);
+                }
+                _ => break,
+            }
+        }
+        if self.position == start {
+            return Err(
+                self.error("expected a class name, `*`, `#name`, `[test]` or `.Tagged(...)`")
+            );
+        }
+        Ok(step)
+    }
+
+    fn test(&mut self) -> Result<Test, SelectorError> {
+        let mut path = vec![self.identifier()?];
+        while self.peek() == Some('.') {
+            self.position += 1;
+            path.push(self.identifier()?);
+        }
+        self.skip_whitespace();
+        let rest = &self.source[self.position..];
+        let op = [
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("*=", Op::Contains),
+            ("^=", Op::StartsWith),
+            ("$=", Op::EndsWith),
+            ("=", Op::Eq),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .into_iter()
+        .find(|(token, _)| rest.starts_with(token));
+        let Some((token, op)) = op else {
+            return Ok(Test {
+                path,
+                op: None,
+                value: None,
+            });
+        };
+        self.position += token.len();
+        self.skip_whitespace();
+        let value_start = self.position;
+        let value = self.literal()?;
+        let numeric = matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge);
+        if numeric && !matches!(value, Literal::Number(_)) {
+            self.position = value_start;
+            return Err(self.error("`<`, `<=`, `>` and `>=` compare numbers"));
+        }
+        let textual = matches!(op, Op::Contains | Op::StartsWith | Op::EndsWith);
+        if textual && !matches!(value, Literal::String(_)) {
+            self.position = value_start;
+            return Err(self.error("`*=`, `^=` and `$=` compare text"));
+        }
+        Ok(Test {
+            path,
+            op: Some(op),
+            value: Some(value),
+        })
+    }
+
+    fn literal(&mut self) -> Result<Literal, SelectorError> {
+        if let Some(quote @ ('"' | '\'')) = self.peek() {
+            self.position += 1;
+            return self.quoted(quote).map(Literal::String);
+        }
+        let word = self.bare_word();
+        Ok(match word.as_str() {
+            "" => return Err(self.error("expected a value")),
+            "true" => Literal::Bool(true),
+            "false" => Literal::Bool(false),
+            "nil" => Literal::Nil,
+            word => match word.parse::<f64>() {
+                Ok(number) if number.is_finite() => Literal::Number(number),
+                _ => Literal::String(word.to_string()),
+            },
+        })
+    }
+
+    /// A `#name`, as a Lua pattern anchored at both ends.
+    fn name_pattern(&mut self) -> Result<String, SelectorError> {
+        let mut pattern = String::from("^");
+        let start = self.position;
+        loop {
+            match self.peek() {
+                Some(quote @ ('"' | '\'')) => {
+                    self.position += 1;
+                    pattern.push_str(&lua_escape(&self.quoted(quote)?));
+                }
+                Some('*') => {
+                    self.position += 1;
+                    pattern.push_str(".*");
+                }
+                Some('?') => {
+                    self.position += 1;
+                    pattern.push('.');
+                }
+                Some(c) if is_identifier_char(c) || c == '-' => {
+                    self.position += c.len_utf8();
+                    pattern.push_str(&lua_escape(&c.to_string()));
+                }
+                _ => break,
+            }
+        }
+        if self.position == start {
+            return Err(self.error("expected a name after `#`"));
+        }
+        pattern.push('$');
+        Ok(pattern)
+    }
+
+    fn identifier(&mut self) -> Result<String, SelectorError> {
+        let start = self.position;
+        if !self.peek().is_some_and(is_identifier_start) {
+            return Err(self.error("expected a name"));
+        }
+        while self.peek().is_some_and(is_identifier_char) {
+            self.position += 1;
+        }
+        Ok(self.source[start..self.position].to_string())
+    }
+
+    /// Letters, digits and `_ . - +` up to the next delimiter.
+    fn bare_word(&mut self) -> String {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| is_identifier_char(c) || matches!(c, '.' | '-' | '+'))
+        {
+            self.position += 1;
+        }
+        self.source[start..self.position].to_string()
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String, SelectorError> {
+        let start = self.position - 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    self.position = start;
+                    return Err(self.error("unterminated quote"));
+                }
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(text);
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    if let Some(escaped) = self.peek() {
+                        text.push(escaped);
+                        self.position += escaped.len_utf8();
+                    }
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.position += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SelectorError> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected `{expected}`")));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Skips whitespace, returning whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += self.peek().map_or(1, char::len_utf8);
+        }
+        self.position > start
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn error(&self, message: &str) -> SelectorError {
+        SelectorError {
+            selector: self.source.to_string(),
+            position: self.position,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Escapes the magic characters of Lua patterns.
+fn lua_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "^$()%.[]*+-?".contains(c) {
+            escaped.push('%');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(selector: &str) -> Selector {
+        selector.parse().unwrap()
+    }
+
+    fn error(selector: &str) -> String {
+        selector.parse::<Selector>().unwrap_err().message
+    }
+
+    #[test]
+    fn combinators() {
+        let selector = parse("Model  Folder > BasePart");
+        let steps = &selector.alternatives[0].steps;
+        let combinators: Vec<_> = steps.iter().map(|step| step.combinator).collect();
+        assert_eq!(
+            combinators,
+            [None, Some(Combinator::Descendant), Some(Combinator::Child)]
+        );
+        assert_eq!(parse("Model>Part"), parse("Model > Part"));
+    }
+
+    #[test]
+    fn commas_bind_looser_than_combinators() {
+        let selector = parse("Model > Part, Folder Script");
+        assert_eq!(selector.alternatives.len(), 2);
+        assert_eq!(selector.alternatives[0].steps.len(), 2);
+        assert_eq!(selector.alternatives[1].steps.len(), 2);
+        assert_eq!(
+            selector.alternatives[1].steps[0].class.as_deref(),
+            Some("Folder")
+        );
+    }
+
+    #[test]
+    fn compiles_to_the_query_the_plugin_runs() {
+        let selector = parse(
+            r#"Model#"Door.1" > BasePart#Handle*[Size.Y>=4][Material=Neon][@Locked][@Owner!='Bob'].Tagged("Grab")"#,
+        );
+        assert_eq!(
+            serde_json::to_value(&selector).unwrap(),
+            json!({
+                "alternatives": [{
+                    "steps": [
+                        { "class": "Model", "name": "^Door%.1$" },
+                        {
+                            "combinator": "child",
+                            "class": "BasePart",
+                            "name": "^Handle.*$",
+                            "tags": ["Grab"],
+                            "properties": [
+                                { "path": ["Size", "Y"], "op": ">=", "value": 4.0 },
+                                { "path": ["Material"], "op": "=", "value": "Neon" },
+                            ],
+                            "attributes": [
+                                { "path": ["Locked"] },
+                                { "path": ["Owner"], "op": "!=", "value": "Bob" },
+                            ],
+                        },
+                    ],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn literals() {
+        let values: Vec<_> =
+            parse("*[A=true][B=nil][C=-1.5][D='1'][E=Enum.Material.Neon]").alternatives[0].steps[0]
+                .properties
+                .iter()
+                .map(|test| test.value.clone().unwrap())
+                .collect();
+        assert_eq!(
+            values,
+            [
+                Literal::Bool(true),
+                Literal::Nil,
+                Literal::Number(-1.5),
+                Literal::String("1".to_string()),
+                Literal::String("Enum.Material.Neon".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_selectors() {
+        assert_eq!(
+            "Part[Size".parse::<Selector>().unwrap_err().to_string(),
+            "Invalid selector at character 10: expected `]`\n  Part[Size\n           ^"
+        );
+        assert_eq!(
+            error("Part,"),
+            "expected a class name, `*`, `#name`, `[test]` or `.Tagged(...)`"
+        );
+        assert_eq!(error("Part#"), "expected a name after `#`");
+        assert_eq!(error("Part#a#b"), "a compound can only have one #name");
+        assert_eq!(
+            error("Part[Size>big]"),
+            "`<`, `<=`, `>` and `>=` compare numbers"
+        );
+        assert_eq!(error("Part[Name*=1]"), "`*=`, `^=` and `$=` compare text");
+        assert_eq!(error("Part[Name=]"), "expected a value");
+        assert_eq!(error("Part[Name='a]"), "unterminated quote");
+        assert_eq!(
+            error("Part.HasTag(\"x\")"),
+            "unknown method `HasTag`; only .Tagged(\"Tag\") is supported"
+        );
+        assert_eq!(error("Part.Tagged()"), "expected a tag name");
+        assert_eq!(
+            error("Part ~ Model"),
+            "expected a class name, `*`, `#name`, `[test]` or `.Tagged(...)`"
+        );
+    }
+
+    #[test]
+    fn validate_suggests_names() {
+        let api = ApiIndex::bundled();
+        assert_eq!(parse("Model > BasePart[Anchored]").validate(&api), Ok(()));
+        assert!(parse("Modle")
+            .validate(&api)
+            .unwrap_err()
+            .contains("`Model`"));
+        assert!(parse("Part[Anchred]")
+            .validate(&api)
+            .unwrap_err()
+            .contains("`Anchored`"));
+    }
+}
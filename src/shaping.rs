@@ -0,0 +1,472 @@
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Rough characters-per-token ratio used to turn `max_tokens` into a character budget.
+const CHARS_PER_TOKEN: usize = 4;
+
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The plugin's JSON, pruned to the budget
+    #[default]
+    Json,
+    /// One `path [ClassName]` line per instance
+    Compact,
+    /// Indented `Name [ClassName]` outline
+    Tree,
+}
+
+/// Output shaping options shared by the hierarchy and search tools. These are applied
+/// server-side and never sent to the plugin.
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema, Clone, Default)]
+pub struct OutputShape {
+    #[schemars(
+        description = "Output format: \"json\" (default), \"compact\" (one path per line) or \"tree\" (indented outline)"
+    )]
+    #[serde(default, skip_serializing)]
+    format: Option<OutputFormat>,
+    #[schemars(
+        description = "Approximate token budget for the output. Instances over budget are summarized per class, e.g. \"... 312 more Parts\""
+    )]
+    #[serde(default, skip_serializing)]
+    max_tokens: Option<u32>,
+}
+
+impl OutputShape {
+    pub fn is_default(&self) -> bool {
+        self.format.unwrap_or_default() == OutputFormat::Json && self.max_tokens.is_none()
+    }
+
+    /// Reshapes plugin output. Anything that isn't a recognized hierarchy or result list
+    /// (errors, plain text) is passed through untouched.
+    pub fn apply(&self, output: String) -> String {
+        if self.is_default() {
+            return output;
+        }
+        let Ok(Value::Object(mut envelope)) = serde_json::from_str::<Value>(&output) else {
+            return output;
+        };
+        let format = self.format.unwrap_or_default();
+        let mut shaper = Shaper {
+            format,
+            budget: self.max_tokens.map(|t| t as usize * CHARS_PER_TOKEN),
+            used: 0,
+            elided: BTreeMap::new(),
+        };
+
+        let body = if let Some(tree) = envelope.remove("tree") {
+            Body::Tree(tree)
+        } else if envelope.contains_key("className") && envelope.contains_key("children") {
+            let children = envelope.remove("children").unwrap_or_default();
+            let mut root = Map::new();
+            for key in ["name", "className", "path", "hasSource", "scriptType"] {
+                if let Some(value) = envelope.remove(key) {
+                    root.insert(key.to_string(), value);
+                }
+            }
+            root.insert("children".to_string(), children);
+            Body::Root(Value::Object(root))
+        } else if let Some((key, Value::Array(items))) = ["results", "children", "services"]
+            .into_iter()
+            .find_map(|key| envelope.remove(key).map(|items| (key, items)))
+        {
+            Body::List(key, items)
+        } else {
+            return output;
+        };
+
+        match format {
+            OutputFormat::Json => {
+                match body {
+                    Body::Tree(tree) => {
+                        let tree = shaper.prune(&tree);
+                        envelope.insert("tree".to_string(), tree);
+                    }
+                    Body::Root(root) => {
+                        if let Value::Object(root) = shaper.prune(&root) {
+                            envelope.extend(root);
+                        }
+                    }
+                    Body::List(key, items) => {
+                        let items = shaper.prune_list(&items);
+                        envelope.insert(key.to_string(), Value::Array(items));
+                    }
+                }
+                if let Some(elided) = shaper.elided_summary() {
+                    envelope.insert("elided".to_string(), elided);
+                }
+                Value::Object(envelope).to_string()
+            }
+            OutputFormat::Compact | OutputFormat::Tree => {
+                let mut out = String::new();
+                match body {
+                    Body::Tree(tree) | Body::Root(tree) => shaper.render_tree(&tree, 0, &mut out),
+                    Body::List(_, items) => shaper.render_list(&items, &mut out),
+                };
+                if let Some(footer) = shaper.elided_footer() {
+                    out.push_str(&footer);
+                }
+                out
+            }
+        }
+    }
+}
+
+enum Body {
+    /// `get_file_tree` nests its tree under a `tree` key
+    Tree(Value),
+    /// `get_project_structure` returns the root node itself, with extra envelope fields
+    Root(Value),
+    List(&'static str, Vec<Value>),
+}
+
+struct Shaper {
+    format: OutputFormat,
+    budget: Option<usize>,
+    used: usize,
+    /// Instances left out of the output, per class, including whole elided subtrees.
+    elided: BTreeMap<String, usize>,
+}
+
+impl Shaper {
+    fn take(&mut self, len: usize) -> bool {
+        match self.budget {
+            Some(budget) if self.used + len > budget => false,
+            _ => {
+                self.used += len;
+                true
+            }
+        }
+    }
+
+    fn elide(&mut self, nodes: &[Value]) -> BTreeMap<String, usize> {
+        let mut siblings = BTreeMap::new();
+        for node in nodes {
+            *siblings.entry(class_name(node).to_string()).or_insert(0) += 1;
+            self.count_subtree(node);
+        }
+        siblings
+    }
+
+    fn count_subtree(&mut self, node: &Value) {
+        *self.elided.entry(class_name(node).to_string()).or_insert(0) += 1;
+        for child in children(node) {
+            self.count_subtree(child);
+        }
+    }
+
+    /// Prunes the children of `node`; the node's own entry is charged by its parent.
+    fn prune(&mut self, node: &Value) -> Value {
+        let mut pruned = match node {
+            Value::Object(map) => map.clone(),
+            other => return other.clone(),
+        };
+        pruned.remove("children");
+        let kids = children(node);
+        if !kids.is_empty() {
+            let mut kept = Vec::new();
+            for (index, child) in kids.iter().enumerate() {
+                let mut own = child.as_object().cloned().unwrap_or_default();
+                own.remove("children");
+                if !self.take(Value::Object(own).to_string().len()) {
+                    let siblings = self.elide(&kids[index..]);
+                    kept.push(elided_marker(&siblings));
+                    break;
+                }
+                kept.push(self.prune(child));
+            }
+            pruned.insert("children".to_string(), Value::Array(kept));
+        }
+        Value::Object(pruned)
+    }
+
+    fn prune_list(&mut self, items: &[Value]) -> Vec<Value> {
+        let mut kept = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            if !self.take(item.to_string().len()) {
+                let siblings = self.elide(&items[index..]);
+                kept.push(elided_marker(&siblings));
+                break;
+            }
+            kept.push(item.clone());
+        }
+        kept
+    }
+
+    fn render_tree(&mut self, node: &Value, depth: usize, out: &mut String) {
+        let line = match self.format {
+            OutputFormat::Compact => compact_line(node),
+            _ => format!("{}{}\n", "  ".repeat(depth), tree_label(node)),
+        };
+        out.push_str(&line);
+        let kids = children(node);
+        for (index, child) in kids.iter().enumerate() {
+            let child_line = match self.format {
+                OutputFormat::Compact => compact_line(child),
+                _ => format!("{}{}\n", "  ".repeat(depth + 1), tree_label(child)),
+            };
+            if !self.take(child_line.len()) {
+                let siblings = self.elide(&kids[index..]);
+                let indent = match self.format {
+                    OutputFormat::Compact => String::new(),
+                    _ => "  ".repeat(depth + 1),
+                };
+                out.push_str(&format!("{indent}{}\n", describe_elided(&siblings)));
+                break;
+            }
+            // The child's own line is already charged, render_tree only charges its children.
+            self.render_tree(child, depth + 1, out);
+        }
+    }
+
+    fn render_list(&mut self, items: &[Value], out: &mut String) {
+        let mut parent: Option<&str> = None;
+        for (index, item) in items.iter().enumerate() {
+            let path = str_field(item, "path").unwrap_or_default();
+            let item_parent = path.rsplit_once('.').map_or("", |(parent, _)| parent);
+            let line = match self.format {
+                OutputFormat::Compact => compact_line(item),
+                _ => {
+                    let mut line = String::new();
+                    if parent != Some(item_parent) {
+                        line.push_str(item_parent);
+                        line.push('\n');
+                    }
+                    line.push_str(&format!("  {}\n", tree_label(item)));
+                    line
+                }
+            };
+            if !self.take(line.len()) {
+                let siblings = self.elide(&items[index..]);
+                out.push_str(&describe_elided(&siblings));
+                out.push('\n');
+                break;
+            }
+            parent = Some(item_parent);
+            out.push_str(&line);
+        }
+    }
+
+    fn elided_summary(&self) -> Option<Value> {
+        if self.elided.is_empty() {
+            return None;
+        }
+        let total: usize = self.elided.values().sum();
+        Some(serde_json::json!({
+            "total": total,
+            "byClass": self.elided,
+        }))
+    }
+
+    fn elided_footer(&self) -> Option<String> {
+        if self.elided.is_empty() {
+            return None;
+        }
+        let total: usize = self.elided.values().sum();
+        Some(format!(
+            "[elided {total} instances over the token budget: {}]\n",
+            self.elided
+                .iter()
+                .map(|(class, count)| format!("{count} {class}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("children")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn str_field<'a>(node: &'a Value, key: &str) -> Option<&'a str> {
+    node.get(key).and_then(Value::as_str)
+}
+
+fn class_name(node: &Value) -> &str {
+    str_field(node, "className").unwrap_or("Instance")
+}
+
+fn tree_label(node: &Value) -> String {
+    let name = str_field(node, "name").unwrap_or("?");
+    let mut label = format!("{name} [{}]", class_name(node));
+    if let Some(count) = node.get("childCount").and_then(Value::as_u64) {
+        if node
+            .get("hasMore")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            label.push_str(&format!(" +{count} children"));
+        }
+    }
+    label
+}
+
+fn compact_line(node: &Value) -> String {
+    let path = str_field(node, "path")
+        .or_else(|| str_field(node, "name"))
+        .unwrap_or("?");
+    match node.get("propertyValue").and_then(Value::as_str) {
+        Some(value) => format!("{path} [{}] = {value}\n", class_name(node)),
+        None => format!("{path} [{}]\n", class_name(node)),
+    }
+}
+
+fn pluralize(class: &str, count: usize) -> String {
+    match count {
+        1 => class.to_string(),
+        _ if class.ends_with('s') => format!("{class}es"),
+        _ => format!("{class}s"),
+    }
+}
+
+/// Deterministic one-line summary of elided siblings, e.g. `... 312 more Parts, 4 more Scripts`.
+fn describe_elided(siblings: &BTreeMap<String, usize>) -> String {
+    let mut counts: Vec<_> = siblings.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let parts: Vec<_> = counts
+        .into_iter()
+        .map(|(class, count)| format!("{count} more {}", pluralize(class, *count)))
+        .collect();
+    format!("... {}", parts.join(", "))
+}
+
+fn elided_marker(siblings: &BTreeMap<String, usize>) -> Value {
+    serde_json::json!({
+        "name": describe_elided(siblings),
+        "className": "Elided",
+        "elided": siblings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn shape(format: OutputFormat, max_tokens: Option<u32>) -> OutputShape {
+        OutputShape {
+            format: Some(format),
+            max_tokens,
+        }
+    }
+
+    fn node(name: &str, class: &str) -> Value {
+        json!({ "name": name, "className": class, "path": format!("Workspace.{name}") })
+    }
+
+    /// A `get_file_tree` response: four Parts and a Script under Workspace.
+    fn file_tree() -> String {
+        let children: Vec<_> = ["P1", "P2", "P3", "P4"]
+            .into_iter()
+            .map(|name| node(name, "Part"))
+            .chain([node("S1", "Script")])
+            .collect();
+        json!({
+            "tree": { "name": "Workspace", "className": "Workspace", "children": children },
+        })
+        .to_string()
+    }
+
+    /// A `search_files` response with the same instances as a result list.
+    fn search_results() -> String {
+        let results: Vec<_> = ["P1", "P2", "P3", "P4"]
+            .into_iter()
+            .map(|name| node(name, "Part"))
+            .chain([node("S1", "Script")])
+            .collect();
+        json!({ "query": "P", "results": results }).to_string()
+    }
+
+    #[test]
+    fn tree_format_elides_over_budget() {
+        // 24 characters: two `  P1 [Part]` lines
+        let output = shape(OutputFormat::Tree, Some(6)).apply(file_tree());
+        assert_eq!(
+            output,
+            "Workspace [Workspace]\n  P1 [Part]\n  P2 [Part]\n  ... 2 more Parts, 1 more Script\n\
+             [elided 3 instances over the token budget: 2 Part, 1 Script]\n"
+        );
+    }
+
+    #[test]
+    fn compact_format_elides_over_budget() {
+        // 40 characters: two `Workspace.P1 [Part]` lines
+        let output = shape(OutputFormat::Compact, Some(10)).apply(search_results());
+        assert_eq!(
+            output,
+            "Workspace.P1 [Part]\nWorkspace.P2 [Part]\n... 2 more Parts, 1 more Script\n\
+             [elided 3 instances over the token budget: 2 Part, 1 Script]\n"
+        );
+    }
+
+    #[test]
+    fn json_format_elides_over_budget() {
+        let item_len = node("P1", "Part").to_string().len();
+        // Room for two results
+        let max_tokens = (2 * item_len).div_ceil(CHARS_PER_TOKEN) as u32;
+        let output = shape(OutputFormat::Json, Some(max_tokens)).apply(search_results());
+        let output: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(output["query"], "P");
+        let results = output["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1]["name"], "P2");
+        assert_eq!(
+            results[2],
+            json!({
+                "name": "... 2 more Parts, 1 more Script",
+                "className": "Elided",
+                "elided": { "Part": 2, "Script": 1 },
+            })
+        );
+        assert_eq!(
+            output["elided"],
+            json!({ "total": 3, "byClass": { "Part": 2, "Script": 1 } })
+        );
+    }
+
+    #[test]
+    fn elided_subtrees_are_counted() {
+        let tree = json!({
+            "tree": {
+                "name": "Workspace",
+                "className": "Workspace",
+                "children": [
+                    { "name": "Car", "className": "Model", "children": [
+                        node("Seat", "Seat"), node("Wheel", "Part"),
+                    ]},
+                ],
+            },
+        });
+        let output = shape(OutputFormat::Tree, Some(1)).apply(tree.to_string());
+        assert_eq!(
+            output,
+            "Workspace [Workspace]\n  ... 1 more Model\n\
+             [elided 3 instances over the token budget: 1 Model, 1 Part, 1 Seat]\n"
+        );
+    }
+
+    #[test]
+    fn output_under_budget_is_unchanged() {
+        let tree = file_tree();
+        assert_eq!(OutputShape::default().apply(tree.clone()), tree);
+        let shaped = shape(OutputFormat::Json, Some(10_000)).apply(tree.clone());
+        assert_eq!(
+            serde_json::from_str::<Value>(&shaped).unwrap(),
+            serde_json::from_str::<Value>(&tree).unwrap()
+        );
+        let outline = shape(OutputFormat::Tree, Some(10_000)).apply(tree);
+        assert!(!outline.contains("elided"), "{outline}");
+        assert_eq!(outline.lines().count(), 6);
+        // Errors and plain text pass through
+        let error = "Instance not found".to_string();
+        assert_eq!(
+            shape(OutputFormat::Tree, Some(1)).apply(error.clone()),
+            error
+        );
+    }
+}
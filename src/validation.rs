@@ -0,0 +1,301 @@
+//! Checks of property writes against the API index, so mistakes come back as a clear error
+//! instead of an opaque Luau one from inside Studio.
+
+use crate::api_dump::{self, ApiIndex, ApiMember, ApiProperty, Security};
+use crate::datatypes::{PropertyValue, RobloxValue};
+use serde_json::Value;
+
+/// Write security levels a Studio plugin has.
+const PLUGIN_WRITABLE: [&str; 2] = ["None", "PluginSecurity"];
+
+/// Checks that `class` can be made with `Instance.new`.
+pub fn check_creatable(api: &ApiIndex, class: &str) -> Result<(), String> {
+    let Some(info) = api.class(class) else {
+        return Err(api.unknown_class(class));
+    };
+    for tag in ["NotCreatable", "Service"] {
+        if api_dump::has_tag(&info.tags, tag) {
+            return Err(format!("`{class}` is tagged {tag} and can't be created"));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a plugin may set `property` of `class` to `value`. Classes missing from the
+/// index are not checked.
+pub fn check_property_write(
+    api: &ApiIndex,
+    class: &str,
+    property: &str,
+    value: &PropertyValue,
+) -> Result<(), String> {
+    if api.class(class).is_none() {
+        return Ok(());
+    }
+    let Some(member) = api.member(class, property) else {
+        return Err(unknown_property(api, class, property));
+    };
+    let ApiMember::Property(info) = member.member else {
+        return Err(format!(
+            "`{class}.{property}` is a {}, not a property",
+            member.member.kind()
+        ));
+    };
+    for tag in ["ReadOnly", "NotScriptable"] {
+        if api_dump::has_tag(&info.tags, tag) {
+            return Err(format!(
+                "`{class}.{property}` is tagged {tag} and can't be set"
+            ));
+        }
+    }
+    let write = match &info.security {
+        Security::Level(level) => level,
+        Security::ReadWrite { write, .. } => write,
+    };
+    if !PLUGIN_WRITABLE.contains(&write.as_str()) {
+        return Err(format!(
+            "`{class}.{property}` needs {write} to write, which plugins don't have"
+        ));
+    }
+    check_value(api, class, info, value)
+}
+
+fn unknown_property(api: &ApiIndex, class: &str, property: &str) -> String {
+    let members = api.members(class);
+    let properties = members
+        .iter()
+        .filter(|member| matches!(member.member, ApiMember::Property(_)))
+        .map(|member| member.member.name());
+    let message = format!("`{class}` has no property `{property}`");
+    match api_dump::closest(property, properties) {
+        Some(suggestion) => format!("{message}. Did you mean `{suggestion}`?"),
+        None => message,
+    }
+}
+
+fn check_value(
+    api: &ApiIndex,
+    class: &str,
+    info: &ApiProperty,
+    value: &PropertyValue,
+) -> Result<(), String> {
+    match value {
+        PropertyValue::Typed(value) => check_typed_value(api, class, info, value),
+        value => check_untyped_value(api, class, info, &value.to_json()),
+    }
+}
+
+fn check_typed_value(
+    api: &ApiIndex,
+    class: &str,
+    info: &ApiProperty,
+    value: &RobloxValue,
+) -> Result<(), String> {
+    let ty = &info.value_type;
+    let ok = match (ty.category.as_str(), value) {
+        ("Enum", RobloxValue::EnumItem { enum_type, name }) if *enum_type == ty.name => {
+            return check_enum_value(api, class, info, &ty.name, &Value::String(name.clone()));
+        }
+        ("Class", RobloxValue::Instance { .. }) => true,
+        ("DataType", RobloxValue::Color3 { .. }) => {
+            matches!(ty.name.as_str(), "Color3" | "Color3uint8")
+        }
+        (_, value) => value.type_name() == ty.name,
+    };
+    if ok {
+        return Ok(());
+    }
+    let got = match value {
+        RobloxValue::EnumItem { enum_type, .. } => format!("Enum.{enum_type}"),
+        value => value.type_name().to_string(),
+    };
+    let expected = match ty.category.as_str() {
+        "Enum" => format!("Enum.{}", ty.name),
+        _ => ty.name.clone(),
+    };
+    Err(format!(
+        "`{class}.{}` expects {expected}, got {got}",
+        info.name
+    ))
+}
+
+/// Checks an untagged `value` against the shorthands `Util.convertPropertyValue` in the plugin
+/// understands.
+fn check_untyped_value(
+    api: &ApiIndex,
+    class: &str,
+    info: &ApiProperty,
+    value: &Value,
+) -> Result<(), String> {
+    let ty = &info.value_type;
+    let ok = match (ty.category.as_str(), ty.name.as_str()) {
+        ("Primitive", "bool") => {
+            value.is_boolean() || matches!(value.as_str(), Some("true" | "false"))
+        }
+        ("Primitive", "int" | "int64" | "float" | "double") => {
+            value.is_number()
+                || value
+                    .as_str()
+                    .is_some_and(|text| text.trim().parse::<f64>().is_ok())
+        }
+        ("Primitive", "string") | ("DataType", "Content" | "ContentId" | "ProtectedString") => {
+            value.is_string() || value.is_number()
+        }
+        ("Enum", name) => return check_enum_value(api, class, info, name, value),
+        ("Class", _) => value.is_string() || value.is_null(),
+        ("DataType", "Vector3") => is_vector(value, &["X", "Y", "Z"]),
+        ("DataType", "Color3" | "Color3uint8") => is_vector(value, &["R", "G", "B"]),
+        ("DataType", "Vector2") => numbers(value).is_some_and(|len| len == 2),
+        ("DataType", "BrickColor") => value.is_string() || value.is_number(),
+        // Datatypes without a shorthand need a tagged value
+        ("DataType", _) => false,
+        _ => true,
+    };
+    if ok {
+        return Ok(());
+    }
+    Err(format!(
+        "`{class}.{}` expects {}, got {value}",
+        info.name,
+        expected_form(&ty.name)
+    ))
+}
+
+fn check_enum_value(
+    api: &ApiIndex,
+    class: &str,
+    info: &ApiProperty,
+    enum_name: &str,
+    value: &Value,
+) -> Result<(), String> {
+    let Some(enum_info) = api.enum_(enum_name) else {
+        return Ok(());
+    };
+    let item = match value {
+        Value::String(item) => item
+            .strip_prefix(&format!("Enum.{enum_name}."))
+            .unwrap_or(item),
+        Value::Number(number) => {
+            return match number.as_f64() {
+                Some(n) if enum_info.items.iter().any(|item| item.value as f64 == n) => Ok(()),
+                _ => Err(format!("{number} is not a value of Enum.{enum_name}")),
+            }
+        }
+        _ => {
+            return Err(format!(
+                "`{class}.{}` is an Enum.{enum_name}; pass an item name such as \"{}\"",
+                info.name,
+                enum_info
+                    .items
+                    .first()
+                    .map(|item| item.name.as_str())
+                    .unwrap_or("")
+            ))
+        }
+    };
+    if enum_info
+        .items
+        .iter()
+        .any(|candidate| candidate.name == item)
+    {
+        return Ok(());
+    }
+    let message = format!("`{item}` is not an item of Enum.{enum_name}");
+    let names = enum_info.items.iter().map(|item| item.name.as_str());
+    Err(match api_dump::closest(item, names) {
+        Some(suggestion) => format!("{message}. Did you mean `{suggestion}`?"),
+        None => message,
+    })
+}
+
+/// An array with one number per key, or an object with some of `keys` and only numbers.
+fn is_vector(value: &Value, keys: &[&str]) -> bool {
+    match value {
+        Value::Object(object) => {
+            keys.iter().any(|key| object.contains_key(*key))
+                && object.values().all(Value::is_number)
+        }
+        _ => numbers(value).is_some_and(|len| len == keys.len()),
+    }
+}
+
+/// Length of `value` if it is an array of numbers.
+fn numbers(value: &Value) -> Option<usize> {
+    let array = value.as_array()?;
+    array.iter().all(Value::is_number).then_some(array.len())
+}
+
+fn expected_form(type_name: &str) -> String {
+    match type_name {
+        "bool" => "true or false".to_string(),
+        "int" | "int64" | "float" | "double" => "a number".to_string(),
+        "string" | "ProtectedString" => "a string".to_string(),
+        "Content" | "ContentId" => "an asset URL such as \"rbxassetid://123\"".to_string(),
+        "Vector3" => "[x, y, z] or {\"$type\": \"Vector3\", \"x\": x, \"y\": y, \"z\": z}".to_string(),
+        "Color3" | "Color3uint8" => {
+            "[r, g, b] or {\"$type\": \"Color3\", \"r\": r, \"g\": g, \"b\": b} with components from 0 to 1"
+                .to_string()
+        }
+        "Vector2" => "[x, y] or {\"$type\": \"Vector2\", \"x\": x, \"y\": y}".to_string(),
+        "BrickColor" => "a BrickColor name".to_string(),
+        name => format!("a {{\"$type\": \"{name}\", ...}} value"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(class: &str, property: &str, value: Value) -> Result<(), String> {
+        let api = ApiIndex::bundled();
+        let value: PropertyValue = serde_json::from_value(value).unwrap();
+        check_property_write(&api, class, property, &value)
+    }
+
+    #[test]
+    fn asset_ids_are_written_as_strings() {
+        for (class, property) in [
+            ("Decal", "Texture"),
+            ("Sound", "SoundId"),
+            ("MeshPart", "TextureID"),
+        ] {
+            assert_eq!(
+                write(class, property, "rbxassetid://123".into()),
+                Ok(()),
+                "{class}.{property}"
+            );
+        }
+    }
+
+    #[test]
+    fn misspelled_property_gets_a_suggestion() {
+        let error = write("Part", "Transparancy", 0.5.into()).unwrap_err();
+        assert_eq!(
+            error,
+            "`Part` has no property `Transparancy`. Did you mean `Transparency`?"
+        );
+    }
+
+    #[test]
+    fn read_only_property_is_refused() {
+        let error = write("Part", "ClassName", "Model".into()).unwrap_err();
+        assert_eq!(
+            error,
+            "`Part.ClassName` is tagged ReadOnly and can't be set"
+        );
+    }
+
+    #[test]
+    fn wrong_type_is_refused() {
+        let error = write("Part", "Anchored", "maybe".into()).unwrap_err();
+        assert_eq!(
+            error,
+            "`Part.Anchored` expects true or false, got \"maybe\""
+        );
+        let error = write("Part", "Size", serde_json::json!([1, 2])).unwrap_err();
+        assert!(
+            error.starts_with("`Part.Size` expects [x, y, z]"),
+            "{error}"
+        );
+    }
+}
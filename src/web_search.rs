@@ -0,0 +1,331 @@
+use crate::cache::normalize_query;
+use crate::config::WebSearchConfig;
+use crate::http::{HttpClient, HttpError};
+use crate::secrets::{Secret, Secrets};
+use rmcp::ErrorData;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone)]
+pub struct WebSearchRequest {
+    pub query: String,
+    /// Ask for slower, more thorough results where the provider supports it
+    pub advanced: bool,
+    pub max_results: usize,
+}
+
+/// Search results in the same shape whichever provider produced them.
+#[derive(Debug, Serialize)]
+pub struct WebSearchResponse {
+    pub provider: &'static str,
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    pub results: Vec<WebSearchResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebSearchResult {
+    pub title: String,
+    pub url: String,
+    /// Short excerpt of the page
+    #[serde(default)]
+    pub snippet: String,
+    /// Full page text, when the provider returns it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published: Option<String>,
+}
+
+/// A web search backend for `search_web_scripts`.
+pub trait WebSearchProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn search<'a>(
+        &'a self,
+        request: &'a WebSearchRequest,
+    ) -> BoxFuture<'a, Result<WebSearchResponse, ErrorData>>;
+}
+
+/// The provider selected by `[web_search]` in the config.
+pub fn provider(
+    config: &WebSearchConfig,
+    http: &HttpClient,
+    secrets: &Secrets,
+) -> Result<Box<dyn WebSearchProvider>, ErrorData> {
+    Ok(match config {
+        WebSearchConfig::Tavily => Box::new(Tavily {
+            http: http.clone(),
+            api_key: secrets
+                .require(Secret::TavilyApiKey)
+                .map_err(|e| ErrorData::invalid_request(e, None))?
+                .to_string(),
+        }),
+        WebSearchConfig::Searxng { url } => Box::new(Searxng {
+            http: http.clone(),
+            url: url.trim_end_matches('/').to_string(),
+        }),
+        WebSearchConfig::Fixture { path } => Box::new(Fixture { path: path.clone() }),
+    })
+}
+
+struct Tavily {
+    http: HttpClient,
+    api_key: String,
+}
+
+impl WebSearchProvider for Tavily {
+    fn name(&self) -> &'static str {
+        "tavily"
+    }
+
+    fn search<'a>(
+        &'a self,
+        request: &'a WebSearchRequest,
+    ) -> BoxFuture<'a, Result<WebSearchResponse, ErrorData>> {
+        Box::pin(async move {
+            let body = serde_json::json!({
+                "query": request.query,
+                "search_depth": if request.advanced { "advanced" } else { "basic" },
+                "include_answer": true,
+                "include_raw_content": true,
+                "max_results": request.max_results,
+            });
+            let json: Value = self
+                .http
+                .send(
+                    self.http
+                        .post("https://api.tavily.com/search")
+                        .bearer_auth(&self.api_key)
+                        .json(&body),
+                )
+                .await?
+                .json()
+                .await
+                .map_err(HttpError::from)?;
+
+            let results = array(&json, "results")
+                .map(|result| WebSearchResult {
+                    title: string(result, "title").unwrap_or_default(),
+                    url: string(result, "url").unwrap_or_default(),
+                    snippet: string(result, "content").unwrap_or_default(),
+                    content: string(result, "raw_content"),
+                    score: result.get("score").and_then(Value::as_f64),
+                    published: string(result, "published_date"),
+                })
+                .collect();
+            Ok(WebSearchResponse {
+                provider: self.name(),
+                query: request.query.clone(),
+                answer: string(&json, "answer"),
+                results,
+            })
+        })
+    }
+}
+
+/// A SearXNG instance, or anything else serving its `/search?format=json` API.
+struct Searxng {
+    http: HttpClient,
+    url: String,
+}
+
+impl WebSearchProvider for Searxng {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    fn search<'a>(
+        &'a self,
+        request: &'a WebSearchRequest,
+    ) -> BoxFuture<'a, Result<WebSearchResponse, ErrorData>> {
+        Box::pin(async move {
+            let url = format!(
+                "{}/search?q={}&format=json",
+                self.url,
+                urlencoding::encode(&request.query)
+            );
+            let json: Value = self
+                .http
+                .send(self.http.get(&url))
+                .await?
+                .json()
+                .await
+                .map_err(HttpError::from)?;
+
+            let results = array(&json, "results")
+                .take(request.max_results)
+                .map(|result| WebSearchResult {
+                    title: string(result, "title").unwrap_or_default(),
+                    url: string(result, "url").unwrap_or_default(),
+                    snippet: string(result, "content").unwrap_or_default(),
+                    content: None,
+                    score: result.get("score").and_then(Value::as_f64),
+                    published: string(result, "publishedDate"),
+                })
+                .collect();
+            let answer = array(&json, "answers").find_map(|answer| match answer {
+                Value::String(text) => Some(text.clone()),
+                answer => string(answer, "answer"),
+            });
+            Ok(WebSearchResponse {
+                provider: self.name(),
+                query: request.query.clone(),
+                answer,
+                results,
+            })
+        })
+    }
+}
+
+/// Canned results from a JSON file, for tests and offline demos. The file maps queries to
+/// `{ "answer": ..., "results": [...] }`; a `"*"` entry answers every other query.
+struct Fixture {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct FixtureEntry {
+    #[serde(default)]
+    answer: Option<String>,
+    #[serde(default)]
+    results: Vec<WebSearchResult>,
+}
+
+impl WebSearchProvider for Fixture {
+    fn name(&self) -> &'static str {
+        "fixture"
+    }
+
+    fn search<'a>(
+        &'a self,
+        request: &'a WebSearchRequest,
+    ) -> BoxFuture<'a, Result<WebSearchResponse, ErrorData>> {
+        Box::pin(async move {
+            let text = tokio::fs::read_to_string(&self.path).await.map_err(|e| {
+                ErrorData::internal_error(
+                    format!("Could not read {}: {e}", self.path.display()),
+                    None,
+                )
+            })?;
+            let mut fixtures: HashMap<String, FixtureEntry> =
+                serde_json::from_str(&text).map_err(|e| {
+                    ErrorData::internal_error(
+                        format!("Invalid search fixture {}: {e}", self.path.display()),
+                        None,
+                    )
+                })?;
+            let query = normalize_query(&request.query);
+            let entry = fixtures
+                .iter()
+                .find(|(key, _)| normalize_query(key) == query)
+                .map(|(key, _)| key.clone())
+                .and_then(|key| fixtures.remove(&key))
+                .or_else(|| fixtures.remove("*"));
+            let (answer, mut results) = entry
+                .map(|entry| (entry.answer, entry.results))
+                .unwrap_or_default();
+            results.truncate(request.max_results);
+            Ok(WebSearchResponse {
+                provider: self.name(),
+                query: request.query.clone(),
+                answer,
+                results,
+            })
+        })
+    }
+}
+
+fn array<'a>(json: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    json.get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn string(json: &Value, key: &str) -> Option<String> {
+    json.get(key)
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn fixture_provider() -> Box<dyn WebSearchProvider> {
+        let path = PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/web_search.json"
+        ));
+        let http = HttpClient::new(&Config::default()).unwrap();
+        provider(
+            &WebSearchConfig::Fixture { path },
+            &http,
+            &Secrets::default(),
+        )
+        .unwrap()
+    }
+
+    fn request(query: &str, max_results: usize) -> WebSearchRequest {
+        WebSearchRequest {
+            query: query.to_string(),
+            advanced: false,
+            max_results,
+        }
+    }
+
+    #[tokio::test]
+    async fn fixture_results_are_normalized() {
+        let response = fixture_provider()
+            .search(&request("roblox datastore   RETRY", 2))
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            serde_json::json!({
+                "provider": "fixture",
+                "query": "roblox datastore   RETRY",
+                "answer": "Wrap SetAsync in pcall and retry with a backoff.",
+                "results": [
+                    {
+                        "title": "Data stores | Documentation",
+                        "url": "https://create.roblox.com/docs/cloud-services/data-stores",
+                        "snippet": "Data stores let you save data between sessions.",
+                        "content": "Data stores let you save data between sessions. Calls can fail, so wrap them in pcall.",
+                        "score": 0.92,
+                        "published": "2024-05-01"
+                    },
+                    {
+                        "title": "Retrying failed SetAsync calls",
+                        "url": "https://devforum.roblox.com/t/retrying-failed-setasync-calls/1",
+                        "snippet": ""
+                    }
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn fixture_falls_back_to_star_entry() {
+        let response = fixture_provider()
+            .search(&request("how do I make a sword", 5))
+            .await
+            .unwrap();
+        assert_eq!(response.query, "how do I make a sword");
+        assert_eq!(response.answer, None);
+        assert_eq!(response.results.len(), 1);
+        assert_eq!(response.results[0].url, "https://create.roblox.com/docs");
+    }
+}